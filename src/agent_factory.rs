@@ -0,0 +1,354 @@
+use anyhow::Error;
+use async_trait::async_trait;
+use rig::agent::{Agent, AgentBuilder};
+use futures::StreamExt;
+use rig::OneOrMany;
+use rig::completion::{Chat, CompletionError, CompletionModel, Message};
+use rig::message::{AssistantContent, UserContent};
+use rig::streaming::StreamingCompletion;
+use rig::tool::{Tool, ToolDyn};
+
+use crate::mcp_servers::McpServer;
+use crate::retry::RetryingModel;
+use crate::config::Config;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use crate::reasoning::{split_reasoning, strip_reasoning, Segment, ThinkSplitter};
+use crate::usage::{MeteredModel, ReportedUsage, UsageTracker};
+use crate::tool_protocol::{probe_tool_calling, ToolCalling, ToolProtocol, ToolProtocolModel};
+use crate::file_tools::{FileReader, FileWriter, FileEditor, CreateDirectory, ListFiles, CodeEditor, CodeInserter, CodebaseAnalyzer, JobExecutor};
+use crate::search_tools::{CodeSearcher, FileFinder};
+use crate::navigation_tools::{DefinitionFinder, ReferenceFinder};
+use crate::dependency_graph::DependencyGrapher;
+use crate::codebase_stats::CodebaseStatsReporter;
+
+/// A model backend agents can be built on. Adding a provider means
+/// implementing this trait in its own module; tools are shared.
+pub trait ModelProvider {
+    type Model: CompletionModel + 'static;
+
+    /// Provider name as used in the config file
+    const NAME: &'static str;
+
+    /// Environment variable holding the API key when the config has none
+    const API_KEY_VAR: Option<&'static str> = None;
+
+    /// Model name after applying the provider's defaults
+    fn model_name(&self) -> String;
+
+    /// The completion model; fails if it cannot be set up, e.g. without an API key
+    fn model(&self) -> Result<Self::Model, Error>;
+
+    /// The configured API key, else the one in `API_KEY_VAR`
+    fn api_key(&self, configured: Option<&String>) -> Result<String, Error> {
+        if let Some(key) = configured.filter(|key| !key.is_empty()) {
+            return Ok(key.clone());
+        }
+        let var = Self::API_KEY_VAR.ok_or_else(|| anyhow::anyhow!("{} takes no API key", Self::NAME))?;
+        std::env::var(var).ok()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| anyhow::anyhow!("{} needs an API key: set providers.{}.api_key or {}", Self::NAME, Self::NAME, var))
+    }
+
+    fn temperature(&self) -> f64 {
+        0.2
+    }
+
+    fn max_tokens(&self) -> u64 {
+        3000
+    }
+
+    /// Whether the provider accepts plain-text context documents; if not,
+    /// they are appended to the preamble instead
+    fn supports_documents(&self) -> bool {
+        true
+    }
+
+    /// Whether tools are passed through the API's function calling or described in the prompt
+    fn tool_calling(&self) -> ToolCalling {
+        ToolCalling::Native
+    }
+
+    /// Token counts the server reported for a completion; missing counts are estimated
+    fn reported_usage(_response: &<Self::Model as CompletionModel>::Response) -> ReportedUsage {
+        ReportedUsage::default()
+    }
+
+    /// Token counts the server reported at the end of a stream
+    fn reported_streaming_usage(_response: &<Self::Model as CompletionModel>::StreamingResponse) -> ReportedUsage {
+        ReportedUsage::default()
+    }
+}
+
+/// Names of all tools in the registry, in registration order
+pub const TOOL_NAMES: &[&str] = &[
+    FileReader::NAME,
+    FileWriter::NAME,
+    FileEditor::NAME,
+    CodeEditor::NAME,
+    CodeInserter::NAME,
+    CreateDirectory::NAME,
+    ListFiles::NAME,
+    CodeSearcher::NAME,
+    FileFinder::NAME,
+    DefinitionFinder::NAME,
+    ReferenceFinder::NAME,
+    DependencyGrapher::NAME,
+    CodebaseAnalyzer::NAME,
+    CodebaseStatsReporter::NAME,
+    JobExecutor::NAME,
+];
+
+/// Something tools are registered with: an agent being built, or the list
+/// of tools the MCP tool host serves
+pub trait ToolSink: Sized {
+    fn add<T: Tool + 'static>(self, tool: T) -> Self;
+}
+
+impl<M: CompletionModel> ToolSink for AgentBuilder<M> {
+    fn add<T: Tool + 'static>(self, tool: T) -> Self {
+        self.tool(tool)
+    }
+}
+
+impl ToolSink for Vec<Box<dyn ToolDyn>> {
+    fn add<T: Tool + 'static>(mut self, tool: T) -> Self {
+        self.push(Box::new(tool));
+        self
+    }
+}
+
+/// Add `tool` unless the configuration leaves it out
+fn add_tool<S: ToolSink, T: Tool + 'static>(sink: S, tool: T, enabled_tools: Option<&[String]>) -> S {
+    match enabled_tools {
+        Some(enabled) if !enabled.iter().any(|name| name == T::NAME) => sink,
+        _ => sink.add(tool),
+    }
+}
+
+/// The single tool registry: every agent gets these tools, or the enabled
+/// subset. Searches, symbol lookups and dependency graphs are confined to `workspace`.
+pub fn register_tools<S: ToolSink>(builder: S, enabled_tools: Option<&[String]>, workspace: &Path) -> S {
+    // Add file manipulation tools
+    let builder = add_tool(builder, FileReader, enabled_tools);
+    let builder = add_tool(builder, FileWriter, enabled_tools);
+    let builder = add_tool(builder, FileEditor, enabled_tools);
+    let builder = add_tool(builder, CodeEditor, enabled_tools);
+    let builder = add_tool(builder, CodeInserter, enabled_tools);
+    let builder = add_tool(builder, CreateDirectory, enabled_tools);
+    let builder = add_tool(builder, ListFiles, enabled_tools);
+    let builder = add_tool(builder, CodeSearcher { root: workspace.to_path_buf() }, enabled_tools);
+    let builder = add_tool(builder, FileFinder { root: workspace.to_path_buf() }, enabled_tools);
+    let builder = add_tool(builder, DefinitionFinder { root: workspace.to_path_buf() }, enabled_tools);
+    let builder = add_tool(builder, ReferenceFinder { root: workspace.to_path_buf() }, enabled_tools);
+    let builder = add_tool(builder, DependencyGrapher { root: workspace.to_path_buf() }, enabled_tools);
+    let builder = add_tool(builder, CodebaseAnalyzer, enabled_tools);
+    let builder = add_tool(builder, CodebaseStatsReporter, enabled_tools);
+    add_tool(builder, JobExecutor, enabled_tools)
+}
+
+/// Label used when logging requests to a provider
+pub fn provider_label<P: ModelProvider>(provider: &P) -> String {
+    format!("{} ({})", P::NAME, provider.model_name())
+}
+
+/// Probed tool protocols per provider and model, so rebuilt agents are not probed again
+static PROBED: LazyLock<Mutex<HashMap<(&'static str, String), ToolProtocol>>> = LazyLock::new(Mutex::default);
+
+/// Probe the provider's model once per process. Inconclusive probes are
+/// not remembered.
+async fn probe_once<P: ModelProvider>(provider: &P, model: &P::Model, label: &str) -> Result<Option<ToolProtocol>, CompletionError> {
+    let key = (P::NAME, provider.model_name());
+    if let Some(protocol) = PROBED.lock().unwrap().get(&key) {
+        return Ok(Some(*protocol));
+    }
+    let protocol = probe_tool_calling(model, label).await?;
+    if let Some(protocol) = protocol {
+        PROBED.lock().unwrap().insert(key, protocol);
+    }
+    Ok(protocol)
+}
+
+/// Make sure the provider's model answers, e.g. before switching to it
+pub async fn check_model<P: ModelProvider>(provider: &P) -> Result<(), Error> {
+    let label = provider_label(provider);
+    let model = provider.model().map_err(|e| anyhow::anyhow!("{} cannot be used: {}", label, e))?;
+    probe_once(provider, &model, &label).await
+        .map(|_| ())
+        .map_err(|e| anyhow::anyhow!("{} cannot be used: {}", label, e))
+}
+
+/// Build an agent for any provider with the shared tools, context documents
+/// and MCP tools. Completion requests are retried according to `config.retry` and
+/// their token usage is recorded in `usage` under the provider's label.
+/// With `tool_calling = "auto"` the model is probed for native tool calls first.
+/// Fails if the provider's model cannot be set up.
+pub async fn build_agent<P: ModelProvider>(
+    provider: &P,
+    prompt: &str,
+    config: &Config,
+    usage: &UsageTracker,
+    mcp_servers: Vec<McpServer>,
+    context_docs: Vec<String>,
+) -> Result<Agent<MeteredModel<RetryingModel<ToolProtocolModel<P::Model>>>>, Error> {
+    let preamble = if provider.supports_documents() {
+        prompt.to_string()
+    } else {
+        context_docs.iter().enumerate().fold(prompt.to_string(), |preamble, (index, doc)| {
+            format!("{}\n\n<file id: context_{}>\n{}\n</file>", preamble, index, doc)
+        })
+    };
+
+    let label = provider_label(provider);
+    let model = provider.model().map_err(|e| anyhow::anyhow!("{} cannot be used: {}", label, e))?;
+    let protocol = match provider.tool_calling() {
+        ToolCalling::Native => ToolProtocol::Native,
+        ToolCalling::Prompt => ToolProtocol::Prompt,
+        ToolCalling::Auto => match probe_once(provider, &model, &label).await {
+            Ok(protocol) => protocol.unwrap_or(ToolProtocol::Native),
+            // The server may come up later; the first turn will tell
+            Err(e) => {
+                println!("⚠️  {} | Could not probe tool calling: {} | Assuming native tool calls", label, e);
+                ToolProtocol::Native
+            }
+        },
+    };
+
+    let model = MeteredModel::new(
+        RetryingModel::new(ToolProtocolModel::new(model, protocol), config.retry.clone(), label.clone()),
+        usage.clone(),
+        label,
+        provider.model_name(),
+        P::reported_usage,
+        P::reported_streaming_usage,
+    );
+    let mut builder = register_tools(AgentBuilder::new(model), config.enabled_tools.as_deref(), &config.workspace)
+        .preamble(&preamble)
+        .temperature(provider.temperature())
+        .max_tokens(provider.max_tokens());
+
+    // Add context documents
+    if provider.supports_documents() {
+        for context_doc in context_docs {
+            builder = builder.context(&context_doc);
+        }
+    }
+
+    // Add the tools of every connected MCP server, prefixed with its name
+    let builder = mcp_servers.iter()
+        .flat_map(McpServer::agent_tools)
+        .fold(builder, |builder, tool| builder.tool(tool));
+
+    Ok(builder.build())
+}
+
+/// Tool-calling rounds allowed per streamed turn before giving up
+pub const MAX_TOOL_TURNS: usize = 20;
+
+/// The model kept calling tools for the whole round limit
+#[derive(Debug, thiserror::Error)]
+#[error("Stopped after {0} tool-calling rounds without a final answer")]
+pub struct MaxTurnsExceeded(pub usize);
+
+/// Something the agent did while answering, reported as it happens
+pub enum AgentEvent<'a> {
+    /// A chunk of assistant text
+    Text(&'a str),
+    /// A chunk of the model's reasoning (inside `<think>` tags)
+    Reasoning(&'a str),
+    ToolCall { name: &'a str, arguments: &'a serde_json::Value },
+    ToolResult { name: &'a str, output: &'a str },
+    ToolError { name: &'a str, error: &'a str },
+}
+
+/// Callback receiving `AgentEvent`s while a turn is streamed
+pub type EventSink<'a> = &'a (dyn Fn(AgentEvent<'_>) + Send + Sync);
+
+/// Provider-independent interface to a built agent
+#[async_trait]
+pub trait ChatAgent: Send + Sync {
+    /// The answer may contain the model's `<think>` block; see `reasoning::split_reasoning`
+    async fn chat(&self, prompt: &str, history: Vec<Message>) -> Result<String, Error>;
+
+    /// Like `chat`, but streams text and tool activity to `on_event` as it
+    /// arrives, for at most `max_turns` tool-calling rounds. Failed tool calls
+    /// are reported back to the model as results. Reasoning is reported
+    /// separately and left out of the answer and history.
+    async fn stream_chat(&self, prompt: &str, history: Vec<Message>, max_turns: usize, on_event: EventSink<'_>) -> Result<String, Error>;
+}
+
+#[async_trait]
+impl<M: CompletionModel + 'static> ChatAgent for Agent<M> {
+    async fn chat(&self, prompt: &str, history: Vec<Message>) -> Result<String, Error> {
+        Chat::chat(self, prompt, history).await.map_err(|e| anyhow::anyhow!(e))
+    }
+
+    async fn stream_chat(&self, prompt: &str, history: Vec<Message>, max_turns: usize, on_event: EventSink<'_>) -> Result<String, Error> {
+        let mut history = history;
+        let mut message = Message::user(prompt);
+
+        for _ in 0..max_turns {
+            let mut stream = self.stream_completion(message.clone(), history.clone()).await?
+                .stream()
+                .await?;
+
+            let mut text = String::new();
+            let mut tool_calls = Vec::new();
+            let mut splitter = ThinkSplitter::default();
+            let emit = |segments: Vec<Segment>, text: &mut String| {
+                for segment in segments {
+                    match segment {
+                        Segment::Reasoning(reasoning) => on_event(AgentEvent::Reasoning(&reasoning)),
+                        Segment::Answer(answer) => {
+                            on_event(AgentEvent::Text(&answer));
+                            text.push_str(&answer);
+                        }
+                    }
+                }
+            };
+            while let Some(content) = stream.next().await {
+                match content? {
+                    AssistantContent::Text(chunk) => emit(splitter.push(&chunk.text), &mut text),
+                    AssistantContent::ToolCall(tool_call) => {
+                        on_event(AgentEvent::ToolCall { name: &tool_call.function.name, arguments: &tool_call.function.arguments });
+                        tool_calls.push(tool_call);
+                    }
+                }
+            }
+
+            emit(splitter.finish(), &mut text);
+
+            history.push(message);
+            history.push(Message::Assistant { content: strip_reasoning(&stream.choice) });
+
+            if tool_calls.is_empty() {
+                // Catches reasoning whose opening tag was part of the prompt template
+                return Ok(split_reasoning(&text).1);
+            }
+
+            let mut results = Vec::new();
+            for tool_call in tool_calls {
+                let name = &tool_call.function.name;
+                let output = match self.tools.call(name, tool_call.function.arguments.to_string()).await {
+                    Ok(output) => {
+                        on_event(AgentEvent::ToolResult { name, output: &output });
+                        output
+                    }
+                    Err(e) => {
+                        let error = e.to_string();
+                        on_event(AgentEvent::ToolError { name, error: &error });
+                        format!("Error: {}", error)
+                    }
+                };
+                results.push(UserContent::tool_result(tool_call.id.clone(), OneOrMany::one(output.into())));
+            }
+
+            message = Message::User {
+                content: OneOrMany::many(results).map_err(|e| anyhow::anyhow!(e))?,
+            };
+        }
+
+        Err(MaxTurnsExceeded(max_turns).into())
+    }
+}
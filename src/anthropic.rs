@@ -0,0 +1,62 @@
+use anyhow::Error;
+use rig::providers::anthropic::{self, ClientBuilder};
+
+use crate::agent_factory::ModelProvider;
+use crate::config::ProviderConfig;
+use crate::tool_protocol::ToolCalling;
+use crate::usage::ReportedUsage;
+
+/// Anthropic Claude, configured from ANTHROPIC_API_KEY and ANTHROPIC_MODEL_NAME
+pub struct AnthropicProvider {
+    pub settings: ProviderConfig,
+}
+
+impl ModelProvider for AnthropicProvider {
+    type Model = anthropic::completion::CompletionModel;
+
+    const NAME: &'static str = "anthropic";
+    const API_KEY_VAR: Option<&'static str> = Some("ANTHROPIC_API_KEY");
+
+    fn model_name(&self) -> String {
+        self.settings.model.clone()
+            .unwrap_or_else(|| std::env::var("ANTHROPIC_MODEL_NAME").unwrap_or_else(|_| anthropic::CLAUDE_3_7_SONNET.to_string()))
+    }
+
+    fn model(&self) -> Result<Self::Model, Error> {
+        let api_key = self.api_key(self.settings.api_key.as_ref())?;
+        let mut builder = ClientBuilder::new(&api_key);
+        if let Some(api_base) = &self.settings.api_base {
+            builder = builder.base_url(api_base);
+        }
+        Ok(anthropic::completion::CompletionModel::new(builder.build(), &self.model_name()))
+    }
+
+    fn temperature(&self) -> f64 {
+        self.settings.temperature.unwrap_or(0.2)
+    }
+
+    fn max_tokens(&self) -> u64 {
+        self.settings.max_tokens.unwrap_or(4096)
+    }
+
+    // rig only sends PDF documents to Anthropic
+    fn supports_documents(&self) -> bool {
+        false
+    }
+
+    fn tool_calling(&self) -> ToolCalling {
+        self.settings.tool_calling.unwrap_or(ToolCalling::Native)
+    }
+
+    fn reported_usage(response: &anthropic::completion::CompletionResponse) -> ReportedUsage {
+        ReportedUsage::new(response.usage.input_tokens, response.usage.output_tokens)
+    }
+
+    fn reported_streaming_usage(response: &anthropic::streaming::StreamingCompletionResponse) -> ReportedUsage {
+        ReportedUsage {
+            input: response.usage.input_tokens.map(|tokens| tokens as u64),
+            output: Some(response.usage.output_tokens as u64),
+            total: None,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Standalone OpenAI-compatible stub server. Point the local provider at it:
+//!
+//! ```sh
+//! cargo run --bin llmo-stub -- --fixtures fixtures/stub
+//! OPENAI_API_KEY=stub cargo run -- --provider local --config llmo.toml  # api_base = "http://127.0.0.1:18080/v1"
+//! ```
+
+use anyhow::Error;
+use clap::Parser;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use llmo::stub_server::{load_fixtures, StubServer};
+
+/// OpenAI-compatible stub server answering from fixture files
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Fixture file or directory of fixture files (default: echo every prompt)
+    #[arg(long)]
+    fixtures: Option<PathBuf>,
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:18080")]
+    addr: SocketAddr,
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    let args = Args::parse();
+
+    let fixtures = match &args.fixtures {
+        Some(path) => load_fixtures(path).map_err(|e| anyhow::anyhow!(e))?,
+        None => Vec::new(),
+    };
+    println!("📂 Loaded {} fixtures", fixtures.len());
+
+    let mut server = StubServer::spawn(args.addr, fixtures).await?;
+    println!("🚀 Stub server listening on {} (api_base = \"{}\")", server.addr, server.base_url());
+
+    tokio::select! {
+        result = server.wait() => result?,
+        _ = tokio::signal::ctrl_c() => {}
+    }
+    println!("👋 Answered {} requests", server.request_count());
+    Ok(())
+}
@@ -0,0 +1,386 @@
+use rig::tool::Tool;
+use rig::completion::request::ToolDefinition;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::BTreeMap;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::file_tools::{is_binary, is_code_file, is_ignored, FileToolError};
+
+/// Number of files listed in the "largest files" section
+const LARGEST_FILES: usize = 10;
+/// Skipped files listed individually in the text report
+const SKIPPED_SHOWN: usize = 20;
+
+/// Build system manifests, matched by file name
+const BUILD_FILES: &[(&str, &str)] = &[
+    ("Cargo.toml", "Cargo"),
+    ("pyproject.toml", "Python (pyproject)"),
+    ("setup.py", "Python (setuptools)"),
+    ("requirements.txt", "Python (pip)"),
+    ("package.json", "npm"),
+    ("Makefile", "Make"),
+    ("CMakeLists.txt", "CMake"),
+    ("go.mod", "Go modules"),
+    ("pom.xml", "Maven"),
+    ("build.gradle", "Gradle"),
+    ("build.gradle.kts", "Gradle"),
+];
+
+/// Human readable language name for a file, by extension or by name
+fn language_name(path: &Path) -> &'static str {
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or("").to_lowercase();
+    match file_name.as_str() {
+        "dockerfile" => return "Dockerfile",
+        "makefile" => return "Makefile",
+        "cmakelists.txt" => return "CMake",
+        _ => {}
+    }
+
+    let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "rs" => "Rust",
+        "py" => "Python",
+        "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+        "ts" | "tsx" => "TypeScript",
+        "java" => "Java",
+        "c" | "h" => "C",
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => "C++",
+        "go" => "Go",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "cs" => "C#",
+        "swift" => "Swift",
+        "kt" => "Kotlin",
+        "scala" => "Scala",
+        "r" => "R",
+        "m" | "mm" => "Objective-C",
+        "sql" => "SQL",
+        "sh" | "bash" => "Shell",
+        "yaml" | "yml" => "YAML",
+        "json" => "JSON",
+        "xml" => "XML",
+        "toml" => "TOML",
+        "ini" => "INI",
+        "md" => "Markdown",
+        "txt" => "Text",
+        "cmake" => "CMake",
+        "html" | "htm" => "HTML",
+        "css" | "scss" => "CSS",
+        _ => "Other",
+    }
+}
+
+/// Returns true if any line, ignoring indentation, starts with one of `prefixes`
+fn has_line_starting(content: &str, prefixes: &[&str]) -> bool {
+    content.lines().any(|line| {
+        let line = line.trim_start();
+        prefixes.iter().any(|prefix| line.starts_with(prefix))
+    })
+}
+
+/// Returns a description if the file looks like a program entry point
+fn entry_point_kind(path: &Path, content: &str) -> Option<&'static str> {
+    let file_name = path.file_name()?.to_str()?;
+    let in_bin_dir = path.parent()
+        .and_then(|parent| parent.file_name())
+        .is_some_and(|name| name == "bin");
+
+    match path.extension()?.to_str()? {
+        "rs" if has_line_starting(content, &["fn main(", "async fn main(", "pub fn main("]) => Some(if in_bin_dir { "Rust binary" } else { "Rust main" }),
+        "rs" if file_name == "lib.rs" => Some("Rust library root"),
+        "py" if has_line_starting(content, &["if __name__ == \"__main__\"", "if __name__ == '__main__'"]) => Some("Python script"),
+        "py" if file_name == "__main__.py" => Some("Python package main"),
+        "go" if has_line_starting(content, &["package main"]) && has_line_starting(content, &["func main("]) => Some("Go main"),
+        "c" | "cpp" | "cc" | "cxx" if has_line_starting(content, &["int main("]) => Some("C/C++ main"),
+        "java" if has_line_starting(content, &["public static void main("]) => Some("Java main"),
+        "js" | "ts" | "mjs" if matches!(path.file_stem()?.to_str()?, "index" | "main" | "server" | "app")
+            && path.components().count() <= 3 => Some("JavaScript entry"),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LanguageStats {
+    pub files: usize,
+    pub lines: usize,
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileSize {
+    pub path: String,
+    pub bytes: u64,
+    pub lines: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildSystem {
+    pub name: String,
+    pub manifest: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EntryPoint {
+    pub path: String,
+    pub kind: String,
+}
+
+/// Language, size and project layout statistics for a directory tree
+#[derive(Debug, Clone, Serialize)]
+pub struct CodebaseStats {
+    pub root: String,
+    pub total_files: usize,
+    pub total_lines: usize,
+    pub total_bytes: u64,
+    pub languages: BTreeMap<String, LanguageStats>,
+    pub largest_files: Vec<FileSize>,
+    /// Code files of `max_file_size` bytes or more
+    pub too_large: Vec<FileSize>,
+    pub max_file_size: usize,
+    pub skipped: Vec<SkippedFile>,
+    pub build_systems: Vec<BuildSystem>,
+    pub entry_points: Vec<EntryPoint>,
+}
+
+impl CodebaseStats {
+    /// Scan `root`, listing code files larger than `max_file_size` as too large
+    /// the same way `analyze_codebase` leaves them out of its code section
+    pub fn collect(root: &Path, max_file_size: usize, max_depth: usize) -> Result<Self, FileToolError> {
+        if !root.is_dir() {
+            return Err(FileToolError::InvalidPath(format!("{} is not a directory", root.display())));
+        }
+
+        let mut stats = CodebaseStats {
+            root: root.display().to_string(),
+            total_files: 0,
+            total_lines: 0,
+            total_bytes: 0,
+            languages: BTreeMap::new(),
+            largest_files: Vec::new(),
+            too_large: Vec::new(),
+            max_file_size,
+            skipped: Vec::new(),
+            build_systems: Vec::new(),
+            entry_points: Vec::new(),
+        };
+        let mut sizes = Vec::new();
+
+        let mut walker = WalkDir::new(root).max_depth(max_depth).into_iter();
+        while let Some(entry) = walker.next() {
+            let Ok(entry) = entry else { continue };
+            let relative = entry.path().strip_prefix(root)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            if is_ignored(&entry) {
+                stats.skipped.push(SkippedFile { path: format!("{}/", relative), reason: "ignored directory".to_string() });
+                walker.skip_current_dir();
+                continue;
+            }
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let path = entry.path();
+            if let Some(file_name) = path.file_name().and_then(|name| name.to_str())
+                && let Some((_, name)) = BUILD_FILES.iter().find(|(manifest, _)| *manifest == file_name) {
+                stats.build_systems.push(BuildSystem { name: name.to_string(), manifest: relative.clone() });
+            }
+
+            let Ok(bytes) = std::fs::read(path) else {
+                stats.skipped.push(SkippedFile { path: relative, reason: "unreadable".to_string() });
+                continue;
+            };
+            if is_binary(&bytes) {
+                stats.skipped.push(SkippedFile { path: relative, reason: "binary".to_string() });
+                continue;
+            }
+            let Ok(content) = String::from_utf8(bytes) else {
+                stats.skipped.push(SkippedFile { path: relative, reason: "not UTF-8".to_string() });
+                continue;
+            };
+
+            let lines = content.lines().count();
+            let size = content.len() as u64;
+            let language = stats.languages.entry(language_name(path).to_string()).or_default();
+            language.files += 1;
+            language.lines += lines;
+            language.bytes += size;
+            stats.total_files += 1;
+            stats.total_lines += lines;
+            stats.total_bytes += size;
+
+            if content.len() >= max_file_size && is_code_file(path) {
+                stats.too_large.push(FileSize { path: relative.clone(), bytes: size, lines });
+            }
+            if let Some(kind) = entry_point_kind(Path::new(&relative), &content) {
+                stats.entry_points.push(EntryPoint { path: relative.clone(), kind: kind.to_string() });
+            }
+            sizes.push(FileSize { path: relative, bytes: size, lines });
+        }
+
+        sizes.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+        sizes.truncate(LARGEST_FILES);
+        stats.largest_files = sizes;
+        stats.build_systems.sort_by(|a, b| a.manifest.cmp(&b.manifest));
+        stats.entry_points.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(stats)
+    }
+
+    /// Plain text report, also embedded in `analyze_codebase` output
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} files, {} lines, {} bytes\n", self.total_files, self.total_lines, self.total_bytes);
+
+        let mut languages: Vec<(&String, &LanguageStats)> = self.languages.iter().collect();
+        languages.sort_by(|a, b| b.1.lines.cmp(&a.1.lines).then_with(|| a.0.cmp(b.0)));
+        text.push_str("\nLanguages:\n");
+        for (name, language) in languages {
+            text.push_str(&format!("  {:<12} {:>5} files {:>8} lines\n", name, language.files, language.lines));
+        }
+
+        if !self.build_systems.is_empty() {
+            text.push_str("\nBuild systems:\n");
+            for build in &self.build_systems {
+                text.push_str(&format!("  {} ({})\n", build.name, build.manifest));
+            }
+        }
+
+        if !self.entry_points.is_empty() {
+            text.push_str("\nLikely entry points:\n");
+            for entry in &self.entry_points {
+                text.push_str(&format!("  {} [{}]\n", entry.path, entry.kind));
+            }
+        }
+
+        if !self.largest_files.is_empty() {
+            text.push_str("\nLargest files:\n");
+            for file in &self.largest_files {
+                text.push_str(&format!("  {} ({} bytes, {} lines)\n", file.path, file.bytes, file.lines));
+            }
+        }
+
+        if !self.too_large.is_empty() {
+            text.push_str(&format!("\nToo large to include (over {} bytes), read with read_file offset and limit:\n", self.max_file_size));
+            for file in &self.too_large {
+                text.push_str(&format!("  {} ({} bytes, {} lines)\n", file.path, file.bytes, file.lines));
+            }
+        }
+
+        if !self.skipped.is_empty() {
+            text.push_str(&format!("\nSkipped ({}):\n", self.skipped.len()));
+            for skipped in self.skipped.iter().take(SKIPPED_SHOWN) {
+                text.push_str(&format!("  {}: {}\n", skipped.path, skipped.reason));
+            }
+            if self.skipped.len() > SKIPPED_SHOWN {
+                text.push_str(&format!("  ... and {} more\n", self.skipped.len() - SKIPPED_SHOWN));
+            }
+        }
+
+        text
+    }
+
+    pub fn to_json(&self) -> Result<String, FileToolError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| FileToolError::Io(std::io::Error::other(e)))
+    }
+}
+
+// Codebase Statistics Tool
+#[derive(Deserialize, JsonSchema)]
+pub struct CodebaseStatsArgs {
+    /// Path to the codebase directory
+    pub path: String,
+    /// Output format: "text" or "json" (default: text)
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Files at least this large are reported as skipped (default: 10000 bytes)
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: usize,
+    /// Maximum directory depth to traverse (default: 10)
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+}
+
+fn default_format() -> String { "text".to_string() }
+fn default_max_file_size() -> usize { 10000 }
+fn default_max_depth() -> usize { 10 }
+
+#[derive(Debug, Clone)]
+pub struct CodebaseStatsReporter;
+
+impl Tool for CodebaseStatsReporter {
+    const NAME: &'static str = "codebase_stats";
+    type Error = FileToolError;
+    type Args = CodebaseStatsArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Summarize a codebase: file and line counts per language, largest files, skipped files and why (ignored, binary, too large), detected build systems and likely entry points. Returns text or JSON.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path to the codebase directory"
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["text", "json"],
+                        "description": "Output format (default: text)",
+                        "default": "text"
+                    },
+                    "max_file_size": {
+                        "type": "integer",
+                        "description": "Files at least this many bytes are reported as too large (default: 10000)",
+                        "default": 10000,
+                        "minimum": 1000
+                    },
+                    "max_depth": {
+                        "type": "integer",
+                        "description": "Maximum directory depth to traverse (default: 10)",
+                        "default": 10,
+                        "minimum": 1
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        println!("🔧 Tool: codebase_stats | Path: {} | Format: {}", args.path, args.format);
+
+        let path = Path::new(&args.path);
+        if !path.exists() {
+            println!("❌ Tool: codebase_stats | Error: Directory not found");
+            return Err(FileToolError::FileNotFound(args.path));
+        }
+
+        if args.format != "text" && args.format != "json" {
+            println!("❌ Tool: codebase_stats | Error: Unknown format");
+            return Err(FileToolError::InvalidArgument(format!("Unknown format '{}', expected text or json", args.format)));
+        }
+
+        // Walking and reading a large tree blocks, so keep it off the async runtime
+        let root = path.to_path_buf();
+        let stats = tokio::task::spawn_blocking(move || CodebaseStats::collect(&root, args.max_file_size, args.max_depth))
+            .await
+            .map_err(|e| FileToolError::Io(std::io::Error::other(e)))??;
+        let rendered = if args.format == "json" { stats.to_json()? } else { stats.to_text() };
+
+        println!("✅ Tool: codebase_stats | Success: {} files in {} languages", stats.total_files, stats.languages.len());
+        Ok(rendered)
+    }
+}
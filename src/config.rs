@@ -0,0 +1,666 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+use crate::agent_factory::{ModelProvider, TOOL_NAMES};
+use crate::mcp_servers::NAMESPACE_SEPARATOR;
+use crate::mock::{MockProvider, MockScript};
+use crate::model_selector::ModelType;
+use crate::retry::RetryPolicy;
+use crate::tool_protocol::ToolCalling;
+use crate::reasoning::ThinkingConfig;
+use crate::usage::{Budget, ModelPrice};
+
+/// Config file used when no `--config` flag is given (optional)
+pub const DEFAULT_CONFIG_FILE: &str = "llmo.toml";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Failed to read config file {path}: {source}")]
+    Read { path: String, source: std::io::Error },
+    #[error("Invalid config file {path}: {message}")]
+    Parse { path: String, message: String },
+    #[error("Environment variable {name} is not set (referenced as ${{{name}}} in the config file)")]
+    MissingEnvVar { name: String },
+    #[error("Unknown profile '{name}'. Available profiles: {available}")]
+    UnknownProfile { name: String, available: String },
+    #[error("Invalid configuration: {0}")]
+    Invalid(String),
+}
+
+/// Model and sampling settings for one provider
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProviderConfig {
+    /// Model name (e.g. "Qwen/Qwen3-32B", "gemini-1.5-pro", "qwen3:32b")
+    pub model: Option<String>,
+    /// Base URL of the API, for OpenAI-compatible servers, Ollama or an Anthropic proxy
+    pub api_base: Option<String>,
+    /// API key; falls back to the provider's usual environment variable
+    pub api_key: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u64>,
+    /// Response script for the mock provider (JSON)
+    pub script: Option<PathBuf>,
+    /// "native", "prompt" (tools described in the system prompt) or "auto" (probe at startup)
+    pub tool_calling: Option<ToolCalling>,
+}
+
+/// Provider and tools for agents with a given role or task label
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RouteConfig {
+    pub provider: String,
+    /// Model to use instead of the provider's configured one
+    pub model: Option<String>,
+    /// Fallback providers (default: the global fallback list)
+    pub fallback: Option<Vec<String>>,
+    /// Tools for this route (default: the global enabled_tools)
+    pub enabled_tools: Option<Vec<String>>,
+}
+
+/// How to reach an MCP server: `url` for SSE, or `command` (with `args` and
+/// `env`) to launch it as a child process speaking over stdio
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct McpServerConfig {
+    /// SSE endpoint, e.g. "http://localhost:3000/sse"
+    pub url: Option<String>,
+    /// Program to launch, e.g. "npx" or "uvx"
+    pub command: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables for the launched server
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Seconds between pings checking the server is alive; 0 turns them off (default: 30)
+    pub health_check_secs: Option<u64>,
+    /// Attempts and backoff when reconnecting to a server that stopped responding
+    #[serde(default)]
+    pub reconnect: RetryPolicy,
+    /// Resources to attach as context documents at startup: URIs or glob
+    /// patterns such as "file:///docs/*.md"
+    #[serde(default)]
+    pub resources: Vec<String>,
+    /// Subscribe to attached resources and refresh the context when they change
+    #[serde(default)]
+    pub subscribe: bool,
+    /// Seconds between re-reads of subscribed resources on SSE servers, which
+    /// cannot send change notifications (default: 30)
+    pub poll_secs: Option<u64>,
+    /// Tools to expose to the agent, as names or glob patterns (default: all)
+    #[serde(default)]
+    pub tools: Vec<String>,
+    /// Tools to hide from the agent, as names or glob patterns; wins over `tools`
+    #[serde(default)]
+    pub exclude_tools: Vec<String>,
+    /// Seconds a tool call may take (default: 60)
+    pub tool_timeout_secs: Option<u64>,
+    /// Timeouts for single tools, in seconds, overriding `tool_timeout_secs`
+    #[serde(default)]
+    pub tool_timeouts: BTreeMap<String, u64>,
+    /// Tools the user has to approve before each call, as names or glob patterns.
+    /// Calls are declined when stdin is not a terminal.
+    #[serde(default)]
+    pub confirm_tools: Vec<String>,
+}
+
+/// Whether `name` matches one of the glob `patterns`
+fn matches_any(patterns: &[String], name: &str) -> bool {
+    patterns.iter().any(|pattern| glob::Pattern::new(pattern).is_ok_and(|pattern| pattern.matches(name)))
+}
+
+impl McpServerConfig {
+    pub fn health_check_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.health_check_secs.unwrap_or(30))
+    }
+
+    pub fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.poll_secs.unwrap_or(30))
+    }
+
+    /// Whether the server's tool `name` is given to the agent
+    pub fn exposes_tool(&self, name: &str) -> bool {
+        (self.tools.is_empty() || matches_any(&self.tools, name)) && !matches_any(&self.exclude_tools, name)
+    }
+
+    pub fn tool_timeout(&self, name: &str) -> std::time::Duration {
+        let secs = self.tool_timeouts.get(name).copied().or(self.tool_timeout_secs).unwrap_or(60);
+        std::time::Duration::from_secs(secs)
+    }
+
+    /// Whether calls of the tool `name` need the user's approval
+    pub fn requires_confirmation(&self, name: &str) -> bool {
+        matches_any(&self.confirm_tools, name)
+    }
+}
+
+/// Application settings loaded from `llmo.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Active provider: "local", "gemini", "anthropic", "ollama" or "mock" (default: $USE_MODEL, then "local")
+    #[serde(default = "default_provider")]
+    pub provider: String,
+    /// File holding the system prompt
+    #[serde(default = "default_system_prompt_file")]
+    pub system_prompt_file: PathBuf,
+    /// Codebase the agent works on
+    #[serde(default = "default_workspace")]
+    pub workspace: PathBuf,
+    /// Job execution script (default: run.sh inside the workspace)
+    #[serde(default)]
+    pub exec_script: Option<PathBuf>,
+    /// Tools given to the agent (default: all tools)
+    #[serde(default)]
+    pub enabled_tools: Option<Vec<String>>,
+    #[serde(default)]
+    pub providers: BTreeMap<String, ProviderConfig>,
+    /// Providers to try, in order, when the active one keeps failing
+    #[serde(default)]
+    pub fallback: Vec<String>,
+    /// Retry policy for completion requests
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Stream answers and tool activity to the terminal as they arrive
+    #[serde(default = "default_stream")]
+    pub stream: bool,
+    /// Prices per model name, used to compute the cost of a session
+    #[serde(default)]
+    pub prices: BTreeMap<String, ModelPrice>,
+    /// Token and cost limits for a session
+    #[serde(default)]
+    pub budget: Budget,
+    /// Reasoning (`<think>` blocks) of models such as Qwen3
+    #[serde(default)]
+    pub thinking: ThinkingConfig,
+    /// Provider per agent role or task label, e.g. `[routes.search]`
+    #[serde(default)]
+    pub routes: BTreeMap<String, RouteConfig>,
+    /// MCP servers whose tools are given to the agent, e.g. `[mcp_servers.github]`
+    /// (default: $MCP_SERVER_URL as a server named "mcp")
+    #[serde(default)]
+    pub mcp_servers: BTreeMap<String, McpServerConfig>,
+}
+
+fn default_provider() -> String {
+    std::env::var("USE_MODEL").unwrap_or_else(|_| "local".to_string())
+}
+fn default_system_prompt_file() -> PathBuf { PathBuf::from("prompt.txt") }
+fn default_workspace() -> PathBuf { PathBuf::from("test_code") }
+fn default_stream() -> bool { true }
+
+/// Settings given on the command line; these win over the config file
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    pub provider: Option<String>,
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u64>,
+    pub workspace: Option<PathBuf>,
+    pub exec_script: Option<PathBuf>,
+    pub system_prompt_file: Option<PathBuf>,
+    pub stream: Option<bool>,
+}
+
+impl Config {
+    /// Load the config file, merge the selected `[profiles.<name>]` table over
+    /// the top-level settings and resolve relative paths against the file's
+    /// directory. Without an explicit path a missing `llmo.toml` just means defaults.
+    pub fn load(path: Option<&Path>, profile: Option<&str>) -> Result<Self, ConfigError> {
+        let (file, explicit) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => (PathBuf::from(DEFAULT_CONFIG_FILE), false),
+        };
+        let display = file.display().to_string();
+
+        let mut table = if file.exists() || explicit {
+            let text = std::fs::read_to_string(&file)
+                .map_err(|source| ConfigError::Read { path: display.clone(), source })?;
+            text.parse::<toml::Table>()
+                .map_err(|e| ConfigError::Parse { path: display.clone(), message: e.to_string() })?
+        } else {
+            toml::Table::new()
+        };
+
+        if let Some(name) = profile {
+            let profiles = table.get("profiles").and_then(toml::Value::as_table);
+            let Some(overrides) = profiles.and_then(|profiles| profiles.get(name)).and_then(toml::Value::as_table) else {
+                let available = profiles
+                    .map(|profiles| profiles.keys().cloned().collect::<Vec<_>>().join(", "))
+                    .filter(|names| !names.is_empty())
+                    .unwrap_or_else(|| "none".to_string());
+                return Err(ConfigError::UnknownProfile { name: name.to_string(), available });
+            };
+            let overrides = overrides.clone();
+            merge_tables(&mut table, overrides);
+        }
+
+        // Profiles are merged above; unselected ones may reference variables that are not set
+        table.remove("profiles");
+        for (_, value) in table.iter_mut() {
+            interpolate_value(value)?;
+        }
+
+        let mut config: Config = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e: toml::de::Error| ConfigError::Parse { path: display, message: e.to_string() })?;
+
+        // Relative paths in a config file are relative to that file
+        if file.exists() {
+            let base = file.parent().filter(|dir| !dir.as_os_str().is_empty());
+            if let Some(base) = base {
+                config.system_prompt_file = base.join(&config.system_prompt_file);
+                config.workspace = base.join(&config.workspace);
+                config.exec_script = config.exec_script.map(|script| base.join(script));
+                for settings in config.providers.values_mut() {
+                    settings.script = settings.script.take().map(|script| base.join(script));
+                }
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn apply_overrides(&mut self, overrides: &ConfigOverrides) {
+        if let Some(provider) = &overrides.provider {
+            self.provider = provider.clone();
+        }
+        if let Some(workspace) = &overrides.workspace {
+            self.workspace = workspace.clone();
+        }
+        if let Some(script) = &overrides.exec_script {
+            self.exec_script = Some(script.clone());
+        }
+        if let Some(prompt_file) = &overrides.system_prompt_file {
+            self.system_prompt_file = prompt_file.clone();
+        }
+        if let Some(stream) = overrides.stream {
+            self.stream = stream;
+        }
+
+        let settings = self.providers.entry(self.provider.clone()).or_default();
+        if let Some(model) = &overrides.model {
+            settings.model = Some(model.clone());
+        }
+        if let Some(temperature) = overrides.temperature {
+            settings.temperature = Some(temperature);
+        }
+        if let Some(max_tokens) = overrides.max_tokens {
+            settings.max_tokens = Some(max_tokens);
+        }
+    }
+
+    /// Check the settings before anything is started
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if ModelType::from_name(&self.provider).is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "unknown provider '{}', expected one of: {}", self.provider, ModelType::NAMES.join(", "))));
+        }
+        for name in self.providers.keys() {
+            if ModelType::from_name(name).is_none() {
+                return Err(ConfigError::Invalid(format!(
+                    "[providers.{}] is not a known provider, expected one of: {}", name, ModelType::NAMES.join(", "))));
+            }
+        }
+
+        validate_fallback(&self.fallback, &self.provider, "fallback")?;
+        if self.retry.max_attempts == 0 {
+            return Err(ConfigError::Invalid("retry.max_attempts must be at least 1".to_string()));
+        }
+
+        for (name, settings) in &self.providers {
+            if let Some(temperature) = settings.temperature
+                && !(0.0..=2.0).contains(&temperature) {
+                return Err(ConfigError::Invalid(format!(
+                    "providers.{}.temperature must be between 0.0 and 2.0, got {}", name, temperature)));
+            }
+            if settings.max_tokens == Some(0) {
+                return Err(ConfigError::Invalid(format!("providers.{}.max_tokens must be greater than 0", name)));
+            }
+            if settings.model.as_deref().is_some_and(|model| model.trim().is_empty()) {
+                return Err(ConfigError::Invalid(format!("providers.{}.model must not be empty", name)));
+            }
+            if settings.script.is_some() && name != MockProvider::NAME {
+                return Err(ConfigError::Invalid(format!("providers.{}.script is only used by the mock provider", name)));
+            }
+        }
+
+        let referenced: Vec<&String> = std::iter::once(&self.provider)
+            .chain(&self.fallback)
+            .chain(self.routes.values().map(|route| &route.provider))
+            .chain(self.routes.values().filter_map(|route| route.fallback.as_ref()).flatten())
+            .collect();
+        for name in &referenced {
+            let Some(var) = ModelType::from_name(name).and_then(|model_type| model_type.api_key_var()) else {
+                continue;
+            };
+            let configured = self.providers.get(*name).and_then(|settings| settings.api_key.as_deref()).is_some_and(|key| !key.is_empty());
+            if !configured && std::env::var(var).map_or(true, |key| key.is_empty()) {
+                return Err(ConfigError::Invalid(format!(
+                    "provider '{}' needs an API key: set providers.{}.api_key or {}", name, name, var)));
+            }
+        }
+
+        if referenced.iter().any(|name| *name == MockProvider::NAME)
+            && let Some(path) = (MockProvider { settings: self.settings_for(MockProvider::NAME) }).script_path() {
+            MockScript::load(&path).map_err(ConfigError::Invalid)?;
+        }
+
+        for (model, price) in &self.prices {
+            if price.input_per_million < 0.0 || price.output_per_million < 0.0 {
+                return Err(ConfigError::Invalid(format!("prices.\"{}\" must not be negative", model)));
+            }
+        }
+        if self.budget.max_tokens == Some(0) {
+            return Err(ConfigError::Invalid("budget.max_tokens must be greater than 0".to_string()));
+        }
+        if let Some(max_cost) = self.budget.max_cost {
+            if max_cost <= 0.0 {
+                return Err(ConfigError::Invalid("budget.max_cost must be greater than 0".to_string()));
+            }
+            if self.prices.is_empty() {
+                return Err(ConfigError::Invalid("budget.max_cost needs model prices in [prices]".to_string()));
+            }
+        }
+
+        if let Some(tools) = &self.enabled_tools {
+            validate_tools(tools, "enabled_tools")?;
+        }
+
+        for (label, route) in &self.routes {
+            if label.is_empty() || !label.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+                return Err(ConfigError::Invalid(format!(
+                    "route name '{}' may only contain letters, digits, '_' and '-'", label)));
+            }
+            if ModelType::from_name(&route.provider).is_none() {
+                return Err(ConfigError::Invalid(format!(
+                    "unknown provider '{}' in routes.{}, expected one of: {}", route.provider, label, ModelType::NAMES.join(", "))));
+            }
+            if route.model.as_deref().is_some_and(|model| model.trim().is_empty()) {
+                return Err(ConfigError::Invalid(format!("routes.{}.model must not be empty", label)));
+            }
+            if let Some(fallback) = &route.fallback {
+                validate_fallback(fallback, &route.provider, &format!("routes.{}.fallback", label))?;
+            }
+            if let Some(tools) = &route.enabled_tools {
+                validate_tools(tools, &format!("routes.{}.enabled_tools", label))?;
+            }
+        }
+
+        for (name, mcp) in &self.mcp_servers {
+            if name.is_empty() || name.contains(NAMESPACE_SEPARATOR)
+                || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                return Err(ConfigError::Invalid(format!(
+                    "MCP server name '{}' may only contain letters, digits, '_' and '-' (and no '{}')", name, NAMESPACE_SEPARATOR)));
+            }
+            let key = format!("mcp_servers.{}", name);
+            match (&mcp.url, &mcp.command) {
+                (Some(_), Some(_)) => return Err(ConfigError::Invalid(format!(
+                    "[{}] takes either url (SSE) or command (stdio), not both", key))),
+                (None, None) => return Err(ConfigError::Invalid(format!(
+                    "[{}] needs a url (SSE) or a command (stdio)", key))),
+                (Some(url), None) if url.trim().is_empty() => return Err(ConfigError::Invalid(format!(
+                    "{}.url must not be empty", key))),
+                (None, Some(command)) if command.trim().is_empty() => return Err(ConfigError::Invalid(format!(
+                    "{}.command must not be empty", key))),
+                (Some(_), None) if !mcp.args.is_empty() || !mcp.env.is_empty() => return Err(ConfigError::Invalid(format!(
+                    "{}.args and {}.env are only used with command", key, key))),
+                _ => {}
+            }
+            for (field, patterns) in [("resources", &mcp.resources), ("tools", &mcp.tools),
+                ("exclude_tools", &mcp.exclude_tools), ("confirm_tools", &mcp.confirm_tools)] {
+                for pattern in patterns {
+                    glob::Pattern::new(pattern).map_err(|e| ConfigError::Invalid(format!(
+                        "invalid pattern '{}' in {}.{}: {}", pattern, key, field, e)))?;
+                }
+            }
+            if mcp.tool_timeout_secs == Some(0) || mcp.tool_timeouts.values().any(|secs| *secs == 0) {
+                return Err(ConfigError::Invalid(format!("{} tool timeouts must be at least 1 second", key)));
+            }
+            if mcp.poll_secs == Some(0) {
+                return Err(ConfigError::Invalid(format!("{}.poll_secs must be at least 1", key)));
+            }
+            if mcp.reconnect.max_attempts == 0 {
+                return Err(ConfigError::Invalid(format!("{}.reconnect.max_attempts must be at least 1", key)));
+            }
+        }
+
+        if !self.system_prompt_file.is_file() {
+            return Err(ConfigError::Invalid(format!(
+                "system prompt file {} does not exist", self.system_prompt_file.display())));
+        }
+        if !self.workspace.is_dir() {
+            return Err(ConfigError::Invalid(format!(
+                "workspace {} is not a directory", self.workspace.display())));
+        }
+        if let Some(script) = &self.exec_script
+            && !script.is_file() {
+            return Err(ConfigError::Invalid(format!(
+                "execution script {} does not exist", script.display())));
+        }
+
+        Ok(())
+    }
+
+    /// Settings for the active provider
+    pub fn provider_settings(&self) -> ProviderConfig {
+        self.settings_for(&self.provider)
+    }
+
+    pub fn settings_for(&self, provider: &str) -> ProviderConfig {
+        self.providers.get(provider).cloned().unwrap_or_default()
+    }
+
+    /// The MCP servers to connect to: `[mcp_servers]`, else `$MCP_SERVER_URL`
+    pub fn mcp_servers(&self) -> BTreeMap<String, McpServerConfig> {
+        if !self.mcp_servers.is_empty() {
+            return self.mcp_servers.clone();
+        }
+        std::env::var("MCP_SERVER_URL").ok()
+            .filter(|url| !url.is_empty())
+            .map(|url| ("mcp".to_string(), McpServerConfig { url: Some(url), ..Default::default() }))
+            .into_iter()
+            .collect()
+    }
+
+    pub fn exec_script(&self) -> PathBuf {
+        self.exec_script.clone().unwrap_or_else(|| self.workspace.join("run.sh"))
+    }
+
+    /// The configuration agents on route `label` are built from: its
+    /// provider, model, fallbacks and tools in place of the global ones
+    pub fn for_route(&self, label: &str) -> Option<Config> {
+        let route = self.routes.get(label)?;
+        let mut config = self.clone();
+        config.provider = route.provider.clone();
+        if let Some(model) = &route.model {
+            config.providers.entry(route.provider.clone()).or_default().model = Some(model.clone());
+        }
+        config.fallback = match &route.fallback {
+            Some(fallback) => fallback.clone(),
+            None => self.fallback.iter().filter(|name| **name != route.provider).cloned().collect(),
+        };
+        if route.enabled_tools.is_some() {
+            config.enabled_tools = route.enabled_tools.clone();
+        }
+        Some(config)
+    }
+}
+
+fn validate_fallback(fallback: &[String], provider: &str, key: &str) -> Result<(), ConfigError> {
+    for name in fallback {
+        if ModelType::from_name(name).is_none() {
+            return Err(ConfigError::Invalid(format!(
+                "unknown provider '{}' in {}, expected one of: {}", name, key, ModelType::NAMES.join(", "))));
+        }
+        if name == provider {
+            return Err(ConfigError::Invalid(format!("{} lists the active provider '{}'", key, name)));
+        }
+    }
+    Ok(())
+}
+
+fn validate_tools(tools: &[String], key: &str) -> Result<(), ConfigError> {
+    for tool in tools {
+        if !TOOL_NAMES.contains(&tool.as_str()) {
+            return Err(ConfigError::Invalid(format!(
+                "unknown tool '{}' in {}, expected any of: {}", tool, key, TOOL_NAMES.join(", "))));
+        }
+    }
+    Ok(())
+}
+
+/// Recursively merge `overrides` into `base`; nested tables merge, other values replace
+fn merge_tables(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(value)) => merge_tables(existing, value),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn interpolate_value(value: &mut toml::Value) -> Result<(), ConfigError> {
+    match value {
+        toml::Value::String(text) => *text = interpolate(text)?,
+        toml::Value::Array(items) => {
+            for item in items {
+                interpolate_value(item)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, value) in table.iter_mut() {
+                interpolate_value(value)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` references to environment variables
+fn interpolate(text: &str) -> Result<String, ConfigError> {
+    let mut result = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        result.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            result.push_str(&rest[start..]);
+            return Ok(result);
+        };
+        let reference = &rest[start + 2..start + end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+
+        match (std::env::var(name), default) {
+            (Ok(value), _) if !value.is_empty() => result.push_str(&value),
+            (_, Some(default)) => result.push_str(default),
+            (Ok(value), None) => result.push_str(&value),
+            (Err(_), None) => return Err(ConfigError::MissingEnvVar { name: name.to_string() }),
+        }
+        rest = &rest[start + end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write `text` as a config file in a fresh temp directory
+    fn config_file(name: &str, text: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("llmo-config-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("llmo.toml");
+        std::fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn unset_variable_uses_its_default() {
+        assert_eq!(interpolate("key-${LLMO_TEST_UNSET_VAR:-fallback}").unwrap(), "key-fallback");
+        assert_eq!(interpolate("${LLMO_TEST_UNSET_VAR:-}").unwrap(), "");
+    }
+
+    #[test]
+    fn unset_variable_without_default_is_an_error() {
+        let error = interpolate("${LLMO_TEST_UNSET_VAR}").unwrap_err();
+        assert!(matches!(error, ConfigError::MissingEnvVar { ref name } if name == "LLMO_TEST_UNSET_VAR"), "{}", error);
+    }
+
+    #[test]
+    fn text_without_references_is_kept() {
+        assert_eq!(interpolate("plain $HOME ${unclosed").unwrap(), "plain $HOME ${unclosed");
+    }
+
+    #[test]
+    fn profile_merge_keeps_sibling_keys_in_nested_tables() {
+        let mut base: toml::Table = "provider = \"local\"\n[providers.local]\nmodel = \"small\"\ntemperature = 0.2\n"
+            .parse().unwrap();
+        let overrides: toml::Table = "[providers.local]\nmodel = \"large\"\n[providers.openai]\nmodel = \"gpt\"\n"
+            .parse().unwrap();
+        merge_tables(&mut base, overrides);
+
+        let local = base["providers"]["local"].as_table().unwrap();
+        assert_eq!(local["model"].as_str(), Some("large"));
+        assert_eq!(local["temperature"].as_float(), Some(0.2));
+        assert_eq!(base["providers"]["openai"]["model"].as_str(), Some("gpt"));
+        assert_eq!(base["provider"].as_str(), Some("local"));
+    }
+
+    #[test]
+    fn load_applies_the_selected_profile() {
+        let path = config_file("profile", "provider = \"local\"\n\
+            [providers.local]\nmodel = \"small\"\nmax_tokens = 100\n\
+            [profiles.big.providers.local]\nmodel = \"large\"\n\
+            [profiles.other]\nprovider = \"${LLMO_TEST_UNSET_VAR}\"\n");
+        let config = Config::load(Some(&path), Some("big")).unwrap();
+        assert_eq!(config.provider, "local");
+        assert_eq!(config.provider_settings().model.as_deref(), Some("large"));
+        assert_eq!(config.provider_settings().max_tokens, Some(100));
+
+        // Unselected profiles are not interpolated
+        let config = Config::load(Some(&path), None).unwrap();
+        assert_eq!(config.provider_settings().model.as_deref(), Some("small"));
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn unknown_profile_lists_the_available_ones() {
+        let path = config_file("unknown-profile", "[profiles.fast]\n[profiles.slow]\n");
+        let error = Config::load(Some(&path), Some("missing")).unwrap_err();
+        assert!(matches!(error, ConfigError::UnknownProfile { ref name, ref available }
+            if name == "missing" && available == "fast, slow"), "{}", error);
+
+        let path = config_file("no-profiles", "provider = \"local\"\n");
+        let error = Config::load(Some(&path), Some("missing")).unwrap_err();
+        assert!(matches!(error, ConfigError::UnknownProfile { ref available, .. } if available == "none"), "{}", error);
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn route_overrides_provider_model_and_fallback() {
+        let path = config_file("routes", "provider = \"local\"\nfallback = [\"local\", \"mock\"]\n\
+            [providers.local]\nmodel = \"small\"\n\
+            [routes.review]\nprovider = \"local\"\nmodel = \"large\"\nenabled_tools = [\"read_file\"]\n");
+        let config = Config::load(Some(&path), None).unwrap();
+
+        let route = config.for_route("review").unwrap();
+        assert_eq!(route.provider_settings().model.as_deref(), Some("large"));
+        assert_eq!(route.fallback, ["mock"]);
+        assert_eq!(route.enabled_tools.as_deref(), Some(&["read_file".to_string()][..]));
+        assert_eq!(config.provider_settings().model.as_deref(), Some("small"));
+        assert!(config.for_route("missing").is_none());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
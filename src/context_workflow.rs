@@ -1,4 +1,5 @@
 use crate::file_tools::{CodebaseAnalyzer, FileToolError};
+use crate::mcp_servers::McpResourceDoc;
 use rig::tool::Tool;
 use anyhow::Error;
 
@@ -13,6 +14,7 @@ impl ContextWorkflow {
             path: codebase_path.to_string(),
             max_file_size: 10000,
             max_depth: 10,
+            format: "text".to_string(),
         };
 
         analyzer.call(args).await.map_err(|e: FileToolError| anyhow::anyhow!(e))
@@ -40,6 +42,14 @@ impl ContextWorkflow {
         )
     }
 
+    /// Context document for a resource attached from an MCP server
+    pub fn create_resource_doc(resource: &McpResourceDoc) -> String {
+        format!(
+            "MCP RESOURCE {} (from server {}):\n\n{}\n\nEND OF MCP RESOURCE\n",
+            resource.uri, resource.server, resource.text
+        )
+    }
+
     /// Create context documents for agent initialization
     pub fn create_context_docs(codebase_content: &str, additional_context: Option<String>, execution_script: &str) -> Vec<String> {
         let mut docs = vec![Self::create_analysis_prompt(codebase_content, execution_script)];
@@ -0,0 +1,765 @@
+use rig::tool::Tool;
+use rig::completion::request::ToolDefinition;
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use walkdir::WalkDir;
+
+use crate::file_tools::{confine, is_ignored, FileToolError};
+
+static RUST_MOD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+([A-Za-z_][A-Za-z0-9_]*)\s*;").unwrap()
+});
+static RUST_USE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?ms)^\s*(?:pub(?:\([^)]*\))?\s+)?use\s+([^;]+);").unwrap()
+});
+static USE_ALIAS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\s+as\s+[A-Za-z_][A-Za-z0-9_]*").unwrap()
+});
+static PY_IMPORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\s*import\s+([A-Za-z0-9_., ]+)").unwrap()
+});
+static PY_FROM_IMPORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^\s*from\s+(\.*[A-Za-z0-9_.]*)\s+import\s+(?:\(([^)]*)\)|([A-Za-z0-9_*, ]+))").unwrap()
+});
+static JS_IMPORT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"(?:\bimport\s*(?:[^'";]*?\bfrom\s*)?|\bexport\s+[^'";]*?\bfrom\s*|\brequire\s*\(\s*|\bimport\s*\(\s*)['"]([^'"]+)['"]"#).unwrap()
+});
+
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SourceKind {
+    Rust,
+    Python,
+    JavaScript,
+}
+
+impl SourceKind {
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(SourceKind::Rust),
+            "py" => Some(SourceKind::Python),
+            ext if JS_EXTENSIONS.contains(&ext) => Some(SourceKind::JavaScript),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SourceKind::Rust => "rust",
+            SourceKind::Python => "python",
+            SourceKind::JavaScript => "javascript",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleNode {
+    /// File path relative to the analyzed root
+    pub id: String,
+    pub language: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DependencyEdge {
+    pub from: String,
+    pub to: String,
+    /// "mod" for Rust module declarations, "import" for everything else
+    pub kind: &'static str,
+}
+
+/// Import graph between the source files of a project
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyGraph {
+    pub root: String,
+    pub nodes: Vec<ModuleNode>,
+    pub edges: Vec<DependencyEdge>,
+    /// External packages/crates imported by each file
+    pub external: BTreeMap<String, BTreeSet<String>>,
+    /// Groups of files with no imports between groups; safe units to hand to separate agents
+    pub components: Vec<Vec<String>>,
+}
+
+impl DependencyGraph {
+    pub fn build(root: &Path) -> Result<Self, FileToolError> {
+        if !root.is_dir() {
+            return Err(FileToolError::InvalidPath(format!("{} is not a directory", root.display())));
+        }
+
+        let mut files: BTreeMap<String, (SourceKind, String)> = BTreeMap::new();
+        for entry in WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|e| !is_ignored(e))
+            .flatten()
+        {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let Some(kind) = SourceKind::from_path(entry.path()) else { continue };
+            let Ok(content) = std::fs::read_to_string(entry.path()) else { continue };
+            files.insert(relative_id(root, entry.path()), (kind, content));
+        }
+
+        let rust_modules = rust_module_paths(files.keys());
+        let mut edges = BTreeSet::new();
+        let mut external: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+
+        for (id, (kind, content)) in &files {
+            let mut add = |target: Target| match target {
+                Target::File(to, kind) if &to != id => {
+                    edges.insert(DependencyEdge { from: id.clone(), to, kind });
+                }
+                Target::External(name) => {
+                    external.entry(id.clone()).or_default().insert(name);
+                }
+                _ => {}
+            };
+
+            match kind {
+                SourceKind::Rust => rust_targets(id, content, &files, &rust_modules).into_iter().for_each(&mut add),
+                SourceKind::Python => python_targets(id, content, &files).into_iter().for_each(&mut add),
+                SourceKind::JavaScript => js_targets(id, content, &files).into_iter().for_each(&mut add),
+            }
+        }
+
+        let nodes: Vec<ModuleNode> = files.iter()
+            .map(|(id, (kind, _))| ModuleNode { id: id.clone(), language: kind.name() })
+            .collect();
+        let edges: Vec<DependencyEdge> = edges.into_iter().collect();
+        let components = connected_components(&nodes, &edges);
+
+        Ok(Self {
+            root: root.to_string_lossy().to_string(),
+            nodes,
+            edges,
+            external,
+            components,
+        })
+    }
+
+    /// Internal dependencies of each file, in file order
+    fn adjacency(&self) -> BTreeMap<&str, Vec<&str>> {
+        let mut adjacency: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for edge in &self.edges {
+            let targets = adjacency.entry(edge.from.as_str()).or_default();
+            if !targets.contains(&edge.to.as_str()) {
+                targets.push(edge.to.as_str());
+            }
+        }
+        adjacency
+    }
+
+    /// Compact "file -> deps" listing used in the codebase analysis
+    pub fn to_adjacency_text(&self) -> String {
+        let mut text = String::new();
+        for (from, targets) in self.adjacency() {
+            text.push_str(&format!("{} -> {}\n", from, targets.join(", ")));
+        }
+        if text.is_empty() {
+            text.push_str("(no internal dependencies found)\n");
+        }
+        text
+    }
+
+    pub fn to_summary(&self) -> String {
+        let external_packages: BTreeSet<&String> = self.external.values().flatten().collect();
+        let mut summary = format!(
+            "=== DEPENDENCY GRAPH: {} ===\nFiles: {} | Internal dependencies: {} | External packages: {}\n\n",
+            self.root, self.nodes.len(), self.edges.len(), external_packages.len()
+        );
+
+        summary.push_str("--- Internal dependencies ---\n");
+        summary.push_str(&self.to_adjacency_text());
+
+        let mut imported_by: HashMap<&str, usize> = HashMap::new();
+        for edge in &self.edges {
+            *imported_by.entry(edge.to.as_str()).or_default() += 1;
+        }
+        let mut most_used: Vec<(&str, usize)> = imported_by.into_iter().collect();
+        most_used.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        if !most_used.is_empty() {
+            summary.push_str("\n--- Most depended-on files ---\n");
+            for (file, count) in most_used.iter().take(10) {
+                summary.push_str(&format!("{} (imported by {})\n", file, count));
+            }
+        }
+
+        if !external_packages.is_empty() {
+            summary.push_str("\n--- External packages ---\n");
+            let names: Vec<&str> = external_packages.iter().map(|s| s.as_str()).collect();
+            summary.push_str(&names.join(", "));
+            summary.push('\n');
+        }
+
+        summary.push_str("\n--- Independent groups (no imports between groups) ---\n");
+        for (index, component) in self.components.iter().enumerate() {
+            summary.push_str(&format!("{}. {}\n", index + 1, component.join(", ")));
+        }
+
+        summary
+    }
+
+    pub fn to_dot(&self, include_external: bool) -> String {
+        let mut dot = String::from("digraph dependencies {\n    rankdir=LR;\n    node [shape=box, fontname=\"monospace\"];\n");
+        for node in &self.nodes {
+            dot.push_str(&format!("    {};\n", dot_id(&node.id)));
+        }
+        for edge in &self.edges {
+            let style = if edge.kind == "mod" { " [style=bold]" } else { "" };
+            dot.push_str(&format!("    {} -> {}{};\n", dot_id(&edge.from), dot_id(&edge.to), style));
+        }
+        if include_external {
+            let packages: BTreeSet<&String> = self.external.values().flatten().collect();
+            for package in packages {
+                dot.push_str(&format!("    {} [shape=ellipse, style=dashed];\n", dot_id(package)));
+            }
+            for (from, packages) in &self.external {
+                for package in packages {
+                    dot.push_str(&format!("    {} -> {} [style=dashed];\n", dot_id(from), dot_id(package)));
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    pub fn to_json(&self) -> Result<String, FileToolError> {
+        serde_json::to_string_pretty(self).map_err(|e| FileToolError::Io(std::io::Error::other(e)))
+    }
+}
+
+/// Quoted DOT identifier
+fn dot_id(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+enum Target {
+    File(String, &'static str),
+    External(String),
+}
+
+fn relative_id(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Normalize "a/./b/../c" style paths built while resolving relative imports
+fn normalize(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component.as_os_str().to_str().unwrap_or("") {
+            "." | "" => {}
+            ".." => { parts.pop(); }
+            part => parts.push(part.to_string()),
+        }
+    }
+    parts.join("/")
+}
+
+fn parent_dir(id: &str) -> PathBuf {
+    Path::new(id).parent().map(Path::to_path_buf).unwrap_or_default()
+}
+
+// ---- Rust ----
+
+/// Module path of a Rust file within its crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RustModule {
+    /// Directory of the crate root file (main.rs/lib.rs)
+    crate_dir: PathBuf,
+    /// "crate", "crate::a::b"
+    path: String,
+}
+
+fn is_crate_root(id: &str) -> bool {
+    Path::new(id).file_name().is_some_and(|name| name == "main.rs" || name == "lib.rs")
+}
+
+/// Map every Rust file to its module path based on the nearest
+/// main.rs/lib.rs crate root above it
+fn rust_module_paths<'a>(ids: impl Iterator<Item = &'a String>) -> HashMap<String, RustModule> {
+    let rust_files: Vec<&String> = ids.filter(|id| id.ends_with(".rs")).collect();
+    let crate_roots: Vec<PathBuf> = rust_files.iter()
+        .filter(|id| is_crate_root(id))
+        .map(|id| parent_dir(id))
+        .collect();
+
+    let mut modules = HashMap::new();
+    for id in rust_files {
+        let path = Path::new(id.as_str());
+        let Some(root) = crate_roots.iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.components().count())
+        else { continue };
+
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        let mut segments: Vec<String> = relative.with_extension("")
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        if matches!(segments.last().map(String::as_str), Some("mod") | Some("main") | Some("lib")) {
+            segments.pop();
+        }
+
+        let module = std::iter::once("crate".to_string()).chain(segments).collect::<Vec<_>>().join("::");
+        // Keep the crate root file as the owner of "crate" when main.rs and lib.rs coexist
+        modules.entry((root.clone(), module)).or_insert_with(|| id.clone());
+    }
+
+    modules.into_iter().map(|((crate_dir, path), id)| (id, RustModule { crate_dir, path })).collect()
+}
+
+/// Expand a use tree like `crate::{a::{B, C}, d}` into flat paths
+fn expand_use_tree(tree: &str) -> Vec<String> {
+    let tree: String = USE_ALIAS.replace_all(tree, "").chars().filter(|c| !c.is_whitespace()).collect();
+    let Some(open) = tree.find('{') else {
+        return vec![tree];
+    };
+
+    let prefix = &tree[..open];
+    let inner = &tree[open + 1..tree.rfind('}').unwrap_or(tree.len())];
+
+    let mut items = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in inner.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&inner[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&inner[start..]);
+
+    items.into_iter()
+        .filter(|item| !item.is_empty())
+        .flat_map(|item| expand_use_tree(&format!("{}{}", prefix, item)))
+        .collect()
+}
+
+fn rust_targets(id: &str, content: &str, files: &BTreeMap<String, (SourceKind, String)>, modules: &HashMap<String, RustModule>) -> Vec<Target> {
+    let mut targets = Vec::new();
+    let dir = parent_dir(id);
+    let stem = Path::new(id).file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let module_dir = if matches!(stem, "main" | "lib" | "mod") { dir.clone() } else { dir.join(stem) };
+
+    for capture in RUST_MOD.captures_iter(content) {
+        let name = &capture[1];
+        for candidate in [module_dir.join(format!("{}.rs", name)), module_dir.join(name).join("mod.rs")] {
+            let candidate = normalize(&candidate);
+            if files.contains_key(&candidate) {
+                targets.push(Target::File(candidate, "mod"));
+                break;
+            }
+        }
+    }
+
+    let Some(current) = modules.get(id) else { return targets };
+    // Module paths only mean something within the file's own crate
+    let by_module: HashMap<&str, &str> = modules.iter()
+        .filter(|(_, module)| module.crate_dir == current.crate_dir)
+        .map(|(file, module)| (module.path.as_str(), file.as_str()))
+        .collect();
+    let current = current.path.as_str();
+    let crate_root = by_module.get("crate").copied().unwrap_or("");
+
+    for capture in RUST_USE.captures_iter(content) {
+        for path in expand_use_tree(&capture[1]) {
+            let mut segments: Vec<&str> = path.split("::").filter(|s| !s.is_empty()).collect();
+            let Some(&first) = segments.first() else { continue };
+
+            let mut base: Vec<String> = match first {
+                "crate" => { segments.remove(0); vec!["crate".to_string()] }
+                "self" => { segments.remove(0); current.split("::").map(str::to_string).collect() }
+                "super" => {
+                    let mut base: Vec<String> = current.split("::").map(str::to_string).collect();
+                    while segments.first() == Some(&"super") {
+                        segments.remove(0);
+                        base.pop();
+                    }
+                    base
+                }
+                // Paths starting with a module declared at the crate root resolve from there
+                name if by_module.contains_key(format!("crate::{}", name).as_str()) && (id == crate_root || current == "crate") => {
+                    vec!["crate".to_string()]
+                }
+                name if by_module.contains_key(format!("{}::{}", current, name).as_str()) => {
+                    current.split("::").map(str::to_string).collect()
+                }
+                "std" | "core" | "alloc" => continue,
+                name => {
+                    targets.push(Target::External(name.to_string()));
+                    continue;
+                }
+            };
+
+            // Longest module prefix that maps to a file
+            let mut resolved = by_module.get(base.join("::").as_str()).copied();
+            for segment in segments {
+                base.push(segment.to_string());
+                match by_module.get(base.join("::").as_str()) {
+                    Some(file) => resolved = Some(*file),
+                    None => break,
+                }
+            }
+            if let Some(file) = resolved {
+                targets.push(Target::File(file.to_string(), "import"));
+            }
+        }
+    }
+
+    targets
+}
+
+// ---- Python ----
+
+fn python_module_file(module_path: &Path, files: &BTreeMap<String, (SourceKind, String)>) -> Option<String> {
+    let as_file = normalize(&module_path.with_extension("py"));
+    if files.contains_key(&as_file) {
+        return Some(as_file);
+    }
+    let as_package = normalize(&module_path.join("__init__.py"));
+    files.contains_key(&as_package).then_some(as_package)
+}
+
+/// Resolve a dotted module from the project root or a "src" layout
+fn python_absolute(module: &str, files: &BTreeMap<String, (SourceKind, String)>) -> Option<String> {
+    let relative: PathBuf = module.split('.').collect();
+    [PathBuf::new(), PathBuf::from("src")]
+        .iter()
+        .find_map(|base| python_module_file(&base.join(&relative), files))
+}
+
+fn python_targets(id: &str, content: &str, files: &BTreeMap<String, (SourceKind, String)>) -> Vec<Target> {
+    let mut targets = Vec::new();
+
+    for capture in PY_IMPORT.captures_iter(content) {
+        for item in capture[1].split(',') {
+            let module = item.split_whitespace().next().unwrap_or("");
+            if module.is_empty() {
+                continue;
+            }
+            match python_absolute(module, files) {
+                Some(file) => targets.push(Target::File(file, "import")),
+                None => targets.push(Target::External(module.split('.').next().unwrap_or(module).to_string())),
+            }
+        }
+    }
+
+    for capture in PY_FROM_IMPORT.captures_iter(content) {
+        let module = &capture[1];
+        let imported = capture.get(2).or_else(|| capture.get(3)).map_or("", |m| m.as_str());
+        let names: Vec<&str> = imported.split(',')
+            .filter_map(|name| name.split_whitespace().next())
+            .filter(|name| *name != "*")
+            .collect();
+
+        let dots = module.chars().take_while(|c| *c == '.').count();
+        let module_path: PathBuf = if dots > 0 {
+            let mut base = parent_dir(id);
+            for _ in 1..dots {
+                base = base.parent().map(Path::to_path_buf).unwrap_or_default();
+            }
+            base.join(module[dots..].split('.').filter(|s| !s.is_empty()).collect::<PathBuf>())
+        } else {
+            module.split('.').collect()
+        };
+
+        // `from pkg import submodule` depends on the submodule file when it exists
+        let mut found_submodule = false;
+        for name in &names {
+            let submodule = if dots > 0 {
+                python_module_file(&module_path.join(name), files)
+            } else {
+                python_absolute(&format!("{}.{}", module, name), files)
+            };
+            if let Some(file) = submodule {
+                targets.push(Target::File(file, "import"));
+                found_submodule = true;
+            }
+        }
+        if found_submodule {
+            continue;
+        }
+
+        let resolved = if dots > 0 { python_module_file(&module_path, files) } else { python_absolute(module, files) };
+        match resolved {
+            Some(file) => targets.push(Target::File(file, "import")),
+            None if dots == 0 => targets.push(Target::External(module.split('.').next().unwrap_or(module).to_string())),
+            None => {}
+        }
+    }
+
+    targets
+}
+
+// ---- JavaScript / TypeScript ----
+
+fn js_targets(id: &str, content: &str, files: &BTreeMap<String, (SourceKind, String)>) -> Vec<Target> {
+    let mut targets = Vec::new();
+
+    for capture in JS_IMPORT.captures_iter(content) {
+        let specifier = &capture[1];
+        if !specifier.starts_with('.') {
+            // "@scope/pkg/sub" -> "@scope/pkg", "pkg/sub" -> "pkg"
+            let parts: Vec<&str> = specifier.split('/').collect();
+            let package = if specifier.starts_with('@') && parts.len() > 1 {
+                format!("{}/{}", parts[0], parts[1])
+            } else {
+                parts[0].to_string()
+            };
+            targets.push(Target::External(package));
+            continue;
+        }
+
+        let base = parent_dir(id).join(specifier);
+        let normalized = normalize(&base);
+        let mut candidates = vec![normalized.clone()];
+        // TypeScript sources are often imported with a .js extension
+        if let Some(stem) = normalized.strip_suffix(".js") {
+            candidates.extend(["ts", "tsx"].iter().map(|ext| format!("{}.{}", stem, ext)));
+        }
+        candidates.extend(JS_EXTENSIONS.iter().map(|ext| format!("{}.{}", normalized, ext)));
+        candidates.extend(JS_EXTENSIONS.iter().map(|ext| format!("{}/index.{}", normalized, ext)));
+
+        if let Some(file) = candidates.into_iter().find(|candidate| files.contains_key(candidate)) {
+            targets.push(Target::File(file, "import"));
+        }
+    }
+
+    targets
+}
+
+/// Weakly connected components of the internal graph, largest first
+fn connected_components(nodes: &[ModuleNode], edges: &[DependencyEdge]) -> Vec<Vec<String>> {
+    let index: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, n)| (n.id.as_str(), i)).collect();
+    let mut parent: Vec<usize> = (0..nodes.len()).collect();
+
+    fn find(parent: &mut [usize], mut node: usize) -> usize {
+        while parent[node] != node {
+            parent[node] = parent[parent[node]];
+            node = parent[node];
+        }
+        node
+    }
+
+    for edge in edges {
+        if let (Some(&a), Some(&b)) = (index.get(edge.from.as_str()), index.get(edge.to.as_str())) {
+            let (root_a, root_b) = (find(&mut parent, a), find(&mut parent, b));
+            parent[root_a] = root_b;
+        }
+    }
+
+    let mut groups: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for (i, node) in nodes.iter().enumerate() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(node.id.clone());
+    }
+
+    let mut components: Vec<Vec<String>> = groups.into_values().collect();
+    components.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+    components
+}
+
+// Dependency Graph Tool
+#[derive(Deserialize, JsonSchema)]
+pub struct DependencyGraphArgs {
+    /// Path to the project directory
+    pub path: String,
+    /// Output format: "summary", "dot" or "json" (default: summary)
+    #[serde(default = "default_format")]
+    pub format: String,
+    /// Optional file to export the graph to instead of returning it
+    #[serde(default)]
+    pub output_file: Option<String>,
+    /// Include external packages in DOT output (default: true)
+    #[serde(default = "default_include_external")]
+    pub include_external: bool,
+}
+
+fn default_format() -> String { "summary".to_string() }
+fn default_include_external() -> bool { true }
+
+#[derive(Debug, Clone)]
+pub struct DependencyGrapher {
+    /// Graphs are built and written inside this directory
+    pub root: PathBuf,
+}
+
+impl Tool for DependencyGrapher {
+    const NAME: &'static str = "dependency_graph";
+    type Error = FileToolError;
+    type Args = DependencyGraphArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Build the import/module dependency graph of a project (Rust mod/use, Python imports, JavaScript/TypeScript imports). The summary lists each file's dependencies, the most depended-on files and independent groups of files that can be worked on separately. Can export DOT or JSON.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "path": {
+                        "type": "string",
+                        "description": "Path to the project directory"
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["summary", "dot", "json"],
+                        "description": "Output format (default: summary)",
+                        "default": "summary"
+                    },
+                    "output_file": {
+                        "type": "string",
+                        "description": "Optional file to export the graph to (e.g. 'deps.dot'); the tool then returns a short summary"
+                    },
+                    "include_external": {
+                        "type": "boolean",
+                        "description": "Include external packages in DOT output (default: true)",
+                        "default": true
+                    }
+                },
+                "required": ["path"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        println!("🔧 Tool: dependency_graph | Path: {} | Format: {}", args.path, args.format);
+
+        let path = Path::new(&args.path);
+        if !path.exists() {
+            println!("❌ Tool: dependency_graph | Error: Directory not found");
+            return Err(FileToolError::FileNotFound(args.path));
+        }
+        confine(&self.root, path)?;
+        let output_file = args.output_file.as_deref().map(|file| confine(&self.root, Path::new(file))).transpose()?;
+
+        let graph = DependencyGraph::build(path)?;
+        let rendered = match args.format.as_str() {
+            "summary" => graph.to_summary(),
+            "dot" => graph.to_dot(args.include_external),
+            "json" => graph.to_json()?,
+            other => return Err(FileToolError::InvalidArgument(format!("Unknown format '{}', expected summary, dot or json", other))),
+        };
+
+        println!("✅ Tool: dependency_graph | Success: {} files, {} dependencies", graph.nodes.len(), graph.edges.len());
+
+        match output_file {
+            Some(output_file) => {
+                if let Some(parent) = output_file.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::write(&output_file, &rendered).await?;
+                Ok(format!("Wrote {} dependency graph ({} files, {} dependencies, {} independent groups) to {}",
+                    args.format, graph.nodes.len(), graph.edges.len(), graph.components.len(), output_file.display()))
+            }
+            None => Ok(rendered),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modules(ids: &[&str]) -> HashMap<String, RustModule> {
+        let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+        rust_module_paths(ids.iter())
+    }
+
+    fn module_path<'a>(modules: &'a HashMap<String, RustModule>, id: &str) -> &'a str {
+        modules[id].path.as_str()
+    }
+
+    #[test]
+    fn only_main_and_lib_are_crate_roots() {
+        let modules = modules(&["src/main.rs", "src/domain.rs", "src/stdlib.rs", "src/toolmain.rs", "src/net/mod.rs", "src/net/http.rs"]);
+        assert_eq!(module_path(&modules, "src/main.rs"), "crate");
+        assert_eq!(module_path(&modules, "src/domain.rs"), "crate::domain");
+        assert_eq!(module_path(&modules, "src/stdlib.rs"), "crate::stdlib");
+        assert_eq!(module_path(&modules, "src/toolmain.rs"), "crate::toolmain");
+        assert_eq!(module_path(&modules, "src/net/mod.rs"), "crate::net");
+        assert_eq!(module_path(&modules, "src/net/http.rs"), "crate::net::http");
+        assert!(modules.values().all(|module| module.crate_dir == Path::new("src")));
+    }
+
+    #[test]
+    fn files_outside_any_crate_have_no_module() {
+        let modules = modules(&["build.rs", "src/lib.rs"]);
+        assert!(!modules.contains_key("build.rs"));
+    }
+
+    #[test]
+    fn crates_in_one_workspace_keep_their_own_modules() {
+        let ids = ["app/src/main.rs", "app/src/config.rs", "core/src/lib.rs", "core/src/config.rs"];
+        let modules = modules(&ids);
+        assert_eq!(module_path(&modules, "app/src/config.rs"), "crate::config");
+        assert_eq!(module_path(&modules, "core/src/config.rs"), "crate::config");
+
+        let files: BTreeMap<String, (SourceKind, String)> = ids.iter()
+            .map(|id| (id.to_string(), (SourceKind::Rust, String::new())))
+            .collect();
+        let targets = |id: &str, content: &str| -> Vec<String> {
+            rust_targets(id, content, &files, &modules).into_iter()
+                .filter_map(|target| match target {
+                    Target::File(file, _) => Some(file),
+                    Target::External(_) => None,
+                })
+                .collect()
+        };
+        assert_eq!(targets("app/src/main.rs", "use crate::config::Settings;"), ["app/src/config.rs"]);
+        assert_eq!(targets("core/src/lib.rs", "use crate::config::Settings;"), ["core/src/config.rs"]);
+    }
+
+    #[test]
+    fn expands_nested_use_trees() {
+        assert_eq!(expand_use_tree("crate::config::Config"), ["crate::config::Config"]);
+        assert_eq!(expand_use_tree("crate::{a::{B, C}, d}"), ["crate::a::B", "crate::a::C", "crate::d"]);
+        assert_eq!(expand_use_tree("crate::net::{self, http::Client as HttpClient}"), ["crate::net::self", "crate::net::http::Client"]);
+        assert_eq!(expand_use_tree("super::{\n    a,\n    b::C,\n}"), ["super::a", "super::b::C"]);
+        assert_eq!(expand_use_tree("std::io::Result as IoResult"), ["std::io::Result"]);
+    }
+
+    #[test]
+    fn dot_ids_are_escaped() {
+        assert_eq!(dot_id("src/main.rs"), "\"src/main.rs\"");
+        assert_eq!(dot_id("say \"hi\".py"), "\"say \\\"hi\\\".py\"");
+        assert_eq!(dot_id("dir\\file.js"), "\"dir\\\\file.js\"");
+    }
+
+    #[tokio::test]
+    async fn output_file_stays_in_the_workspace() {
+        let workspace = std::env::temp_dir().join(format!("llmo-dependency-graph-{}", std::process::id()));
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::write(workspace.join("main.py"), "import os\n").unwrap();
+        let grapher = DependencyGrapher { root: workspace.clone() };
+        let args = |output_file: &Path| DependencyGraphArgs {
+            path: workspace.display().to_string(),
+            format: "dot".to_string(),
+            output_file: Some(output_file.display().to_string()),
+            include_external: true,
+        };
+
+        let outside = workspace.with_extension("dot");
+        let error = grapher.call(args(&outside)).await.unwrap_err();
+        assert!(matches!(error, FileToolError::PermissionDenied(_)), "{}", error);
+        assert!(!outside.exists());
+
+        let inside = workspace.join("graphs/deps.dot");
+        grapher.call(args(&inside)).await.unwrap();
+        assert!(std::fs::read_to_string(&inside).unwrap().contains("\"main.py\" -> \"os\""));
+
+        std::fs::remove_dir_all(&workspace).unwrap();
+    }
+}
@@ -1,18 +1,20 @@
 use rig::tool::Tool;
 use rig::completion::request::ToolDefinition;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 use walkdir::WalkDir;
 use std::process::{Command, Stdio};
 use std::io::{BufRead, BufReader};
-use std::io::Write;
 use std::time::{SystemTime, Duration};
 use chrono::{DateTime, Local};
 use std::fmt;
 
+use crate::codebase_stats::CodebaseStats;
+use crate::dependency_graph::DependencyGraph;
+
 #[derive(Debug, thiserror::Error)]
 pub enum FileToolError {
     #[error("IO error: {0}")]
@@ -25,6 +27,54 @@ pub enum FileToolError {
     InvalidPath(String),
     #[error("String not found in file")]
     StringNotFound,
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(String),
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+}
+
+/// Resolve `path` (relative to the current directory) and make sure it lies
+/// inside `root`. Paths that do not exist yet are resolved through their
+/// nearest existing parent, so new files can be checked too.
+pub(crate) fn confine(root: &Path, path: &Path) -> Result<PathBuf, FileToolError> {
+    let outside = || FileToolError::PermissionDenied(format!(
+        "{} is outside the workspace {}", path.display(), root.display()));
+    let root = root.canonicalize()
+        .map_err(|e| FileToolError::InvalidPath(format!("workspace {}: {}", root.display(), e)))?;
+
+    let mut existing = path;
+    let mut missing = Vec::new();
+    let resolved = loop {
+        match existing.canonicalize() {
+            Ok(resolved) => break resolved,
+            Err(_) => match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => {
+                    missing.push(name.to_os_string());
+                    existing = if parent.as_os_str().is_empty() { Path::new(".") } else { parent };
+                }
+                // ".." or a missing root; nothing left to resolve through
+                _ => return Err(outside()),
+            },
+        }
+    };
+    let resolved = missing.into_iter().rev().fold(resolved, |resolved, name| resolved.join(name));
+
+    if resolved.starts_with(&root) { Ok(resolved) } else { Err(outside()) }
+}
+
+// Directories skipped by every tool that walks the workspace
+pub(crate) const SKIP_DIRS: &[&str] = &[
+    ".git", "node_modules", "target", "dist", "build", "out", ".idea",
+    ".vscode", "__pycache__", ".pytest_cache", "venv", "env", ".env",
+    "vendor", "bower_components", ".next", ".nuxt", "coverage",
+    "out-shakespeare-char"
+];
+
+/// Returns true if a walked entry is one of the ignored directories.
+pub(crate) fn is_ignored(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_type().is_dir()
+        && entry.file_name().to_str().is_some_and(|name| SKIP_DIRS.contains(&name))
 }
 
 // FileReader Tool
@@ -32,6 +82,25 @@ pub enum FileToolError {
 pub struct ReadFileArgs {
     /// Path to the file to read
     path: String,
+    /// Line number (1-based) to start reading from (default: 1)
+    #[serde(default)]
+    offset: Option<usize>,
+    /// Maximum number of lines to return (default: 2000)
+    #[serde(default)]
+    limit: Option<usize>,
+    /// Prefix each line with its line number (default: true)
+    #[serde(default = "default_line_numbers")]
+    line_numbers: bool,
+}
+
+fn default_line_numbers() -> bool { true }
+
+/// Lines returned by read_file when no limit is given
+pub(crate) const DEFAULT_READ_LIMIT: usize = 2000;
+
+/// Returns true if the bytes look like a binary file (NUL byte in the first 8KB)
+pub(crate) fn is_binary(bytes: &[u8]) -> bool {
+    bytes.iter().take(8192).any(|b| *b == 0)
 }
 
 #[derive(Debug, Clone)]
@@ -46,13 +115,28 @@ impl Tool for FileReader {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: Self::NAME.to_string(),
-            description: "Read the contents of a file. Returns the entire file content as a string.".to_string(),
+            description: "Read the contents of a text file with line numbers. Returns up to 2000 lines by default; use offset and limit to read large files in pieces. Binary files are detected and not returned.".to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
                     "path": {
                         "type": "string",
                         "description": "Path to the file to read"
+                    },
+                    "offset": {
+                        "type": "integer",
+                        "description": "Line number (1-based) to start reading from (default: 1)",
+                        "minimum": 1
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "description": "Maximum number of lines to return (default: 2000)",
+                        "minimum": 1
+                    },
+                    "line_numbers": {
+                        "type": "boolean",
+                        "description": "Prefix each line with its line number (default: true). Line numbers are not part of the file content.",
+                        "default": true
                     }
                 },
                 "required": ["path"]
@@ -61,7 +145,7 @@ impl Tool for FileReader {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!("🔧 Tool: read_file | Path: {}", args.path);
+        println!("🔧 Tool: read_file | Path: {} | Offset: {:?} | Limit: {:?}", args.path, args.offset, args.limit);
 
         let path = Path::new(&args.path);
 
@@ -70,20 +154,56 @@ impl Tool for FileReader {
             return Err(FileToolError::FileNotFound(args.path));
         }
 
-        match fs::read_to_string(&args.path).await {
-            Ok(content) => {
-                println!("✅ Tool: read_file | Success: Read {} bytes", content.len());
-                Ok(content)
-            },
+        let bytes = match fs::read(&args.path).await {
+            Ok(bytes) => bytes,
             Err(e) => {
                 println!("❌ Tool: read_file | Error: {}", e);
-                if e.kind() == std::io::ErrorKind::PermissionDenied {
+                return if e.kind() == std::io::ErrorKind::PermissionDenied {
                     Err(FileToolError::PermissionDenied(args.path))
                 } else {
                     Err(FileToolError::Io(e))
-                }
+                };
+            }
+        };
+
+        if is_binary(&bytes) {
+            println!("✅ Tool: read_file | Binary file, {} bytes", bytes.len());
+            return Ok(format!("{} is a binary file ({} bytes); its contents are not shown.", args.path, bytes.len()));
+        }
+
+        let content = String::from_utf8_lossy(&bytes);
+        let lines: Vec<&str> = content.lines().collect();
+        let total_lines = lines.len();
+
+        let offset = args.offset.unwrap_or(1).max(1);
+        let limit = args.limit.unwrap_or(DEFAULT_READ_LIMIT).max(1);
+
+        if offset > total_lines.max(1) {
+            println!("❌ Tool: read_file | Error: Offset past end of file");
+            return Err(FileToolError::InvalidArgument(format!("Offset {} is past the end of {} ({} lines)", offset, args.path, total_lines)));
+        }
+
+        let start = offset - 1;
+        let end = start.saturating_add(limit).min(total_lines);
+        let width = end.to_string().len().max(4);
+
+        let mut output = String::new();
+        for (index, line) in lines[start..end].iter().enumerate() {
+            if args.line_numbers {
+                output.push_str(&format!("{:>width$}\t{}\n", start + index + 1, line, width = width));
+            } else {
+                output.push_str(line);
+                output.push('\n');
             }
         }
+
+        if end < total_lines {
+            output.push_str(&format!("... truncated, {} more lines (file has {} lines). Call read_file with offset={} to continue.\n",
+                total_lines - end, total_lines, end + 1));
+        }
+
+        println!("✅ Tool: read_file | Success: Read lines {}-{} of {}", offset, end, total_lines);
+        Ok(output)
     }
 }
 
@@ -563,6 +683,31 @@ impl Tool for ListFiles {
     }
 }
 
+/// Common code file extensions included in codebase analysis
+pub(crate) const CODE_EXTENSIONS: &[&str] = &[
+    "rs", "py", "js", "ts", "jsx", "tsx", "java", "cpp", "c", "h", "hpp",
+    "go", "rb", "php", "cs", "swift", "kt", "scala", "r", "m", "mm",
+    "sql", "sh", "bash", "yaml", "yml", "json", "xml", "toml", "ini",
+    "md", "txt", "dockerfile", "makefile", "cmake"
+];
+
+/// Returns true if the file is a code file, by extension or by name
+pub(crate) fn is_code_file(path: &Path) -> bool {
+    let extension = path.extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let file_name = path.file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    CODE_EXTENSIONS.contains(&extension.as_str()) ||
+        file_name == "dockerfile" ||
+        file_name == "makefile" ||
+        file_name == "cmakelists.txt"
+}
+
 // Codebase Analysis Tool
 #[derive(Deserialize, JsonSchema)]
 pub struct AnalyzeCodebaseArgs {
@@ -574,10 +719,86 @@ pub struct AnalyzeCodebaseArgs {
     /// Maximum directory depth to traverse (default: 10)
     #[serde(default = "default_max_depth")]
     pub max_depth: usize,
+    /// Output format: "text" or "json" (default: text)
+    #[serde(default = "default_analysis_format")]
+    pub format: String,
 }
 
 fn default_max_file_size() -> usize { 10000 }
 fn default_max_depth() -> usize { 10 }
+fn default_analysis_format() -> String { "text".to_string() }
+
+/// A directory or file in the structure overview
+#[derive(Debug, Serialize)]
+pub struct TreeEntry {
+    /// Relative to the analyzed root; "." for the root itself
+    pub path: String,
+    pub depth: usize,
+    pub directory: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SourceFile {
+    pub path: String,
+    pub content: String,
+}
+
+/// Everything `analyze_codebase` gathers about a directory
+#[derive(Debug, Serialize)]
+pub struct CodebaseAnalysis {
+    pub root: String,
+    /// Directories to depth 3 and files to depth 2
+    pub structure: Vec<TreeEntry>,
+    pub summary: Option<CodebaseStats>,
+    /// Only present when some modules import others
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<DependencyGraph>,
+    pub config_files: Vec<SourceFile>,
+    /// Code files under the size limit; the summary lists the others
+    pub code_files: Vec<SourceFile>,
+}
+
+impl CodebaseAnalysis {
+    pub fn to_text(&self) -> String {
+        let mut text = format!("\n=== CODEBASE ANALYSIS FOR: {} ===\n\n", self.root);
+
+        text.push_str("\n=== PROJECT STRUCTURE ===\n");
+        for entry in &self.structure {
+            let name = match entry.depth {
+                0 => Path::new(&self.root).file_name().map(|name| name.to_string_lossy()).unwrap_or_else(|| self.root.as_str().into()),
+                _ => Path::new(&entry.path).file_name().map(|name| name.to_string_lossy()).unwrap_or_default(),
+            };
+            let icon = if entry.directory { "📁" } else { "📄" };
+            text.push_str(&format!("{}{} {}\n", "  ".repeat(entry.depth), icon, name));
+        }
+
+        if let Some(summary) = &self.summary {
+            text.push_str("\n=== PROJECT SUMMARY ===\n");
+            text.push_str(&summary.to_text());
+        }
+
+        if let Some(graph) = &self.dependencies {
+            text.push_str("\n=== MODULE DEPENDENCIES ===\n");
+            text.push_str(&graph.to_adjacency_text());
+        }
+
+        for file in &self.config_files {
+            text.push_str(&format!("\n\n=== FILE: {} ===\n", file.path));
+            text.push_str(&file.content);
+        }
+
+        text.push_str("\n\n=== CODE FILES ===\n");
+        for file in &self.code_files {
+            text.push_str(&format!("\n\n=== FILE: {} ===\n", file.path));
+            text.push_str(&file.content);
+        }
+        text
+    }
+
+    pub fn to_json(&self) -> Result<String, FileToolError> {
+        serde_json::to_string_pretty(self).map_err(|e| FileToolError::Io(std::io::Error::other(e)))
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct CodebaseAnalyzer;
@@ -591,7 +812,7 @@ impl Tool for CodebaseAnalyzer {
     async fn definition(&self, _prompt: String) -> ToolDefinition {
         ToolDefinition {
             name: Self::NAME.to_string(),
-            description: "Analyze a codebase directory and return a comprehensive summary including project structure, configuration files, and code content. Perfect for understanding unfamiliar codebases.".to_string(),
+            description: "Analyze a codebase directory and return a comprehensive summary including project structure, language and size statistics, build systems, entry points, configuration files, and code content. Perfect for understanding unfamiliar codebases.".to_string(),
             parameters: serde_json::json!({
                 "type": "object",
                 "properties": {
@@ -610,6 +831,12 @@ impl Tool for CodebaseAnalyzer {
                         "description": "Maximum directory depth to traverse (default: 10)",
                         "default": 10,
                         "minimum": 1
+                    },
+                    "format": {
+                        "type": "string",
+                        "enum": ["text", "json"],
+                        "description": "Output format (default: text)",
+                        "default": "text"
                     }
                 },
                 "required": ["path"]
@@ -618,8 +845,8 @@ impl Tool for CodebaseAnalyzer {
     }
 
     async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        println!("🔧 Tool: analyze_codebase | Path: {} | Max Size: {} | Max Depth: {}",
-                args.path, args.max_file_size, args.max_depth);
+        println!("🔧 Tool: analyze_codebase | Path: {} | Max Size: {} | Max Depth: {} | Format: {}",
+                args.path, args.max_file_size, args.max_depth, args.format);
 
         let path = Path::new(&args.path);
 
@@ -633,194 +860,80 @@ impl Tool for CodebaseAnalyzer {
             return Err(FileToolError::InvalidPath(format!("{} is not a directory", args.path)));
         }
 
-        let mut codebase_content = String::new();
-
-        // Common code file extensions to include
-        let code_extensions = vec![
-            "rs", "py", "js", "ts", "jsx", "tsx", "java", "cpp", "c", "h", "hpp",
-            "go", "rb", "php", "cs", "swift", "kt", "scala", "r", "m", "mm",
-            "sql", "sh", "bash", "yaml", "yml", "json", "xml", "toml", "ini",
-            "md", "txt", "dockerfile", "makefile", "cmake"
-        ];
-
-        // Directories to skip
-        let skip_dirs = vec![
-            ".git", "node_modules", "target", "dist", "build", "out", ".idea",
-            ".vscode", "__pycache__", ".pytest_cache", "venv", "env", ".env",
-            "vendor", "bower_components", ".next", ".nuxt", "coverage",
-            "out-shakespeare-char"
-        ];
-
-        codebase_content.push_str(&format!("\n=== CODEBASE ANALYSIS FOR: {} ===\n\n", args.path));
+        if !matches!(args.format.as_str(), "text" | "json") {
+            println!("❌ Tool: analyze_codebase | Error: Unknown format");
+            return Err(FileToolError::InvalidArgument(format!("Unknown format '{}', expected text or json", args.format)));
+        }
 
-        // Add project structure overview
-        codebase_content.push_str("\n=== PROJECT STRUCTURE ===\n");
-        let mut dir_structure = String::new();
+        let relative = |file_path: &Path| file_path.strip_prefix(path)
+            .unwrap_or(file_path)
+            .to_string_lossy()
+            .replace('\\', "/");
 
-        for entry in WalkDir::new(path)
+        // Project structure overview
+        let structure = WalkDir::new(path)
             .max_depth(3)
             .into_iter()
-            .filter_entry(|e| {
-                !skip_dirs.iter().any(|dir| e.path().to_string_lossy().contains(dir))
+            .filter_entry(|e| !is_ignored(e))
+            .flatten()
+            .filter(|entry| entry.file_type().is_dir() || entry.depth() <= 2)
+            .map(|entry| TreeEntry {
+                path: if entry.depth() == 0 { ".".to_string() } else { relative(entry.path()) },
+                depth: entry.depth(),
+                directory: entry.file_type().is_dir(),
             })
-        {
-            if let Ok(entry) = entry {
-                let depth = entry.depth();
-                let indent = "  ".repeat(depth);
-                let file_name = entry.file_name().to_string_lossy();
-
-                if entry.file_type().is_dir() {
-                    dir_structure.push_str(&format!("{}📁 {}\n", indent, file_name));
-                } else if depth <= 2 {
-                    dir_structure.push_str(&format!("{}📄 {}\n", indent, file_name));
-                }
-            }
-        }
-        codebase_content.push_str(&dir_structure);
+            .collect();
+
+        // Language, size and build system summary, and module dependencies; both walk the whole tree
+        let root = path.to_path_buf();
+        let (max_file_size, max_depth) = (args.max_file_size, args.max_depth);
+        let (summary, dependencies) = tokio::task::spawn_blocking(move || (
+            CodebaseStats::collect(&root, max_file_size, max_depth).ok(),
+            DependencyGraph::build(&root).ok().filter(|graph| !graph.edges.is_empty()),
+        ))
+            .await
+            .map_err(|e| FileToolError::Io(std::io::Error::other(e)))?;
 
         // Read important configuration files
-        let config_files = vec![
+        let config_names = vec![
             "Cargo.toml", "package.json", "requirements.txt", "setup.py",
             "pom.xml", "build.gradle", "CMakeLists.txt", "Makefile",
             "docker-compose.yml", "Dockerfile", ".gitignore", "README.md"
         ];
 
-        for config_file in &config_files {
+        let mut config_files = Vec::new();
+        for config_file in &config_names {
             let config_path = path.join(config_file);
-            if config_path.exists() {
-                match fs::read_to_string(&config_path).await {
-                    Ok(content) => {
-                        codebase_content.push_str(&format!("\n\n=== FILE: {} ===\n", config_file));
-                        codebase_content.push_str(&content);
-                    }
-                    Err(_) => continue,
-                }
+            if config_path.exists()
+                && let Ok(content) = fs::read_to_string(&config_path).await {
+                config_files.push(SourceFile { path: config_file.to_string(), content });
             }
         }
 
-        // Read actual code files
-        codebase_content.push_str("\n\n=== CODE FILES ===\n");
-
+        // Read actual code files; the summary lists the ones too large to include
+        let mut code_files = Vec::new();
         for entry in WalkDir::new(path)
             .max_depth(args.max_depth)
             .into_iter()
-            .filter_entry(|e| {
-                !skip_dirs.iter().any(|dir| e.path().to_string_lossy().contains(dir))
-            })
+            .filter_entry(|e| !is_ignored(e))
+            .flatten()
         {
-            if let Ok(entry) = entry {
-                if entry.file_type().is_file() {
-                    let file_path = entry.path();
-                    let extension = file_path.extension()
-                        .and_then(|ext| ext.to_str())
-                        .unwrap_or("");
-
-                    let file_name = file_path.file_name()
-                        .and_then(|name| name.to_str())
-                        .unwrap_or("");
-
-                    // Check if it's a code file by extension or by name
-                    let is_code_file = code_extensions.contains(&extension.to_lowercase().as_str()) ||
-                        file_name.to_lowercase() == "dockerfile" ||
-                        file_name.to_lowercase() == "makefile" ||
-                        file_name.to_lowercase() == "cmakelists.txt";
-
-                    if is_code_file {
-                        match fs::read_to_string(&file_path).await {
-                            Ok(content) => {
-                                // Only include files under specified size limit
-                                if content.len() < args.max_file_size {
-                                    let relative_path = file_path.strip_prefix(path)
-                                        .unwrap_or(&file_path)
-                                        .to_string_lossy();
-
-                                    codebase_content.push_str(&format!("\n\n=== FILE: {} ===\n", relative_path));
-                                    codebase_content.push_str(&content);
-                                }
-                            }
-                            Err(_) => continue,
-                        }
-                    }
-                }
+            let file_path = entry.path();
+            if entry.file_type().is_file() && is_code_file(file_path)
+                && let Ok(content) = fs::read_to_string(&file_path).await
+                && content.len() < args.max_file_size {
+                code_files.push(SourceFile { path: relative(file_path), content });
             }
         }
 
-        println!("✅ Tool: analyze_codebase | Success: Generated {} characters of analysis", codebase_content.len());
-        Ok(codebase_content)
-    }
-}
-
-// Context Management Tool
-#[derive(Deserialize, JsonSchema)]
-pub struct SetContextArgs {
-    /// Context key/name for the information
-    key: String,
-    /// Content to store in context
-    content: String,
-}
-
-#[derive(Debug, Clone)]
-pub struct ContextManager {
-    pub context: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, String>>>,
-}
-
-impl ContextManager {
-    pub fn new() -> Self {
-        Self {
-            context: std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
-        }
-    }
-
-    pub fn get_all_context(&self) -> String {
-        let context = self.context.lock().unwrap();
-        if context.is_empty() {
-            return "No context stored yet.".to_string();
-        }
-
-        let mut result = String::new();
-        result.push_str("=== CURRENT CONTEXT ===\n\n");
-
-        for (key, value) in context.iter() {
-            result.push_str(&format!("--- {} ---\n{}\n\n", key.to_uppercase(), value));
-        }
-
-        result
-    }
-}
-
-impl Tool for ContextManager {
-    const NAME: &'static str = "set_context";
-    type Error = FileToolError;
-    type Args = SetContextArgs;
-    type Output = String;
-
-    async fn definition(&self, _prompt: String) -> ToolDefinition {
-        ToolDefinition {
-            name: Self::NAME.to_string(),
-            description: "Store information in the conversation context for future reference. Perfect for storing codebase analysis, configurations, or any important data that should be remembered.".to_string(),
-            parameters: serde_json::json!({
-                "type": "object",
-                "properties": {
-                    "key": {
-                        "type": "string",
-                        "description": "Name/key for this context (e.g., 'codebase_analysis', 'project_config')"
-                    },
-                    "content": {
-                        "type": "string",
-                        "description": "Content to store in context"
-                    }
-                },
-                "required": ["key", "content"]
-            }),
-        }
-    }
-
-    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
-        let mut context = self.context.lock().unwrap();
-        context.insert(args.key.clone(), args.content.clone());
+        let analysis = CodebaseAnalysis { root: args.path.clone(), structure, summary, dependencies, config_files, code_files };
+        let rendered = match args.format.as_str() {
+            "json" => analysis.to_json()?,
+            _ => analysis.to_text(),
+        };
 
-        Ok(format!("Successfully stored '{}' in context. Context now contains {} items.",
-                   args.key, context.len()))
+        println!("✅ Tool: analyze_codebase | Success: Generated {} characters of analysis", rendered.len());
+        Ok(rendered)
     }
 }
 
@@ -920,13 +1033,13 @@ impl Tool for JobExecutor {
 
         // Create output file
         let mut log_file = tokio::fs::File::create(&output_file).await
-            .map_err(|e| FileToolError::Io(e))?;
+            .map_err(FileToolError::Io)?;
 
         // Write header
         let header = format!("Job: {}\nStart Time: {}\n----------------------------------------\n",
                            args.script_path, start_time);
         log_file.write_all(header.as_bytes()).await
-            .map_err(|e| FileToolError::Io(e))?;
+            .map_err(FileToolError::Io)?;
 
         // Determine executor
         let (program, script_args) = Self::determine_executor(&args.script_path)?;
@@ -950,7 +1063,7 @@ impl Tool for JobExecutor {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .spawn()
-            .map_err(|e| FileToolError::Io(e))?;
+            .map_err(FileToolError::Io)?;
 
         // Capture output
         let mut output_lines = Vec::new();
@@ -958,26 +1071,22 @@ impl Tool for JobExecutor {
 
         if let Some(stdout) = child.stdout.take() {
             let reader = BufReader::new(stdout);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    output_lines.push(format!("[STDOUT] {}", line));
-                }
+            for line in reader.lines().map_while(Result::ok) {
+                output_lines.push(format!("[STDOUT] {}", line));
             }
         }
 
         if let Some(stderr) = child.stderr.take() {
             let reader = BufReader::new(stderr);
-            for line in reader.lines() {
-                if let Ok(line) = line {
-                    error_lines.push(format!("[STDERR] {}", line));
-                }
+            for line in reader.lines().map_while(Result::ok) {
+                error_lines.push(format!("[STDERR] {}", line));
             }
         }
 
         // Wait for completion
         println!("⏳ Tool: execute_job | Waiting for completion...");
         let exit_status = child.wait()
-            .map_err(|e| FileToolError::Io(e))?;
+            .map_err(FileToolError::Io)?;
 
         let end_time = Local::now();
         let duration = start_system_time.elapsed()
@@ -992,14 +1101,14 @@ impl Tool for JobExecutor {
 
         for line in &all_output {
             log_file.write_all(format!("{}\n", line).as_bytes()).await
-                .map_err(|e| FileToolError::Io(e))?;
+                .map_err(FileToolError::Io)?;
         }
 
         // Write footer
         let footer = format!("----------------------------------------\nEnd Time: {}\nDuration: {:?}\nExit Code: {}\n",
                            end_time, duration, exit_status.code().unwrap_or(-1));
         log_file.write_all(footer.as_bytes()).await
-            .map_err(|e| FileToolError::Io(e))?;
+            .map_err(FileToolError::Io)?;
 
         let result = JobResult {
             start_time,
@@ -1009,7 +1118,7 @@ impl Tool for JobExecutor {
             output_file: output_file.clone(),
         };
 
-        let preview: Vec<String> = all_output.iter().rev().take(10).rev().map(|s| s.clone()).collect();
+        let preview: Vec<String> = all_output.iter().rev().take(10).rev().cloned().collect();
         let summary = format!(
             "Job execution completed!\n\n{}\n\nOutput preview (last 10 lines):\n{}",
             result,
@@ -1040,7 +1149,7 @@ impl JobExecutor {
             "js" => ("node".to_string(), vec![script_name]),
             "rb" => ("ruby".to_string(), vec![script_name]),
             "pl" => ("perl".to_string(), vec![script_name]),
-            "" | _ => {
+            _ => {
                 // Try to execute directly (might be executable)
                 (format!("./{}", script_name), vec![])
             }
@@ -1049,3 +1158,95 @@ impl JobExecutor {
         Ok((program, args))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp directory
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("llmo-file-tools-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn confine_accepts_paths_inside_the_root() {
+        let root = temp_dir("confine-inside");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let canonical = root.canonicalize().unwrap();
+
+        assert_eq!(confine(&root, &root.join("src")).unwrap(), canonical.join("src"));
+        // Missing files resolve through their nearest existing parent
+        assert_eq!(confine(&root, &root.join("src/new/file.rs")).unwrap(), canonical.join("src/new/file.rs"));
+        assert_eq!(confine(&root, &root.join("src/../notes.txt")).unwrap(), canonical.join("notes.txt"));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn confine_rejects_parent_and_absolute_paths() {
+        let root = temp_dir("confine-outside");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+
+        let error = confine(&root, &root.join("src/../../escape.txt")).unwrap_err();
+        assert!(matches!(error, FileToolError::PermissionDenied(_)), "{}", error);
+        let error = confine(&root, &root.join("missing/../../escape.txt")).unwrap_err();
+        assert!(matches!(error, FileToolError::PermissionDenied(_)), "{}", error);
+        let error = confine(&root, Path::new("/etc/passwd")).unwrap_err();
+        assert!(matches!(error, FileToolError::PermissionDenied(_)), "{}", error);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn confine_follows_symlinks() {
+        let root = temp_dir("confine-symlink");
+        let outside = temp_dir("confine-symlink-target");
+        std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::os::unix::fs::symlink(root.join("src"), root.join("inner")).unwrap();
+
+        let error = confine(&root, &root.join("link/secret.txt")).unwrap_err();
+        assert!(matches!(error, FileToolError::PermissionDenied(_)), "{}", error);
+        let error = confine(&root, &root.join("link/new.txt")).unwrap_err();
+        assert!(matches!(error, FileToolError::PermissionDenied(_)), "{}", error);
+        assert_eq!(confine(&root, &root.join("inner")).unwrap(), root.canonicalize().unwrap().join("src"));
+        let _ = std::fs::remove_dir_all(&root);
+        let _ = std::fs::remove_dir_all(&outside);
+    }
+
+    #[test]
+    fn confine_needs_an_existing_root() {
+        let root = std::env::temp_dir().join(format!("llmo-file-tools-missing-root-{}", std::process::id()));
+        let error = confine(&root, &root.join("file.txt")).unwrap_err();
+        assert!(matches!(error, FileToolError::InvalidPath(_)), "{}", error);
+    }
+
+    fn read_args(path: &Path, offset: Option<usize>, limit: Option<usize>) -> ReadFileArgs {
+        ReadFileArgs { path: path.display().to_string(), offset, limit, line_numbers: false }
+    }
+
+    #[tokio::test]
+    async fn read_file_with_huge_limit_reads_to_the_end() {
+        let dir = temp_dir("read-limit");
+        let file = dir.join("lines.txt");
+        std::fs::write(&file, "one\ntwo\nthree\n").unwrap();
+
+        let output = FileReader.call(read_args(&file, Some(2), Some(usize::MAX))).await.unwrap();
+        assert_eq!(output, "two\nthree\n");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn read_file_offset_past_the_end_is_an_invalid_argument() {
+        let dir = temp_dir("read-offset");
+        let file = dir.join("lines.txt");
+        std::fs::write(&file, "one\ntwo\n").unwrap();
+
+        let error = FileReader.call(read_args(&file, Some(5), None)).await.unwrap_err();
+        assert!(matches!(error, FileToolError::InvalidArgument(_)), "{}", error);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,103 +1,53 @@
 // src/gemini.rs
 use anyhow::Error;
-use mcp_core::types::ToolsListResponse;
-use rig::agent::{self, Agent};
-use rig::{completion::Prompt};
-use rig::client::ProviderClient;
 use rig::providers::gemini::completion::CompletionModel as GeminiCompletionModel;
-use crate::mcp_test::MCPClient;
-use crate::file_tools::{FileReader, FileWriter, FileEditor, CreateDirectory, ListFiles, CodeEditor, CodeInserter, CodebaseAnalyzer, JobExecutor};
-use rig::providers::gemini::{completion, Client as GeminiClient};
-use serde_json;
+use crate::agent_factory::ModelProvider;
+use crate::config::ProviderConfig;
+use crate::tool_protocol::ToolCalling;
+use crate::usage::ReportedUsage;
+use rig::providers::gemini::{completion, streaming, Client as GeminiClient};
+
+/// Google Gemini, configured from GEMINI_API_KEY and GEMINI_MODEL_NAME
+pub struct GeminiProvider {
+    pub settings: ProviderConfig,
+}
 
+impl ModelProvider for GeminiProvider {
+    type Model = GeminiCompletionModel;
 
-fn _get_model() -> GeminiCompletionModel{
-    let model_name = std::env::var("GEMINI_MODEL_NAME").unwrap_or_else(|_| "gemini-1.5-pro".to_string());
-    let client = GeminiClient::from_env();
-    let model = GeminiCompletionModel::new(client, model_name.as_str());
-    model
-}
+    const NAME: &'static str = "gemini";
+    const API_KEY_VAR: Option<&'static str> = Some("GEMINI_API_KEY");
 
-pub fn _get_agent(prompt:&str, mcp_config: Option<(MCPClient, ToolsListResponse)>) -> Agent<GeminiCompletionModel> {
-    let model = _get_model();
-    let mut builder = agent::AgentBuilder::new(model)
-        .preamble(prompt)
-        .temperature(0.2)
-        .max_tokens(1000)
-        // Add file manipulation tools
-        .tool(FileReader)
-        .tool(FileWriter)
-        .tool(FileEditor)
-        .tool(CodeEditor)
-        .tool(CodeInserter)
-        .tool(CreateDirectory)
-        .tool(ListFiles)
-        .tool(CodebaseAnalyzer)
-        .tool(JobExecutor);
+    fn model_name(&self) -> String {
+        self.settings.model.clone()
+            .unwrap_or_else(|| std::env::var("GEMINI_MODEL_NAME").unwrap_or_else(|_| "gemini-1.5-pro".to_string()))
+    }
 
-    // Add MCP tools dynamically if MCP is configured
-    builder = if let Some((mcp_client, tools)) = mcp_config {
-        tools.tools
-            .into_iter()
-            .fold(builder, |builder, tool| {
-                builder.mcp_tool(tool, mcp_client.inner.clone().into())
-            })
-    } else {
-        builder
-    };
-    builder.build()
-}
+    fn model(&self) -> Result<Self::Model, Error> {
+        let client = GeminiClient::new(&self.api_key(self.settings.api_key.as_ref())?);
+        Ok(GeminiCompletionModel::new(client, &self.model_name()))
+    }
 
-pub fn _get_agent_with_context(prompt:&str, mcp_config: Option<(MCPClient, ToolsListResponse)>, context_docs: Vec<String>) -> Agent<GeminiCompletionModel> {
-    let model = _get_model();
-    let mut builder = agent::AgentBuilder::new(model)
-        .preamble(prompt)
-        .temperature(0.2)
-        .max_tokens(1000)
-        // Add file manipulation tools
-        .tool(FileReader)
-        .tool(FileWriter)
-        .tool(FileEditor)
-        .tool(CodeEditor)
-        .tool(CodeInserter)
-        .tool(CreateDirectory)
-        .tool(ListFiles)
-        .tool(CodebaseAnalyzer)
-        .tool(JobExecutor);
+    fn temperature(&self) -> f64 {
+        self.settings.temperature.unwrap_or(0.2)
+    }
 
-    // Add context documents
-    for context_doc in context_docs {
-        builder = builder.context(&context_doc);
+    fn max_tokens(&self) -> u64 {
+        self.settings.max_tokens.unwrap_or(1000)
     }
 
-    // Add MCP tools dynamically if MCP is configured
-    builder = if let Some((mcp_client, tools)) = mcp_config {
-        tools.tools
-            .into_iter()
-            .fold(builder, |builder, tool| {
-                builder.mcp_tool(tool, mcp_client.inner.clone().into())
-            })
-    } else {
-        builder
-    };
-    builder.build()
-}
+    fn tool_calling(&self) -> ToolCalling {
+        self.settings.tool_calling.unwrap_or(ToolCalling::Native)
+    }
 
-pub async fn _run_gemini() -> Result<(), Error> {
-    let client = GeminiClient::from_env();
-    let agent = client
-        .agent(completion::GEMINI_1_5_PRO)
-        .preamble("You are precise and informative.")
-        .temperature(0.2)
-        .additional_params(serde_json::to_value(completion::gemini_api_types::GenerationConfig {
-            top_k: Some(1),
-            top_p: Some(0.95),
-            candidate_count: Some(1),
-            ..Default::default()
-        })?)
-        .build();
+    fn reported_usage(response: &completion::gemini_api_types::GenerateContentResponse) -> ReportedUsage {
+        response.usage_metadata.as_ref()
+            .map(|usage| ReportedUsage::new(usage.prompt_token_count as u64, usage.candidates_token_count as u64))
+            .unwrap_or_default()
+    }
 
-    let resp = agent.prompt("What is the capital of Japan?").await?;
-    println!("Gemini client says: {}", resp);
-    Ok(())
+    /// Streamed responses only carry the total
+    fn reported_streaming_usage(response: &streaming::StreamingCompletionResponse) -> ReportedUsage {
+        ReportedUsage { total: Some(response.usage_metadata.total_token_count as u64), ..Default::default() }
+    }
 }
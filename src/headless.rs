@@ -0,0 +1,225 @@
+//! Headless runs: one prompt, run to completion without a terminal, and a
+//! JSON report of what happened for scripts and CI jobs.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+use std::sync::Mutex;
+use walkdir::WalkDir;
+
+use crate::agent_factory::{AgentEvent, MaxTurnsExceeded};
+use crate::file_tools::is_ignored;
+use crate::model_selector::AgentWrapper;
+use crate::usage::UsageTotals;
+
+/// How a headless run ended, also its process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    /// The agent gave a final answer
+    Success = 0,
+    /// The agent or its provider failed
+    Error = 1,
+    /// No final answer within the tool-calling round limit
+    MaxTurns = 3,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolCallRecord {
+    pub name: String,
+    pub arguments: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    Created,
+    Modified,
+    Deleted,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangedFile {
+    /// Relative to the workspace
+    pub path: String,
+    pub change: FileChange,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Usage {
+    pub requests: u64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    /// Some of the counts were estimated locally
+    pub estimated: bool,
+    /// In US dollars; `None` when a model used has no configured price
+    pub cost: Option<f64>,
+}
+
+impl From<UsageTotals> for Usage {
+    fn from(totals: UsageTotals) -> Self {
+        Usage {
+            requests: totals.requests,
+            input_tokens: totals.input,
+            output_tokens: totals.output,
+            estimated: totals.estimated,
+            cost: (!totals.unpriced).then_some(totals.cost),
+        }
+    }
+}
+
+/// The JSON document a headless run prints
+#[derive(Debug, Serialize)]
+pub struct RunReport {
+    pub status: RunStatus,
+    pub exit_code: i32,
+    pub answer: Option<String>,
+    pub error: Option<String>,
+    pub tool_calls: Vec<ToolCallRecord>,
+    pub files_changed: Vec<ChangedFile>,
+    pub usage: Usage,
+}
+
+impl RunReport {
+    /// Report for a run that failed before the agent got the prompt
+    pub fn failed(error: &anyhow::Error) -> Self {
+        RunReport {
+            status: RunStatus::Error,
+            exit_code: RunStatus::Error as i32,
+            answer: None,
+            error: Some(format!("{:#}", error)),
+            tool_calls: Vec::new(),
+            files_changed: Vec::new(),
+            usage: UsageTotals::default().into(),
+        }
+    }
+}
+
+/// Size and content hash of every workspace file. Contents are compared
+/// because an edit that keeps the size can also keep the modification time.
+type Snapshot = BTreeMap<String, (u64, u64)>;
+
+fn snapshot(root: &Path) -> Snapshot {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| !is_ignored(entry))
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let contents = std::fs::read(entry.path()).ok()?;
+            let mut hasher = DefaultHasher::new();
+            contents.hash(&mut hasher);
+            let path = entry.path().strip_prefix(root).unwrap_or(entry.path()).to_string_lossy().replace('\\', "/");
+            Some((path, (contents.len() as u64, hasher.finish())))
+        })
+        .collect()
+}
+
+fn changed_files(before: &Snapshot, after: &Snapshot) -> Vec<ChangedFile> {
+    let mut changes = Vec::new();
+    for (path, state) in after {
+        match before.get(path) {
+            None => changes.push(ChangedFile { path: path.clone(), change: FileChange::Created }),
+            Some(old) if old != state => changes.push(ChangedFile { path: path.clone(), change: FileChange::Modified }),
+            Some(_) => {}
+        }
+    }
+    for path in before.keys().filter(|path| !after.contains_key(*path)) {
+        changes.push(ChangedFile { path: path.clone(), change: FileChange::Deleted });
+    }
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+/// Run `prompt` to completion on `agent` and report on it. Tool activity is
+/// still logged as it happens, so callers should keep stdout for the report.
+pub async fn run(agent: &AgentWrapper, prompt: &str, workspace: &Path, max_turns: usize) -> RunReport {
+    let before = snapshot(workspace);
+    let tool_calls = Mutex::new(Vec::<ToolCallRecord>::new());
+
+    agent.usage().begin_turn();
+    let result = agent.stream_chat(prompt, Vec::new(), max_turns, &|event| {
+        let Ok(mut tool_calls) = tool_calls.lock() else {
+            return;
+        };
+        // Results arrive in the order the calls were made
+        let pending = |tool_calls: &mut Vec<ToolCallRecord>, name: &str| {
+            tool_calls.iter_mut().position(|call| call.name == name && call.result.is_none() && call.error.is_none())
+        };
+        match event {
+            AgentEvent::ToolCall { name, arguments } => tool_calls.push(ToolCallRecord {
+                name: name.to_string(),
+                arguments: arguments.clone(),
+                result: None,
+                error: None,
+            }),
+            AgentEvent::ToolResult { name, output } => {
+                if let Some(index) = pending(&mut tool_calls, name) {
+                    // Tool outputs arrive JSON encoded
+                    let output = serde_json::from_str::<String>(output).unwrap_or_else(|_| output.to_string());
+                    tool_calls[index].result = Some(output);
+                }
+            }
+            AgentEvent::ToolError { name, error } => {
+                if let Some(index) = pending(&mut tool_calls, name) {
+                    tool_calls[index].error = Some(error.to_string());
+                }
+            }
+            AgentEvent::Text(_) | AgentEvent::Reasoning(_) => {}
+        }
+    }).await;
+
+    let (status, answer, error) = match result {
+        Ok(answer) => (RunStatus::Success, Some(answer), None),
+        Err(e) if e.is::<MaxTurnsExceeded>() => (RunStatus::MaxTurns, None, Some(e.to_string())),
+        Err(e) => (RunStatus::Error, None, Some(e.to_string())),
+    };
+    RunReport {
+        status,
+        exit_code: status as i32,
+        answer,
+        error,
+        tool_calls: tool_calls.into_inner().unwrap_or_default(),
+        files_changed: changed_files(&before, &snapshot(workspace)),
+        usage: agent.usage().turn().into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(root: &Path, before: &Snapshot) -> Vec<(String, FileChange)> {
+        changed_files(before, &snapshot(root)).into_iter().map(|file| (file.path, file.change)).collect()
+    }
+
+    #[test]
+    fn same_size_edit_counts_as_modified() {
+        let root = std::env::temp_dir().join(format!("llmo-headless-changes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn a() {}\n").unwrap();
+        std::fs::write(root.join("old.txt"), "old").unwrap();
+
+        let before = snapshot(&root);
+        assert!(changes(&root, &before).is_empty());
+
+        // Same size, and written well within the same second
+        std::fs::write(root.join("src/main.rs"), "fn b() {}\n").unwrap();
+        std::fs::write(root.join("new.txt"), "new").unwrap();
+        std::fs::remove_file(root.join("old.txt")).unwrap();
+
+        assert_eq!(changes(&root, &before), [
+            ("new.txt".to_string(), FileChange::Created),
+            ("old.txt".to_string(), FileChange::Deleted),
+            ("src/main.rs".to_string(), FileChange::Modified),
+        ]);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,29 @@
+//! LLMO's providers, tools, MCP client and tool host, and the agent plumbing
+//! the `LLMO` and `llmo-stub` binaries are built from.
+
+pub mod gemini;
+pub mod anthropic;
+pub mod ollama;
+pub mod mock;
+pub mod agent_factory;
+pub mod local;
+pub mod mcp_client;
+pub mod mcp_transport;
+pub mod mcp_servers;
+pub mod mcp_host;
+pub mod model_selector;
+pub mod file_tools;
+pub mod search_tools;
+pub mod symbol_index;
+pub mod lsp_client;
+pub mod navigation_tools;
+pub mod dependency_graph;
+pub mod codebase_stats;
+pub mod context_workflow;
+pub mod config;
+pub mod retry;
+pub mod usage;
+pub mod tool_protocol;
+pub mod reasoning;
+pub mod headless;
+pub mod stub_server;
@@ -1,84 +1,62 @@
-use rig::agent::Agent;
-use rig::agent::AgentBuilder;
+use anyhow::Error;
 use rig::client::{CompletionClient};
 use rig::providers::openai;
 
-use crate::mcp_test::MCPClient;
-use crate::file_tools::{FileReader, FileWriter, FileEditor, CreateDirectory, ListFiles, CodeEditor, CodeInserter, CodebaseAnalyzer, JobExecutor};
-use mcp_core::types::ToolsListResponse;
+use crate::agent_factory::ModelProvider;
+use crate::config::ProviderConfig;
+use crate::tool_protocol::ToolCalling;
+use crate::usage::ReportedUsage;
 
-
-fn get_model() -> openai::CompletionModel {
-    let api_key = std::env::var("OPENAI_API_KEY").expect("OPENAI_API_KEY must be set");
-    let api_base = std::env::var("OPENAI_API_BASE").unwrap_or_else(|_| "http://localhost:1234/".into());
-    let client = openai::Client::from_url(&api_key, &api_base);
-    let model = client.completion_model("Qwen/Qwen3-32B");
-    model
+/// OpenAI-compatible local server (LM Studio, vLLM, ...)
+pub struct LocalProvider {
+    pub settings: ProviderConfig,
 }
 
-pub fn get_agent(prompt: &str, mcp_config: Option<(MCPClient, ToolsListResponse)>) -> Agent<openai::CompletionModel> {
-    let model = get_model();
-    let builder = AgentBuilder::new(model)
-        .preamble(prompt)
-        .temperature(0.2)
-        .max_tokens(3000)
-        // Add file manipulation tools
-        .tool(FileReader)
-        .tool(FileWriter)
-        .tool(FileEditor)
-        .tool(CodeEditor)
-        .tool(CodeInserter)
-        .tool(CreateDirectory)
-        .tool(ListFiles)
-        .tool(CodebaseAnalyzer)
-        .tool(JobExecutor);
+impl ModelProvider for LocalProvider {
+    type Model = openai::CompletionModel;
 
-    // Add MCP tools dynamically if MCP is configured
-    let builder = if let Some((mcp_client, tools)) = mcp_config {
-        tools.tools
-            .into_iter()
-            .fold(builder, |builder, tool| {
-                builder.mcp_tool(tool, mcp_client.inner.clone().into())
-            })
-    } else {
-        builder
-    };
+    const NAME: &'static str = "local";
+    const API_KEY_VAR: Option<&'static str> = Some("OPENAI_API_KEY");
 
-    builder.build()
-}
+    fn model_name(&self) -> String {
+        self.settings.model.clone().unwrap_or_else(|| "Qwen/Qwen3-32B".to_string())
+    }
 
-pub fn get_agent_with_context(prompt: &str, mcp_config: Option<(MCPClient, ToolsListResponse)>, context_docs: Vec<String>) -> Agent<openai::CompletionModel> {
-    let model = get_model();
-    let mut builder = AgentBuilder::new(model)
-        .preamble(prompt)
-        .temperature(0.2)
-        .max_tokens(3000)
-        // Add file manipulation tools
-        .tool(FileReader)
-        .tool(FileWriter)
-        .tool(FileEditor)
-        .tool(CodeEditor)
-        .tool(CodeInserter)
-        .tool(CreateDirectory)
-        .tool(ListFiles)
-        .tool(CodebaseAnalyzer)
-        .tool(JobExecutor);
+    fn model(&self) -> Result<Self::Model, Error> {
+        let api_key = self.api_key(self.settings.api_key.as_ref())?;
+        let api_base = self.settings.api_base.clone()
+            .unwrap_or_else(|| std::env::var("OPENAI_API_BASE").unwrap_or_else(|_| "http://localhost:1234/".into()));
+        let client = openai::Client::from_url(&api_key, &api_base);
+        Ok(client.completion_model(&self.model_name()))
+    }
 
-    // Add context documents
-    for context_doc in context_docs {
-        builder = builder.context(&context_doc);
+    fn temperature(&self) -> f64 {
+        self.settings.temperature.unwrap_or(0.2)
     }
 
-    // Add MCP tools dynamically if MCP is configured
-    let builder = if let Some((mcp_client, tools)) = mcp_config {
-        tools.tools
-            .into_iter()
-            .fold(builder, |builder, tool| {
-                builder.mcp_tool(tool, mcp_client.inner.clone().into())
-            })
-    } else {
-        builder
-    };
+    fn max_tokens(&self) -> u64 {
+        self.settings.max_tokens.unwrap_or(3000)
+    }
+
+    /// Local models often lack function calling, so they are probed unless configured
+    fn tool_calling(&self) -> ToolCalling {
+        self.settings.tool_calling.unwrap_or(ToolCalling::Auto)
+    }
 
-    builder.build()
+    fn reported_usage(response: &openai::CompletionResponse) -> ReportedUsage {
+        response.usage.as_ref().map(openai_usage).unwrap_or_default()
+    }
+
+    fn reported_streaming_usage(response: &openai::StreamingCompletionResponse) -> ReportedUsage {
+        openai_usage(&response.usage)
+    }
+}
+
+/// OpenAI only reports prompt and total tokens. Servers that ignore
+/// `stream_options.include_usage` leave both at zero.
+fn openai_usage(usage: &openai::Usage) -> ReportedUsage {
+    if usage.total_tokens == 0 {
+        return ReportedUsage::default();
+    }
+    ReportedUsage { input: Some(usage.prompt_tokens as u64), output: None, total: Some(usage.total_tokens as u64) }
 }
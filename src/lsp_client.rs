@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::time::timeout;
+use url::Url;
+
+use crate::file_tools::FileToolError;
+use crate::symbol_index::{display_path, Language, SymbolLocation};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Attempts (two seconds apart) while a server is still indexing the workspace
+const INDEXING_RETRIES: usize = 30;
+
+/// Language servers we know how to launch over stdio
+pub fn server_for(language: Language) -> Option<(&'static str, &'static [&'static str])> {
+    match language {
+        Language::Rust => Some(("rust-analyzer", &[])),
+        Language::Python => Some(("pyright-langserver", &["--stdio"])),
+        Language::JavaScript | Language::TypeScript | Language::Tsx => Some(("typescript-language-server", &["--stdio"])),
+        Language::Go => Some(("gopls", &[])),
+        Language::C | Language::Cpp => Some(("clangd", &[])),
+        Language::Java => None,
+    }
+}
+
+/// Returns true if `program` is an executable on PATH
+pub fn is_installed(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+        .unwrap_or(false)
+}
+
+/// A language server shared by the lookups in one workspace
+pub type SharedClient = Arc<tokio::sync::Mutex<LspClient>>;
+
+/// Running servers per workspace root and program, so only the first lookup
+/// pays for startup and indexing
+static SERVERS: LazyLock<Mutex<HashMap<(PathBuf, &'static str), SharedClient>>> = LazyLock::new(Mutex::default);
+
+/// The server for `program` in `root`, started on first use and restarted if it exited
+pub async fn shared_client(program: &'static str, args: &[&str], root: &Path) -> Result<SharedClient, FileToolError> {
+    let key = (std::fs::canonicalize(root)?, program);
+    let cached = SERVERS.lock().unwrap().get(&key).cloned();
+    if let Some(client) = cached {
+        // A server busy with another lookup is running
+        let running = client.try_lock().map_or(true, |mut client| client.is_running());
+        if running {
+            return Ok(client);
+        }
+        println!("⚠️  Tool: language server | {} exited, restarting it", program);
+    }
+
+    println!("🛰️  Tool: language server | Starting {}", program);
+    let client = Arc::new(tokio::sync::Mutex::new(LspClient::start(program, args, &key.0).await?));
+    SERVERS.lock().unwrap().insert(key, client.clone());
+    Ok(client)
+}
+
+fn lsp_error(message: impl Into<String>) -> FileToolError {
+    FileToolError::Io(std::io::Error::other(message.into()))
+}
+
+/// Minimal LSP client speaking JSON-RPC over a language server's stdin/stdout
+pub struct LspClient {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i64,
+    root: PathBuf,
+}
+
+impl LspClient {
+    pub async fn start(program: &str, args: &[&str], root: &Path) -> Result<Self, FileToolError> {
+        let root = std::fs::canonicalize(root)?;
+        let mut child = Command::new(program)
+            .args(args)
+            .current_dir(&root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let stdin = child.stdin.take().ok_or_else(|| lsp_error("Language server stdin unavailable"))?;
+        let stdout = child.stdout.take().ok_or_else(|| lsp_error("Language server stdout unavailable"))?;
+
+        let mut client = Self { child, stdin, stdout: BufReader::new(stdout), next_id: 1, root };
+
+        let root_uri = Url::from_directory_path(&client.root)
+            .map_err(|_| lsp_error("Invalid workspace root"))?;
+        client.request("initialize", json!({
+            "processId": std::process::id(),
+            "rootUri": root_uri.as_str(),
+            "workspaceFolders": [{ "uri": root_uri.as_str(), "name": "workspace" }],
+            "capabilities": {
+                "textDocument": {
+                    "definition": { "linkSupport": false },
+                    "references": {}
+                }
+            }
+        })).await?;
+        client.notify("initialized", json!({})).await?;
+
+        Ok(client)
+    }
+
+    async fn send(&mut self, message: Value) -> Result<(), FileToolError> {
+        let body = message.to_string();
+        let frame = format!("Content-Length: {}\r\n\r\n{}", body.len(), body);
+        self.stdin.write_all(frame.as_bytes()).await?;
+        self.stdin.flush().await?;
+        Ok(())
+    }
+
+    async fn read_message(&mut self) -> Result<Value, FileToolError> {
+        let mut content_length = None;
+        loop {
+            let mut header = String::new();
+            if self.stdout.read_line(&mut header).await? == 0 {
+                return Err(lsp_error("Language server closed the connection"));
+            }
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some(length) = header.strip_prefix("Content-Length:") {
+                content_length = length.trim().parse::<usize>().ok();
+            }
+        }
+
+        let length = content_length.ok_or_else(|| lsp_error("Missing Content-Length header"))?;
+        let mut body = vec![0; length];
+        self.stdout.read_exact(&mut body).await?;
+        serde_json::from_slice(&body).map_err(|e| lsp_error(e.to_string()))
+    }
+
+    pub async fn notify(&mut self, method: &str, params: Value) -> Result<(), FileToolError> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params })).await
+    }
+
+    /// Send a request and wait for its response message, which may carry an error
+    async fn request_message(&mut self, method: &str, params: Value) -> Result<Value, FileToolError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })).await?;
+
+        timeout(REQUEST_TIMEOUT, async {
+            loop {
+                let message = self.read_message().await?;
+                match (message.get("id"), message.get("method")) {
+                    // Requests from the server (configuration, progress tokens); reply with null
+                    (Some(server_id), Some(_)) => {
+                        let reply = json!({ "jsonrpc": "2.0", "id": server_id, "result": null });
+                        self.send(reply).await?;
+                    }
+                    (Some(response_id), None) if response_id.as_i64() == Some(id) => return Ok(message),
+                    _ => continue,
+                }
+            }
+        })
+        .await
+        .map_err(|_| lsp_error(format!("Language server timed out on {}", method)))?
+    }
+
+    pub async fn request(&mut self, method: &str, params: Value) -> Result<Value, FileToolError> {
+        let response = self.request_message(method, params).await?;
+        if let Some(error) = response.get("error") {
+            return Err(lsp_error(format!("{} failed: {}", method, error)));
+        }
+        Ok(response.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    pub async fn open_document(&mut self, path: &Path, language: Language) -> Result<Url, FileToolError> {
+        let absolute = std::fs::canonicalize(path)?;
+        let text = tokio::fs::read_to_string(&absolute).await?;
+        let uri = Url::from_file_path(&absolute).map_err(|_| lsp_error("Invalid document path"))?;
+        self.notify("textDocument/didOpen", json!({
+            "textDocument": { "uri": uri.as_str(), "languageId": language.lsp_id(), "version": 1, "text": text }
+        })).await?;
+        Ok(uri)
+    }
+
+    pub async fn close_document(&mut self, uri: &Url) -> Result<(), FileToolError> {
+        self.notify("textDocument/didClose", json!({ "textDocument": { "uri": uri.as_str() } })).await
+    }
+
+    /// Run a position request (definition, references), retrying while the server is still indexing
+    async fn position_request(&mut self, method: &str, uri: &Url, line: usize, character: usize, extra: Value) -> Result<Vec<SymbolLocation>, FileToolError> {
+        let mut params = json!({
+            "textDocument": { "uri": uri.as_str() },
+            "position": { "line": line, "character": character }
+        });
+        if let (Some(params), Some(extra)) = (params.as_object_mut(), extra.as_object()) {
+            params.extend(extra.clone());
+        }
+
+        for _ in 0..INDEXING_RETRIES {
+            let response = self.request_message(method, params.clone()).await?;
+            match response.get("error") {
+                // ContentModified / ServerCancelled are returned while the workspace is loading
+                Some(error) if matches!(error["code"].as_i64(), Some(-32801) | Some(-32802)) => {
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                }
+                Some(error) => return Err(lsp_error(format!("{} failed: {}", method, error))),
+                // An empty result means the symbol has no definition or references
+                None => return Ok(Self::locations(&response["result"])),
+            }
+        }
+        Err(lsp_error(format!("{} failed: the language server was still indexing", method)))
+    }
+
+    pub async fn definition(&mut self, uri: &Url, line: usize, character: usize) -> Result<Vec<SymbolLocation>, FileToolError> {
+        self.position_request("textDocument/definition", uri, line, character, json!({})).await
+    }
+
+    pub async fn references(&mut self, uri: &Url, line: usize, character: usize) -> Result<Vec<SymbolLocation>, FileToolError> {
+        self.position_request("textDocument/references", uri, line, character,
+            json!({ "context": { "includeDeclaration": true } })).await
+    }
+
+    /// Convert a Location, Location[] or LocationLink[] result into workspace locations
+    fn locations(result: &Value) -> Vec<SymbolLocation> {
+        let items = match result {
+            Value::Array(items) => items.clone(),
+            Value::Null => Vec::new(),
+            single => vec![single.clone()],
+        };
+
+        let cwd = std::env::current_dir().and_then(std::fs::canonicalize).unwrap_or_default();
+        items.iter().filter_map(|item| {
+            let uri = item.get("uri").or_else(|| item.get("targetUri"))?.as_str()?;
+            let range = item.get("range").or_else(|| item.get("targetSelectionRange"))?;
+            let line = range["start"]["line"].as_u64()? as usize;
+            let character = range["start"]["character"].as_u64()? as usize;
+
+            let path = Url::parse(uri).ok()?.to_file_path().ok()?;
+            let line_text = std::fs::read_to_string(&path).ok()
+                .and_then(|content| content.lines().nth(line).map(str::to_string))
+                .unwrap_or_default();
+            let column = utf16_to_char_column(&line_text, character);
+            let relative = path.strip_prefix(&cwd).map(Path::to_path_buf).unwrap_or(path);
+
+            Some(SymbolLocation {
+                path: display_path(&relative),
+                line: line + 1,
+                column: column + 1,
+                line_text: line_text.trim().to_string(),
+            })
+        }).collect()
+    }
+
+    fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+/// LSP positions count UTF-16 code units by default
+pub fn char_to_utf16_column(line: &str, column: usize) -> usize {
+    line.chars().take(column).map(char::len_utf16).sum()
+}
+
+fn utf16_to_char_column(line: &str, utf16_column: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in line.chars().enumerate() {
+        if units >= utf16_column {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    line.chars().count()
+}
@@ -1,62 +1,681 @@
 use anyhow::Error;
+use clap::{Parser, Subcommand};
 use dotenv::dotenv;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use chrono::{DateTime, Local};
-
-mod gemini;
-mod local;
-mod mcp_test;
-mod model_selector;
-mod file_tools;
-mod context_workflow;
+use std::path::{Path, PathBuf};
 
 use std::io::{self, Write};
-use crate::mcp_test::MCPClient;
-use crate::model_selector::AgentWrapper;
-use crate::context_workflow::ContextWorkflow;
-use rig::completion::{Message, Prompt, Chat};
+use std::process::ExitCode;
+use std::net::SocketAddr;
+use llmo::{agent_factory, codebase_stats, file_tools, headless, mcp_host, mcp_servers, model_selector};
+use llmo::mcp_host::{ServeTransport, ToolHost};
+use llmo::mcp_servers::{namespaced, McpServer, NAMESPACE_SEPARATOR};
+use llmo::model_selector::{AgentRouter, AgentWrapper};
+use llmo::context_workflow::ContextWorkflow;
+use llmo::config::{Config, ConfigOverrides};
+use llmo::headless::RunReport;
+use llmo::agent_factory::{AgentEvent, MAX_TOOL_TURNS};
+use llmo::usage::{UsageTotals, UsageTracker};
+use llmo::reasoning::{parse_thinking_toggle, split_reasoning, with_thinking_switch, ThinkingConfig, ThinkingDisplay};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use rig::completion::Message;
+use rig::tool::Tool;
+
+/// Context-aware coding assistant
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Config file (default: llmo.toml if present)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+    /// Profile from the config file to apply
+    #[arg(long, global = true)]
+    profile: Option<String>,
+    /// Provider to use: local, gemini, anthropic, ollama or mock
+    #[arg(long, global = true)]
+    provider: Option<String>,
+    /// Model name for the active provider
+    #[arg(long, global = true)]
+    model: Option<String>,
+    /// Sampling temperature
+    #[arg(long, global = true)]
+    temperature: Option<f64>,
+    /// Maximum tokens per response
+    #[arg(long, global = true)]
+    max_tokens: Option<u64>,
+    /// Codebase directory to work on
+    #[arg(long, global = true)]
+    workspace: Option<PathBuf>,
+    /// Job execution script
+    #[arg(long, global = true)]
+    exec_script: Option<PathBuf>,
+    /// File holding the system prompt
+    #[arg(long, global = true)]
+    system_prompt: Option<PathBuf>,
+    /// Wait for the complete answer instead of streaming it
+    #[arg(long, global = true)]
+    no_stream: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Chat with the agent about the workspace (default)
+    Chat,
+    /// Answer one prompt and exit
+    Ask {
+        /// The prompt
+        #[arg(required = true)]
+        prompt: Vec<String>,
+    },
+    /// Run a prompt to completion without a terminal and print a JSON report:
+    /// the answer, tool calls, files changed and token usage. Exits with 0 on
+    /// success, 1 when the agent fails, 2 without a prompt and 3 when the
+    /// round limit is reached.
+    Headless {
+        /// The prompt; read from stdin when left out or "-"
+        prompt: Vec<String>,
+        /// Tool-calling rounds allowed before giving up
+        #[arg(long, default_value_t = MAX_TOOL_TURNS)]
+        max_turns: usize,
+    },
+    /// Print a report of a codebase: languages, largest files, build systems, entry points
+    Analyze {
+        /// Codebase directory (default: the workspace)
+        path: Option<PathBuf>,
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Execute a job script and print its results
+    Run {
+        /// Script to execute (default: the configured exec_script)
+        script: Option<PathBuf>,
+    },
+    /// Serve the built-in tools to other MCP clients and LLMO processes
+    Serve {
+        /// How clients connect: sse (HTTP) or stdio
+        #[arg(long, value_enum, default_value = "sse")]
+        transport: ServeTransport,
+        /// Address to listen on with --transport sse
+        #[arg(long, default_value = "127.0.0.1:3100")]
+        listen: SocketAddr,
+    },
+}
 
-fn read_text_file(file: &str) -> Result<String, Error> {
+impl Args {
+    fn overrides(&self) -> ConfigOverrides {
+        ConfigOverrides {
+            provider: self.provider.clone(),
+            model: self.model.clone(),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            workspace: self.workspace.clone(),
+            exec_script: self.exec_script.clone(),
+            system_prompt_file: self.system_prompt.clone(),
+            stream: self.no_stream.then_some(false),
+        }
+    }
+}
+
+fn read_text_file(file: &Path) -> Result<String, Error> {
     fs::read_to_string(file)
-        .map_err(|e| anyhow::anyhow!("Failed to read prompt.txt: {}", e))
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", file.display(), e))
 }
 
+/// Longest tool argument string shown while streaming
+const TOOL_ARGS_PREVIEW: usize = 200;
 
-pub async fn run_chat_with_tools(agent: AgentWrapper) -> Result<(), Error> {
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
 
-    println!("Prompting RIG agent:");
-    let mut history = Vec::new();
+/// Print a complete answer's reasoning according to `display`
+fn print_reasoning(reasoning: &str, display: ThinkingDisplay) {
+    if reasoning.is_empty() {
+        return;
+    }
+    match display {
+        ThinkingDisplay::Show => println!("💭 {}{}{}", DIM, reasoning, RESET),
+        ThinkingDisplay::Collapsed => println!("💭 Thought for {} words", reasoning.split_whitespace().count()),
+        ThinkingDisplay::Hidden => {}
+    }
+}
 
-    let question1 = "What database tables do we have?";
-    println!("Question: {}", question1);
-    let response = agent.prompt("what database tables do we have?").await?;
-    println!("Agent response: {:?}", response);
-    history.push(Message::user(question1));
-    history.push(Message::assistant(response));
-    println!("------------------------------------------");
-    let question2 = "What is inside the tables of the database?";
-    println!("Question: {}", question2);
-    let response = agent.prompt("what database tables do we have in `my_dummy_db`?").await?;
-    println!("Agent response: {:?}", response);
+/// Prints streamed text and tool activity as it arrives
+struct StreamPrinter {
+    /// True when the next text chunk starts a new assistant paragraph
+    at_line_start: AtomicBool,
+    display: ThinkingDisplay,
+    /// Reasoning of the block being streamed, `None` outside of one
+    reasoning: Mutex<Option<String>>,
+}
 
-    history.push(Message::user(question2));
-    history.push(Message::assistant(response));
+impl StreamPrinter {
+    fn new(display: ThinkingDisplay) -> Self {
+        StreamPrinter { at_line_start: AtomicBool::new(true), display, reasoning: Mutex::new(None) }
+    }
 
-    println!("------------------------------------------");
+    /// Close a reasoning block once something else arrives
+    fn end_reasoning(&self) {
+        let Some(reasoning) = self.reasoning.lock().ok().and_then(|mut reasoning| reasoning.take()) else {
+            return;
+        };
+        match self.display {
+            ThinkingDisplay::Show if reasoning.ends_with('\n') => print!("{}", RESET),
+            ThinkingDisplay::Show => println!("{}", RESET),
+            ThinkingDisplay::Collapsed => println!(" ({} words)", reasoning.split_whitespace().count()),
+            ThinkingDisplay::Hidden => {}
+        }
+    }
 
-    Ok(())
+    fn render(&self, event: AgentEvent<'_>) {
+        if !matches!(event, AgentEvent::Reasoning(_)) {
+            self.end_reasoning();
+        }
+        match event {
+            AgentEvent::Reasoning(text) => {
+                let Ok(mut reasoning) = self.reasoning.lock() else {
+                    return;
+                };
+                if reasoning.is_none() {
+                    if !self.at_line_start.swap(true, Ordering::Relaxed) {
+                        println!();
+                    }
+                    match self.display {
+                        ThinkingDisplay::Show => print!("💭 {}", DIM),
+                        ThinkingDisplay::Collapsed => print!("💭 Thinking…"),
+                        ThinkingDisplay::Hidden => {}
+                    }
+                }
+                reasoning.get_or_insert_default().push_str(text);
+                if self.display == ThinkingDisplay::Show {
+                    print!("{}", text);
+                }
+                let _ = io::stdout().flush();
+            }
+            AgentEvent::Text(text) => {
+                if text.is_empty() {
+                    return;
+                }
+                if self.at_line_start.swap(false, Ordering::Relaxed) {
+                    print!("Assistant: ");
+                }
+                print!("{}", text);
+                let _ = io::stdout().flush();
+            }
+            AgentEvent::ToolCall { name, arguments } => {
+                let mut arguments = arguments.to_string();
+                if arguments.chars().count() > TOOL_ARGS_PREVIEW {
+                    arguments = arguments.chars().take(TOOL_ARGS_PREVIEW).collect::<String>() + "…";
+                }
+                if !self.at_line_start.swap(true, Ordering::Relaxed) {
+                    println!();
+                }
+                println!("🛠️  Calling {} {}", name, arguments);
+            }
+            AgentEvent::ToolResult { name, output } => {
+                // Tool outputs arrive JSON encoded
+                let output = serde_json::from_str::<String>(output).unwrap_or_else(|_| output.to_string());
+                println!("📎 {} returned {} lines ({} bytes)", name, output.lines().count(), output.len());
+            }
+            AgentEvent::ToolError { name, error } => {
+                println!("⚠️  {} failed: {}", name, error);
+            }
+        }
+    }
+}
+
+/// " | Cost: $0.0123", or nothing if no price is configured for the models used
+fn format_cost(totals: &UsageTotals) -> String {
+    if !totals.unpriced {
+        format!(" | Cost: ${:.4}", totals.cost)
+    } else if totals.cost > 0.0 {
+        format!(" | Cost: ${:.4} (some models have no price)", totals.cost)
+    } else {
+        String::new()
+    }
+}
+
+fn print_turn_usage(usage: &UsageTracker) {
+    let turn = usage.turn();
+    if turn.requests == 0 {
+        return;
+    }
+    let session = usage.session();
+    println!("📊 Tokens: {} this turn | {} this session{}", turn, session, format_cost(&session));
+}
+
+/// Session totals, broken down per agent when more than one was used
+fn print_session_usage(usage: &UsageTracker) {
+    let session = usage.session();
+    if session.requests == 0 {
+        return;
+    }
+    println!("📊 Session usage: {} over {} requests{}", session, session.requests, format_cost(&session));
+    let agents = usage.agents();
+    if agents.len() > 1 {
+        for (agent, totals) in agents {
+            println!("   • {}: {} over {} requests{}", agent, totals, totals.requests, format_cost(&totals));
+        }
+    }
+}
+
+/// Split a leading "@route" off the input
+fn parse_route(input: &str) -> (Option<&str>, &str) {
+    match input.strip_prefix('@').and_then(|rest| rest.split_once(char::is_whitespace)) {
+        Some((route, rest)) if !route.is_empty() => (Some(route), rest.trim_start()),
+        _ => (None, input),
+    }
+}
+
+/// Default file for /save and /load
+const SESSION_FILE: &str = "llmo-session.json";
+
+/// Longest message shown by /history
+const HISTORY_PREVIEW: usize = 200;
+
+/// A chat saved with /save
+#[derive(Serialize, Deserialize)]
+struct Session {
+    provider: String,
+    model: String,
+    history: Vec<Message>,
+}
+
+/// Text of a history message, without tool calls and results
+fn message_text(message: &Message) -> String {
+    use rig::message::{AssistantContent, UserContent};
+    match message {
+        Message::User { content } => content.iter()
+            .filter_map(|content| match content {
+                UserContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Message::Assistant { content } => content.iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn preview(text: &str, limit: usize) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() > limit {
+        text.chars().take(limit).collect::<String>() + "…"
+    } else {
+        text
+    }
+}
+
+fn print_chat_help(router: &AgentRouter) {
+    println!("Commands (handled locally, nothing is sent to the model):");
+    println!("  /help                 Show this help");
+    println!("  /clear                Forget the conversation");
+    println!("  /history              Show the conversation so far");
+    println!("  /undo                 Drop the last exchange from the history (changed files are not restored)");
+    println!("  /model <name>         Switch the {} provider to another model", router.config().provider);
+    println!("  /context              Show the context documents the agent was given");
+    println!("  /reload               Analyze the workspace again and refresh the context");
+    println!("  /tools                List the tools the agent can call");
+    println!("  /run                  Execute the job script");
+    println!("  /save [file]          Save the conversation (default: {})", SESSION_FILE);
+    println!("  /load [file]          Continue a saved conversation (default: {})", SESSION_FILE);
+    if !router.mcp_servers().is_empty() {
+        println!("  /resources, /attach <server> <uri>, /prompts, /<server>__<prompt>   MCP resources and prompts");
+    }
+    println!("Start a request with /think or /no_think to switch thinking for it, or with @<route> to hand it to another model.");
+    println!("Type 'exit' or 'quit' when you're done.");
+}
+
+/// Handle the chat's own slash commands. Returns false for input that is not
+/// one of them, such as `/think` or the MCP commands.
+async fn run_chat_command(router: &mut AgentRouter, history: &mut Vec<Message>, input: &str) -> bool {
+    let Some(command) = input.strip_prefix('/') else {
+        return false;
+    };
+    let (name, argument) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    let argument = argument.trim();
+
+    match name {
+        "help" => print_chat_help(router),
+        "clear" => {
+            history.clear();
+            println!("🧹 Conversation cleared");
+        }
+        "history" => {
+            if history.is_empty() {
+                println!("📜 No conversation yet");
+            }
+            for message in history.iter() {
+                let role = match message {
+                    Message::User { .. } => "You",
+                    Message::Assistant { .. } => "Assistant",
+                };
+                println!("{}: {}", role, preview(&message_text(message), HISTORY_PREVIEW));
+            }
+        }
+        "undo" => match history.iter().rposition(|message| matches!(message, Message::User { .. })) {
+            Some(index) => {
+                let request = message_text(&history[index]);
+                history.truncate(index);
+                println!("↩️  Dropped from the history: {}", preview(&request, 60));
+            }
+            None => println!("⚠️  Nothing to undo"),
+        },
+        "model" => {
+            if argument.is_empty() {
+                let config = router.config();
+                println!("🧠 Provider: {} | Model: {}", config.provider, model_selector::active_model_name(config));
+                println!("⚠️  Usage: /model <name>");
+            } else {
+                match router.set_model(argument).await {
+                    Ok(()) => {
+                        let config = router.config();
+                        println!("🧠 Provider: {} | Model: {}", config.provider, model_selector::active_model_name(config));
+                    }
+                    Err(e) => println!("❌ {} | Keeping {}", e, model_selector::active_model_name(router.config())),
+                }
+            }
+        }
+        "context" => {
+            let config = router.config();
+            println!("📚 Workspace: {} | Job script: {}", config.workspace.display(), config.exec_script().display());
+            for doc in router.context_docs() {
+                println!("  • {} ({} characters)", preview(doc.lines().next().unwrap_or_default(), 80), doc.len());
+            }
+            for resource in router.resources() {
+                println!("  • MCP resource {} from server {} ({} characters)", resource.uri, resource.server, resource.text.len());
+            }
+            println!("💬 Conversation: {} messages", history.len());
+        }
+        "reload" => {
+            let config = router.config();
+            let codebase_path = config.workspace.to_string_lossy().to_string();
+            let exec_script = config.exec_script().to_string_lossy().to_string();
+            match analyze_context(&codebase_path, &exec_script).await {
+                Ok(context_docs) => match router.set_context_docs(context_docs).await {
+                    Ok(()) => println!("✅ Context reloaded from {}", codebase_path),
+                    Err(e) => println!("❌ Failed to rebuild the agents: {}", e),
+                },
+                Err(e) => println!("❌ Failed to analyze {}: {}", codebase_path, e),
+            }
+        }
+        "tools" => {
+            let enabled = router.config().enabled_tools.as_deref();
+            let tools = agent_factory::TOOL_NAMES.iter()
+                .filter(|name| enabled.is_none_or(|enabled| enabled.iter().any(|tool| tool == *name)))
+                .copied()
+                .collect::<Vec<_>>();
+            println!("🧰 Built-in tools ({}): {}", tools.len(), tools.join(", "));
+            for server in router.mcp_servers() {
+                let tools = server.tools.iter().map(|tool| namespaced(&server.name, &tool.name)).collect::<Vec<_>>();
+                println!("🧰 MCP server {} ({}): {}", server.name, tools.len(), tools.join(", "));
+            }
+        }
+        "run" => {
+            if let Err(e) = run_job(&router.config().exec_script()).await {
+                println!("❌ Job failed: {}", e);
+            }
+        }
+        "save" => {
+            let path = if argument.is_empty() { SESSION_FILE } else { argument };
+            let config = router.config();
+            let session = Session {
+                provider: config.provider.clone(),
+                model: model_selector::active_model_name(config),
+                history: history.clone(),
+            };
+            match serde_json::to_string_pretty(&session).map_err(Error::from)
+                .and_then(|json| fs::write(path, json).map_err(Error::from)) {
+                Ok(()) => println!("💾 Saved {} messages to {}", history.len(), path),
+                Err(e) => println!("❌ Failed to save {}: {}", path, e),
+            }
+        }
+        "load" => {
+            let path = if argument.is_empty() { SESSION_FILE } else { argument };
+            match read_text_file(Path::new(path))
+                .and_then(|json| serde_json::from_str::<Session>(&json).map_err(Error::from)) {
+                Ok(session) => {
+                    *history = session.history;
+                    println!("📂 Loaded {} messages from {} (saved with {} | {})",
+                        history.len(), path, session.provider, session.model);
+                }
+                Err(e) => println!("❌ Failed to load {}: {}", path, e),
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// What a chat line starting with '/' turned into
+enum McpCommand {
+    /// Handled locally; nothing to send to the model
+    Done,
+    /// An MCP prompt: messages to add to the history and the request to send
+    Prompt { messages: Vec<Message>, prompt: String },
+}
+
+/// Handle the MCP slash commands: `/resources`, `/attach <server> <uri>`,
+/// `/prompts` and `/<server>__<prompt> [name=value ...]`. Anything else,
+/// such as `/think`, is left alone.
+async fn run_mcp_command(router: &mut AgentRouter, input: &str) -> Option<McpCommand> {
+    let command = input.strip_prefix('/')?;
+    let (name, rest) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+    let rest = rest.trim();
+
+    match name {
+        "resources" => {
+            println!("📚 MCP resources (attach with /attach <server> <uri>):");
+            for server in router.mcp_servers() {
+                for resource in &server.resources {
+                    let attached = router.resources().iter()
+                        .any(|doc| doc.server == server.name && doc.uri == resource.uri.as_str());
+                    println!("  {} | {} | {}{}", server.name, resource.uri, resource.name,
+                        if attached { " (attached)" } else { "" });
+                }
+            }
+            Some(McpCommand::Done)
+        }
+        "attach" => {
+            let Some((server_name, uri)) = rest.split_once(char::is_whitespace) else {
+                println!("⚠️  Usage: /attach <server> <uri>");
+                return Some(McpCommand::Done);
+            };
+            let Some(server) = router.mcp_servers().iter().find(|server| server.name == server_name) else {
+                println!("⚠️  Unknown MCP server '{}'", server_name);
+                return Some(McpCommand::Done);
+            };
+            match server.attach(uri.trim()).await {
+                Ok(doc) => {
+                    println!("📎 Attached {} from MCP server {} ({} characters)", doc.uri, doc.server, doc.text.len());
+                    if let Err(e) = router.attach_resources(vec![doc]).await {
+                        println!("❌ Failed to rebuild the agents: {}", e);
+                    }
+                }
+                Err(e) => println!("❌ Failed to read {} from MCP server {}: {}", uri.trim(), server_name, e),
+            }
+            Some(McpCommand::Done)
+        }
+        "prompts" => {
+            println!("💬 MCP prompts (run with /<server>__<prompt> [name=value ...]):");
+            for server in router.mcp_servers() {
+                for prompt in &server.prompts {
+                    let arguments = prompt.arguments.iter().flatten()
+                        .map(|argument| format!("{}{}", argument.name, if argument.required == Some(true) { "*" } else { "" }))
+                        .collect::<Vec<_>>();
+                    println!("  /{} {} — {}", namespaced(&server.name, &prompt.name),
+                        arguments.join(" "), prompt.description.as_deref().unwrap_or(""));
+                }
+            }
+            Some(McpCommand::Done)
+        }
+        _ => {
+            let (server_name, prompt_name) = name.split_once(NAMESPACE_SEPARATOR)?;
+            let server = router.mcp_servers().iter().find(|server| server.name == server_name)?;
+            let prompt = server.prompts.iter().find(|prompt| prompt.name == prompt_name)?;
+            let arguments = parse_prompt_arguments(prompt, rest);
+
+            let missing = prompt.arguments.iter().flatten()
+                .filter(|argument| argument.required == Some(true) && !arguments.contains_key(&argument.name))
+                .map(|argument| argument.name.as_str())
+                .collect::<Vec<_>>();
+            if !missing.is_empty() {
+                println!("⚠️  /{} needs: {}", name, missing.join(", "));
+                return Some(McpCommand::Done);
+            }
+
+            match server.client.get_prompt(prompt_name, &arguments).await {
+                Ok(prompt_messages) => {
+                    println!("💬 Prompt {} from MCP server {} ({} messages)", prompt_name, server_name, prompt_messages.len());
+                    Some(prompt_to_messages(prompt_messages))
+                }
+                Err(e) => {
+                    println!("❌ Failed to get prompt {} from MCP server {}: {}", prompt_name, server_name, e);
+                    Some(McpCommand::Done)
+                }
+            }
+        }
+    }
+}
+
+/// `name=value` pairs; other words become the value of the first argument not given by name
+fn parse_prompt_arguments(prompt: &mcp_core::types::Prompt, text: &str) -> BTreeMap<String, String> {
+    let mut arguments = BTreeMap::new();
+    let mut words = Vec::new();
+    for word in text.split_whitespace() {
+        match word.split_once('=') {
+            Some((name, value)) if prompt.arguments.iter().flatten().any(|argument| argument.name == name) => {
+                arguments.insert(name.to_string(), value.to_string());
+            }
+            _ => words.push(word),
+        }
+    }
+    if !words.is_empty()
+        && let Some(argument) = prompt.arguments.iter().flatten()
+            .filter(|argument| !arguments.contains_key(&argument.name))
+            .min_by_key(|argument| argument.required != Some(true)) {
+        arguments.insert(argument.name.clone(), words.join(" "));
+    }
+    arguments
+}
+
+/// Earlier prompt messages go to the history; a final user message is the request
+fn prompt_to_messages(prompt_messages: Vec<mcp_core::types::PromptMessage>) -> McpCommand {
+    use mcp_core::types::{PromptMessageContent, Role};
+    let mut messages = prompt_messages.into_iter()
+        .map(|message| {
+            let text = match message.content {
+                PromptMessageContent::Text(text) => text.text,
+                PromptMessageContent::Resource(resource) => resource.resource.text
+                    .unwrap_or_else(|| resource.resource.uri.to_string()),
+                PromptMessageContent::Image(image) => format!("[image {}]", image.mime_type),
+                PromptMessageContent::Audio(audio) => format!("[audio {}]", audio.mime_type),
+            };
+            (message.role, text)
+        })
+        .collect::<Vec<_>>();
+    let prompt = match messages.last() {
+        Some((Role::User, _)) => messages.pop().map(|(_, text)| text).unwrap_or_default(),
+        _ => "Continue.".to_string(),
+    };
+    let messages = messages.into_iter()
+        .map(|(role, text)| match role {
+            Role::User => Message::user(text),
+            Role::Assistant => Message::assistant(text),
+        })
+        .collect();
+    McpCommand::Prompt { messages, prompt }
+}
+
+/// A leading "/word" that no command handled; "/think", "/no_think" and
+/// paths like "/src/main.rs" go to the model
+fn is_unknown_command(input: &str) -> bool {
+    let Some(command) = input.strip_prefix('/') else {
+        return false;
+    };
+    let name = command.split_whitespace().next().unwrap_or_default();
+    !name.is_empty()
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        && parse_thinking_toggle(input).0.is_none()
+}
+
+/// Re-read attached resources whose servers reported a change
+async fn refresh_resources(router: &mut AgentRouter) {
+    let mut changed = Vec::new();
+    for server in router.mcp_servers() {
+        for uri in server.client.take_updated_resources() {
+            if !router.resources().iter().any(|doc| doc.server == server.name && doc.uri == uri) {
+                continue;
+            }
+            match server.read(&uri).await {
+                Ok(doc) => {
+                    println!("🔄 {} changed on MCP server {}; context updated", uri, server.name);
+                    changed.push(doc);
+                }
+                Err(e) => println!("⚠️  Failed to re-read {} from MCP server {}: {}", uri, server.name, e),
+            }
+        }
+    }
+    if !changed.is_empty()
+        && let Err(e) = router.attach_resources(changed).await {
+        println!("❌ Failed to rebuild the agents: {}", e);
+    }
 }
 
+/// Send one request, printing the answer (and tool activity when streaming)
+async fn run_turn(
+    agent: &AgentWrapper,
+    prompt: &str,
+    history: &[Message],
+    stream: bool,
+    thinking: &ThinkingConfig,
+) -> Result<String, Error> {
+    println!("🤔 Processing your request...");
+    agent.usage().begin_turn();
+    if stream {
+        let printer = StreamPrinter::new(thinking.display);
+        let result = agent.stream_chat(prompt, history.to_vec(), MAX_TOOL_TURNS, &|event| printer.render(event)).await;
+        printer.end_reasoning();
+        if !printer.at_line_start.load(Ordering::Relaxed) {
+            println!();
+        }
+        result
+    } else {
+        agent.chat(prompt, history.to_vec()).await.map(|response| {
+            let (reasoning, answer) = split_reasoning(&response);
+            print_reasoning(&reasoning, thinking.display);
+            println!("Assistant: {}", answer);
+            answer
+        })
+    }
+}
 
-async fn run_cli_chat(agent: AgentWrapper) -> Result<(), Error> {
+async fn run_cli_chat(mut router: AgentRouter, stream: bool, thinking: &ThinkingConfig) -> Result<(), Error> {
 
     println!("✨ Welcome to the Context-Aware LLMO Assistant! ✨");
-    println!("I have full knowledge of the test_code directory and can help with:");
+    println!("I have full knowledge of {} and can help with:", router.config().workspace.display());
     println!("• Code analysis and optimization suggestions");
     println!("• Performance bottleneck identification");
     println!("• Architecture and structure questions");
     println!("• Code modifications and improvements");
-    println!("Feel free to ask me anything! Type 'exit' or 'quit' when you're done.");
+    println!("Feel free to ask me anything! Type /help for commands, 'exit' or 'quit' when you're done.");
+    let routes = router.describe_routes();
+    if !routes.is_empty() {
+        println!("Start a request with @<route> to hand it to another model: {}", routes.join(", "));
+    }
+    if !router.mcp_servers().is_empty() {
+        println!("MCP: /resources and /attach <server> <uri> add server resources to the context; /prompts lists server prompts to run as /<server>__<prompt>");
+    }
     println!("---------------------------------------------------");
 
     let mut history = Vec::new();
@@ -68,38 +687,87 @@ async fn run_cli_chat(agent: AgentWrapper) -> Result<(), Error> {
         io::stdout().flush()?; // Print prompt immediately
 
         let mut input = String::new();
-        stdin.read_line(&mut input)?;
-        let input = input.trim();
+        if stdin.read_line(&mut input)? == 0 {
+            println!();
+            println!("Goodbye!");
+            break;
+        }
+        let mut input = input.trim().to_string();
 
         if input.eq_ignore_ascii_case("exit") || input.eq_ignore_ascii_case("quit") {
             println!("Goodbye!");
             break;
         }
 
-        history.push(Message::user(input));
+        // Subscribed resources may have changed since the last turn
+        refresh_resources(&mut router).await;
+
+        if run_chat_command(&mut router, &mut history, &input).await {
+            continue;
+        }
+        // Messages from an MCP prompt join the history only once the turn succeeds
+        let mut prompt_messages = Vec::new();
+        match run_mcp_command(&mut router, &input).await {
+            Some(McpCommand::Done) => continue,
+            Some(McpCommand::Prompt { messages, prompt }) => {
+                prompt_messages = messages;
+                input = prompt;
+            }
+            None => {}
+        }
+        if is_unknown_command(&input) {
+            println!("⚠️  Unknown command {}. Type /help for the list.", input.split_whitespace().next().unwrap_or_default());
+            continue;
+        }
+
+        let (route, input) = parse_route(&input);
+        if let Some(route) = route
+            && !router.has_route(route) {
+            let routes = router.describe_routes();
+            let routes = if routes.is_empty() { "none".to_string() } else { routes.join(", ") };
+            println!("⚠️  Unknown route '{}'. Configured routes: {}", route, routes);
+            continue;
+        }
+        let agent = match route {
+            Some(route) => match router.agent_for(route).await {
+                Ok(agent) => agent,
+                Err(e) => {
+                    println!("❌ Route {} is unavailable: {}", route, e);
+                    continue;
+                }
+            },
+            None => router.default_agent(),
+        };
 
-        println!("🤔 Processing your request...");
-        match agent.chat(input, history.clone()).await {
+        // "/think ..." or "/no_think ..." switches thinking for this request only
+        let (toggle, input) = parse_thinking_toggle(input);
+        let prompt = with_thinking_switch(input, toggle.or(thinking.enabled));
+
+        let context = [history.as_slice(), prompt_messages.as_slice()].concat();
+        match run_turn(agent, &prompt, &context, stream, thinking).await {
             Ok(response) => {
-                println!("Assistant: {}", response);
+                history.extend(prompt_messages);
+                history.push(Message::user(input));
                 history.push(Message::assistant(response));
             }
             Err(err) => {
                 eprintln!("Error: {:?}", err);
             }
         }
+        print_turn_usage(agent.usage());
+        if let Some(exceeded) = agent.usage().budget_exceeded() {
+            println!("🛑 {}. Ending the session.", exceeded);
+            break;
+        }
         println!("---------------------------------------------------");
     }
 
+    print_session_usage(router.usage());
     Ok(())
 }
 
-async fn create_contextual_agent(
-    system_prompt: &str,
-    mcp_config: Option<(MCPClient, mcp_core::types::ToolsListResponse)>,
-    codebase_path: &str,
-    exection_script: &str,
-) -> Result<AgentWrapper, Error> {
+/// Analyze the codebase and turn it into the agents' context documents
+async fn analyze_context(codebase_path: &str, exection_script: &str) -> Result<Vec<String>, Error> {
     println!("🔍 Analyzing codebase at: {}", codebase_path);
 
     // Analyze codebase directly (no LLM calls, avoids MaxDepthError)
@@ -109,65 +777,192 @@ async fn create_contextual_agent(
     // Create context documents
     let context_docs = ContextWorkflow::create_context_docs(&codebase_content, None, exection_script);
     println!("✅ Context documents prepared ({} docs)", context_docs.len());
+    Ok(context_docs)
+}
+
+async fn create_contextual_agent(
+    config: &Config,
+    system_prompt: &str,
+    mcp_servers: Vec<McpServer>,
+    codebase_path: &str,
+    exection_script: &str,
+) -> Result<AgentRouter, Error> {
+    let context_docs = analyze_context(codebase_path, exection_script).await?;
 
     // Create agent with context
-    let agent = model_selector::get_agent_with_context(system_prompt, mcp_config, context_docs);
+    let resources = mcp_servers::attach_configured_resources(&mcp_servers).await;
+    let agent = AgentRouter::new(config, system_prompt, mcp_servers, context_docs, resources).await?;
     println!("✅ Context-aware agent created");
 
     Ok(agent)
 }
 
+/// Answer a single prompt, for `LLMO ask`
+async fn run_ask(mut router: AgentRouter, prompt: &str, stream: bool, thinking: &ThinkingConfig) -> Result<(), Error> {
+    let (route, input) = parse_route(prompt);
+    let agent = match route {
+        Some(route) if router.has_route(route) => router.agent_for(route).await?,
+        Some(route) => anyhow::bail!("Unknown route '{}'", route),
+        None => router.default_agent(),
+    };
+    let (toggle, input) = parse_thinking_toggle(input);
+    let prompt = with_thinking_switch(input, toggle.or(thinking.enabled));
+    let result = run_turn(agent, &prompt, &[], stream, thinking).await;
+    print_session_usage(router.usage());
+    result.map(|_| ())
+}
+
+/// Run `LLMO headless` and write its JSON report to `out`; returns the exit code
+async fn run_headless(
+    args: &Args,
+    prompt: &[String],
+    max_turns: usize,
+    mut out: impl Write,
+) -> Result<ExitCode, Error> {
+    let prompt = match prompt {
+        [] => io::read_to_string(io::stdin())?,
+        [dash] if dash == "-" => io::read_to_string(io::stdin())?,
+        words => words.join(" "),
+    };
+    if prompt.trim().is_empty() {
+        eprintln!("❌ No prompt given on the command line or stdin");
+        return Ok(ExitCode::from(2));
+    }
+
+    // Failures before the run are reported like failures during it
+    let report = match headless_report(args, prompt.trim(), max_turns).await {
+        Ok(report) => report,
+        Err(e) => RunReport::failed(&e),
+    };
+    writeln!(out, "{}", serde_json::to_string_pretty(&report)?)?;
+    out.flush()?;
+    Ok(ExitCode::from(report.exit_code as u8))
+}
+
+async fn headless_report(args: &Args, prompt: &str, max_turns: usize) -> Result<RunReport, Error> {
+    let config = load_config(args)?;
+    let mut router = create_router(&config).await?;
+
+    let (route, input) = parse_route(prompt);
+    let agent = match route {
+        Some(route) if router.has_route(route) => router.agent_for(route).await?,
+        Some(route) => anyhow::bail!("Unknown route '{}'", route),
+        None => router.default_agent(),
+    };
+    let (toggle, input) = parse_thinking_toggle(input);
+    let prompt = with_thinking_switch(input, toggle.or(config.thinking.enabled));
+
+    Ok(headless::run(agent, &prompt, &config.workspace, max_turns).await)
+}
+
+/// Print the codebase report, for `LLMO analyze`
+fn run_analyze(path: &Path, json: bool, out: &mut impl Write) -> Result<(), Error> {
+    println!("🔍 Analyzing codebase at: {}", path.display());
+    let stats = codebase_stats::CodebaseStats::collect(path, 10000, 10)?;
+    let report = if json { stats.to_json()? } else { stats.to_text() };
+    writeln!(out, "{}", report)?;
+    Ok(())
+}
+
+/// Execute a job script through the job tool, for `LLMO run`
+async fn run_job(script: &Path) -> Result<(), Error> {
+    let args = file_tools::ExecuteJobArgs {
+        script_path: script.to_string_lossy().to_string(),
+        output_file: None,
+        working_directory: None,
+    };
+    let summary = file_tools::JobExecutor.call(args).await?;
+    println!("{}", summary);
+    Ok(())
+}
+
 #[tokio::main]
-async fn main() -> Result<(), Error> {
+async fn main() -> Result<ExitCode, Error> {
+    let args = Args::parse();
+    let command = args.command.clone().unwrap_or(Command::Chat);
+
+    // Over stdio, stdout carries MCP messages and everything printed goes to
+    // stderr; JSON reports likewise keep stdout to themselves
+    let mut report_stdout = match command {
+        Command::Serve { transport: ServeTransport::Stdio, .. }
+        | Command::Analyze { json: true, .. }
+        | Command::Headless { .. } => {
+            Some(mcp_host::take_stdout()?)
+        }
+        _ => None,
+    };
+
     println!("🚀 Starting LLMO application...");
 
     println!("📄 Loading environment variables...");
     dotenv().ok();
 
-    println!("📋 Loading system prompt...");
-    let system_prompt = read_text_file("prompt.txt")?;
-    println!("✅ System prompt loaded successfully");
+    // Returned rather than exited with, so MCP server children are still cleaned up
+    if let Command::Headless { prompt, max_turns } = &command {
+        let out = report_stdout.take().ok_or_else(|| anyhow::anyhow!("stdout is not available"))?;
+        return run_headless(&args, prompt, *max_turns, out).await;
+    }
 
-    // Check if MCP server URL is provided
-    let mcp_config = match std::env::var("MCP_SERVER_URL") {
-        Ok(mcp_url) if !mcp_url.is_empty() => {
-            println!("🔌 Initializing MCP client with URL: {}", mcp_url);
-            match MCPClient::new_with_url(&mcp_url).await {
-                Ok(mcp_client) => {
-                    println!("✅ MCP client initialized");
-                    
-                    println!("🛠️  Listing available tools...");
-                    match mcp_client.inner.list_tools(None, None).await {
-                        Ok(tools) => {
-                            println!("✅ Found {} MCP tools", tools.tools.len());
-                            Some((mcp_client, tools))
-                        }
-                        Err(e) => {
-                            eprintln!("⚠️  Failed to list MCP tools: {}. Continuing without MCP.", e);
-                            None
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("⚠️  Failed to initialize MCP client: {}. Continuing without MCP.", e);
-                    None
-                }
+    let config = load_config(&args)?;
+    match &command {
+        Command::Analyze { path, json } => {
+            let path = path.clone().unwrap_or_else(|| config.workspace.clone());
+            match report_stdout.take() {
+                Some(mut stdout) => run_analyze(&path, *json, &mut stdout)?,
+                None => run_analyze(&path, *json, &mut io::stdout())?,
+            };
+        }
+        Command::Run { script } => {
+            let script = script.clone().unwrap_or_else(|| config.exec_script());
+            run_job(&script).await?;
+        }
+        Command::Serve { transport, listen } => {
+            let host = ToolHost::new(config.enabled_tools.as_deref(), &config.workspace);
+            println!("🧰 Serving {} tools over MCP ({:?}): {}",
+                host.tool_names().len(), transport, host.tool_names().join(", "));
+            match report_stdout {
+                Some(stdout) => mcp_host::serve_stdio(host, tokio::fs::File::from_std(stdout)).await?,
+                None => mcp_host::serve_sse(host, *listen).await?,
             }
         }
-        _ => {
-            println!("ℹ️  No MCP_SERVER_URL provided, running without MCP integration");
-            None
+        Command::Ask { prompt } => {
+            let agent = create_router(&config).await?;
+            run_ask(agent, &prompt.join(" "), config.stream, &config.thinking).await?;
         }
-    };
+        Command::Chat => {
+            let agent = create_router(&config).await?;
+            run_cli_chat(agent, config.stream, &config.thinking).await?;
+        }
+        Command::Headless { .. } => unreachable!("headless runs return before the config is loaded"),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn load_config(args: &Args) -> Result<Config, Error> {
+    println!("⚙️  Loading configuration...");
+    let mut config = Config::load(args.config.as_deref(), args.profile.as_deref())?;
+    config.apply_overrides(&args.overrides());
+    config.validate()?;
+    println!("✅ Configuration loaded");
+    Ok(config)
+}
+
+/// Connect the MCP servers and build the agents with the codebase as context
+async fn create_router(config: &Config) -> Result<AgentRouter, Error> {
+    println!("🧠 Provider: {} | Model: {}", config.provider, model_selector::active_model_name(config));
+
+    println!("📋 Loading system prompt...");
+    let system_prompt = read_text_file(&config.system_prompt_file)?;
+    println!("✅ System prompt loaded successfully");
+
+    let mcp_servers = mcp_servers::connect_servers(&config.mcp_servers()).await;
 
     println!("🤖 Setting up context-aware agent...");
-    let codebase_path = "test_code";
-    let job_execution_script = "./test_code/run.sh";
+    let codebase_path = config.workspace.to_string_lossy().to_string();
+    let job_execution_script = config.exec_script().to_string_lossy().to_string();
     println!("📂 Codebase path: {}", codebase_path);
     println!("📄 Job execution script: {}", job_execution_script);
-    let agent = create_contextual_agent(&system_prompt, mcp_config, codebase_path, job_execution_script).await?;
+    let agent = create_contextual_agent(config, &system_prompt, mcp_servers, &codebase_path, &job_execution_script).await?;
     println!("✅ Context-aware agent ready with knowledge of {}", codebase_path);
-
-    run_cli_chat(agent).await?;
-    Ok(())
+    Ok(agent)
 }
@@ -0,0 +1,310 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+use anyhow::Result;
+use mcp_core::{
+    client::{Client, ClientBuilder},
+    protocol::{ProtocolBuilder, RequestOptions},
+    transport::{ClientSseTransportBuilder, Transport},
+    types::{CallToolResponse, ErrorCode, Prompt, PromptMessage, PromptsListResponse, ReadResourceResponse, Resource, ToolsListResponse},
+};
+use serde::Deserialize;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::config::McpServerConfig;
+use crate::mcp_transport::{ChildStdioTransport, McpTransport};
+
+/// How long a health check waits for the server to answer a ping
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Client for one MCP server. When the server stops answering (the SSE
+/// stream dropped, the child process exited) it reconnects with backoff.
+#[derive(Clone)]
+pub struct MCPClient {
+    name: String,
+    server: McpServerConfig,
+    connection: Arc<RwLock<(Client<McpTransport>, McpTransport)>>,
+    /// Held while reconnecting so concurrent failures reconnect only once
+    reconnecting: Arc<Mutex<()>>,
+    /// Subscribed resource URIs and a hash of their last known content
+    subscriptions: Arc<std::sync::Mutex<HashMap<String, u64>>>,
+    /// Subscribed resources that changed since `take_updated_resources`
+    updated: Arc<std::sync::Mutex<BTreeSet<String>>>,
+}
+
+/// Parameters of `notifications/resources/updated`
+#[derive(Deserialize)]
+struct ResourceUpdated {
+    uri: String,
+}
+
+/// A tool call that did not finish within its timeout
+#[derive(Debug, thiserror::Error)]
+#[error("timed out after {}s", .0.as_secs())]
+pub struct ToolTimeout(Duration);
+
+/// Result of `prompts/get`
+#[derive(Deserialize)]
+struct GetPromptResponse {
+    messages: Vec<PromptMessage>,
+}
+
+fn content_hash(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl MCPClient {
+    /// Connect to the server described by `[mcp_servers.<name>]`
+    pub async fn connect(name: &str, server: &McpServerConfig) -> Result<Self> {
+        let updated = Arc::new(std::sync::Mutex::new(BTreeSet::new()));
+        let connection = Self::open(server, &updated).await?;
+        Ok(MCPClient {
+            name: name.to_string(),
+            server: server.clone(),
+            connection: Arc::new(RwLock::new(connection)),
+            reconnecting: Arc::new(Mutex::new(())),
+            subscriptions: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            updated,
+        })
+    }
+
+    async fn open(server: &McpServerConfig, updated: &Arc<std::sync::Mutex<BTreeSet<String>>>) -> Result<(Client<McpTransport>, McpTransport)> {
+        let transport = match (&server.url, &server.command) {
+            (_, Some(command)) => {
+                tracing::info!("Initializing MCP client with stdio transport...");
+                let updated = updated.clone();
+                let protocol = ProtocolBuilder::new()
+                    .notification_handler("notifications/resources/updated", move |notification: ResourceUpdated| {
+                        updated.lock().unwrap().insert(notification.uri);
+                        Box::pin(async { Ok(()) })
+                    })
+                    .build();
+                McpTransport::Stdio(ChildStdioTransport::new(command, &server.args, &server.env, protocol))
+            }
+            (Some(url), None) => {
+                tracing::info!("Initializing MCP client with SSE transport...");
+                McpTransport::Sse(ClientSseTransportBuilder::new(url.clone()).build())
+            }
+            (None, None) => anyhow::bail!("MCP server needs a url or a command"),
+        };
+
+        // Build client with transport
+        let client = ClientBuilder::new(transport.clone())
+            .set_protocol_version(mcp_core::types::ProtocolVersion::V2024_11_05)
+            .set_client_info("tool_list_client".to_string(), "0.1.0".to_string())
+            .build();
+
+        if let Err(e) = async {
+            client.open().await?;
+            client.initialize().await
+        }.await {
+            // Do not leave a half-started child process behind
+            let _ = transport.close().await;
+            return Err(e);
+        }
+
+        Ok((client, transport))
+    }
+
+    async fn client(&self) -> Client<McpTransport> {
+        self.connection.read().await.0.clone()
+    }
+
+    pub async fn list_tools(&self) -> Result<ToolsListResponse> {
+        self.client().await.list_tools(None, None).await
+    }
+
+    /// Ask the server whether it is still there
+    pub async fn ping(&self) -> Result<()> {
+        self.client().await.request("ping", None, RequestOptions::default().timeout(PING_TIMEOUT)).await?;
+        Ok(())
+    }
+
+    /// Call a tool, giving up after `timeout`. If the call fails because the
+    /// server stopped responding, reconnect and try once more; errors of the
+    /// tool itself and timeouts are returned as is.
+    pub async fn call_tool(&self, tool_name: &str, arguments: serde_json::Value, timeout: Duration) -> Result<CallToolResponse> {
+        match self.request_tool(tool_name, &arguments, timeout).await {
+            Ok(response) => Ok(response),
+            Err(e) if e.is::<ToolTimeout>() => Err(e),
+            Err(e) if self.ping().await.is_err() => {
+                println!("⚠️  MCP server {} | Not responding ({}). Reconnecting...", self.name, e);
+                self.reconnect().await?;
+                self.request_tool(tool_name, &arguments, timeout).await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn request_tool(&self, tool_name: &str, arguments: &serde_json::Value, timeout: Duration) -> Result<CallToolResponse> {
+        let params = serde_json::json!({ "name": tool_name, "arguments": arguments });
+        // Ask the transport directly: the client's errors do not tell a timeout apart
+        let transport = self.connection.read().await.1.clone();
+        let response = transport.request("tools/call", Some(params), RequestOptions::default().timeout(timeout)).await?;
+        match (response.result, response.error) {
+            (Some(result), _) => Ok(serde_json::from_value(result)?),
+            (None, Some(error)) if error.code == ErrorCode::RequestTimeout as i32 => Err(ToolTimeout(timeout).into()),
+            (None, error) => Err(anyhow::anyhow!("Request failed: {:?}", error)),
+        }
+    }
+
+    /// Replace the connection with a new one, retrying with exponential backoff
+    pub async fn reconnect(&self) -> Result<()> {
+        let _guard = self.reconnecting.lock().await;
+        // Someone else may have reconnected while we waited for the lock
+        if self.ping().await.is_ok() {
+            return Ok(());
+        }
+
+        let policy = &self.server.reconnect;
+        let mut attempt = 1;
+        loop {
+            let old_transport = self.connection.read().await.1.clone();
+            let _ = old_transport.close().await;
+
+            match Self::open(&self.server, &self.updated).await {
+                Ok(connection) => {
+                    *self.connection.write().await = connection;
+                    println!("✅ MCP server {} | Reconnected", self.name);
+                    // A restarted server has forgotten our subscriptions, and
+                    // the resources may have changed while it was away
+                    let uris = self.subscriptions.lock().unwrap().keys().cloned().collect::<Vec<_>>();
+                    for uri in uris {
+                        let _ = self.resubscribe(&uri).await;
+                        self.updated.lock().unwrap().insert(uri);
+                    }
+                    return Ok(());
+                }
+                Err(e) if attempt < policy.max_attempts => {
+                    let delay = policy.backoff(attempt);
+                    println!("⚠️  MCP server {} | Reconnect attempt {}/{} failed: {} | Retrying in {:.1}s",
+                        self.name, attempt, policy.max_attempts, e, delay.as_secs_f64());
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    println!("❌ MCP server {} | Reconnect attempt {}/{} failed: {}",
+                        self.name, attempt, policy.max_attempts, e);
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Ping the server in the background every `health_check_secs` and
+    /// reconnect when it does not answer. While it stays down the checks
+    /// back off to at most 16 intervals apart.
+    pub fn spawn_health_check(&self) {
+        let interval = self.server.health_check_interval();
+        if interval.is_zero() {
+            return;
+        }
+        let client = self.clone();
+        tokio::spawn(async move {
+            let mut delay = interval;
+            loop {
+                tokio::time::sleep(delay).await;
+                let Err(e) = client.ping().await else {
+                    delay = interval;
+                    continue;
+                };
+                if delay == interval {
+                    println!("⚠️  MCP server {} | Health check failed ({}). Reconnecting...", client.name, e);
+                }
+                delay = match client.reconnect().await {
+                    Ok(()) => interval,
+                    Err(_) => (delay * 2).min(interval * 16),
+                };
+            }
+        });
+    }
+
+    /// Re-read subscribed resources of an SSE server in the background every
+    /// `poll_secs`, independently of the health checks
+    pub fn spawn_subscription_poll(&self) {
+        if !self.server.subscribe || self.server.url.is_none() {
+            return;
+        }
+        let client = self.clone();
+        tokio::spawn(async move {
+            let interval = client.server.poll_interval();
+            loop {
+                tokio::time::sleep(interval).await;
+                client.poll_subscriptions().await;
+            }
+        });
+    }
+
+    pub async fn list_resources(&self) -> Result<Vec<Resource>> {
+        Ok(self.client().await.list_resources(None, None).await?.resources)
+    }
+
+    /// Text of a resource; binary contents are described rather than included
+    pub async fn read_resource(&self, uri: &str) -> Result<String> {
+        let response = self.client().await
+            .request("resources/read", Some(serde_json::json!({ "uri": uri })), RequestOptions::default())
+            .await?;
+        let response: ReadResourceResponse = serde_json::from_value(response)?;
+        Ok(response.contents.into_iter()
+            .map(|contents| match (contents.text, contents.blob) {
+                (Some(text), _) => text,
+                (None, Some(blob)) => format!("[binary {} ({} bytes base64)]",
+                    contents.mime_type.unwrap_or_else(|| "data".to_string()), blob.len()),
+                (None, None) => String::new(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    /// Ask to be told when a resource changes; see `take_updated_resources`
+    pub async fn subscribe(&self, uri: &str, text: &str) -> Result<()> {
+        self.subscriptions.lock().unwrap().insert(uri.to_string(), content_hash(text));
+        self.resubscribe(uri).await
+    }
+
+    async fn resubscribe(&self, uri: &str) -> Result<()> {
+        // SSE servers are polled instead; mcp-core's SSE transport drops notifications
+        match self.connection.read().await.1 {
+            McpTransport::Stdio(_) => self.client().await.subscribe_to_resource(url::Url::parse(uri)?).await,
+            McpTransport::Sse(_) => Ok(()),
+        }
+    }
+
+    /// Re-read subscribed resources of an SSE server and note the ones that changed
+    async fn poll_subscriptions(&self) {
+        if matches!(self.connection.read().await.1, McpTransport::Stdio(_)) {
+            return;
+        }
+        let subscriptions = self.subscriptions.lock().unwrap().clone();
+        for (uri, hash) in subscriptions {
+            if let Ok(text) = self.read_resource(&uri).await
+                && content_hash(&text) != hash {
+                self.subscriptions.lock().unwrap().insert(uri.clone(), content_hash(&text));
+                self.updated.lock().unwrap().insert(uri);
+            }
+        }
+    }
+
+    /// Subscribed resources that changed since the last call
+    pub fn take_updated_resources(&self) -> Vec<String> {
+        std::mem::take(&mut *self.updated.lock().unwrap()).into_iter().collect()
+    }
+
+    pub async fn list_prompts(&self) -> Result<Vec<Prompt>> {
+        let response = self.client().await.request("prompts/list", Some(serde_json::json!({})), RequestOptions::default()).await?;
+        let response: PromptsListResponse = serde_json::from_value(response)?;
+        Ok(response.prompts)
+    }
+
+    /// The messages of a prompt filled in with `arguments`
+    pub async fn get_prompt(&self, prompt: &str, arguments: &BTreeMap<String, String>) -> Result<Vec<PromptMessage>> {
+        let params = serde_json::json!({ "name": prompt, "arguments": arguments });
+        let response = self.client().await.request("prompts/get", Some(params), RequestOptions::default()).await?;
+        let response: GetPromptResponse = serde_json::from_value(response)?;
+        Ok(response.messages)
+    }
+
+}
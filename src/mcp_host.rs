@@ -0,0 +1,329 @@
+//! Serves LLMO's own tools (file, search, navigation and job tools) over MCP,
+//! so other MCP clients and other LLMO processes can share one tool host.
+//! Clients connect over SSE (`GET /sse`, then `POST /message?sessionId=…`)
+//! or launch the host as a child process speaking over stdio.
+//!
+//! Every path argument is confined to the workspace, and a client that
+//! changes a file holds a lease on it for `LEASE_SECS`, during which other
+//! clients cannot change that file.
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use clap::ValueEnum;
+use futures::StreamExt;
+use rig::tool::{Tool, ToolDyn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+use crate::agent_factory::register_tools;
+use crate::dependency_graph::DependencyGrapher;
+use crate::file_tools::{confine, CodeEditor, CodeInserter, CreateDirectory, FileEditor, FileWriter, JobExecutor};
+use crate::symbol_index::parse_location;
+
+/// MCP protocol version the host speaks
+const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// How long a client keeps the files it changed to itself
+const LEASE_SECS: u64 = 60;
+
+/// Tool arguments that name a file or directory
+const PATH_ARGUMENTS: &[&str] = &["path", "script_path", "output_file", "working_directory"];
+
+/// Tools that change files; their path arguments are leased to the caller
+const WRITE_TOOLS: &[&str] = &[
+    FileWriter::NAME,
+    FileEditor::NAME,
+    CodeEditor::NAME,
+    CodeInserter::NAME,
+    CreateDirectory::NAME,
+    DependencyGrapher::NAME,
+    JobExecutor::NAME,
+];
+
+/// Client name of the single stdio client
+const STDIO_CLIENT: &str = "stdio";
+
+/// How the tool host is reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ServeTransport {
+    Stdio,
+    Sse,
+}
+
+/// The built-in tools behind an MCP JSON-RPC interface
+pub struct ToolHost {
+    tools: Vec<Box<dyn ToolDyn>>,
+    workspace: PathBuf,
+    /// Client holding each changed path, and until when
+    leases: Mutex<HashMap<PathBuf, (String, Instant)>>,
+}
+
+impl ToolHost {
+    /// Host the registry's tools, or the enabled subset
+    pub fn new(enabled_tools: Option<&[String]>, workspace: &Path) -> Self {
+        ToolHost {
+            tools: register_tools(Vec::new(), enabled_tools, workspace),
+            workspace: workspace.to_path_buf(),
+            leases: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn tool_names(&self) -> Vec<String> {
+        self.tools.iter().map(|tool| tool.name()).collect()
+    }
+
+    /// Answer one JSON-RPC message from `client`; notifications get no answer
+    pub async fn handle(&self, client: &str, message: Value) -> Option<Value> {
+        let id = message.get("id").cloned().filter(|id| !id.is_null())?;
+        let method = message.get("method").and_then(Value::as_str).unwrap_or_default();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        let result = match method {
+            "initialize" => Ok(json!({
+                "protocolVersion": PROTOCOL_VERSION,
+                "capabilities": { "tools": { "listChanged": false } },
+                "serverInfo": { "name": "llmo", "version": env!("CARGO_PKG_VERSION") },
+            })),
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(self.list_tools().await),
+            "tools/call" => self.call_tool(client, &params).await,
+            _ => Err((-32601, format!("Method not found: {}", method))),
+        };
+
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }),
+        })
+    }
+
+    async fn list_tools(&self) -> Value {
+        let mut tools = Vec::new();
+        for tool in &self.tools {
+            let definition = tool.definition(String::new()).await;
+            tools.push(json!({
+                "name": definition.name,
+                "description": definition.description,
+                "inputSchema": definition.parameters,
+            }));
+        }
+        json!({ "tools": tools })
+    }
+
+    async fn call_tool(&self, client: &str, params: &Value) -> Result<Value, (i64, String)> {
+        let name = params.get("name").and_then(Value::as_str)
+            .ok_or_else(|| (-32602, "tools/call needs a tool name".to_string()))?;
+        let tool = self.tools.iter().find(|tool| tool.name() == name)
+            .ok_or_else(|| (-32602, format!("Unknown tool: {}", name)))?;
+        let mut arguments = params.get("arguments").cloned().unwrap_or_else(|| json!({}));
+
+        let paths = match self.confine_arguments(&mut arguments) {
+            Ok(paths) => paths,
+            Err(e) => return Ok(tool_error(e)),
+        };
+        if WRITE_TOOLS.contains(&name)
+            && let Err(e) = self.lease(client, &paths) {
+            return Ok(tool_error(e));
+        }
+
+        // Tool failures are results with isError set, so the client's model sees them
+        let (text, is_error) = match tool.call(arguments.to_string()).await {
+            // Outputs come back JSON encoded; hand plain strings over as text
+            Ok(output) => match serde_json::from_str::<Value>(&output) {
+                Ok(Value::String(text)) => (text, false),
+                _ => (output, false),
+            },
+            Err(e) => (e.to_string(), true),
+        };
+        Ok(json!({ "content": [{ "type": "text", "text": text }], "isError": is_error }))
+    }
+
+    /// Resolve the path arguments of a call inside the workspace, rewriting
+    /// them to absolute paths. Returns the resolved paths.
+    fn confine_arguments(&self, arguments: &mut Value) -> Result<Vec<PathBuf>, String> {
+        let Some(arguments) = arguments.as_object_mut() else {
+            return Ok(Vec::new());
+        };
+        let mut paths = Vec::new();
+        for key in PATH_ARGUMENTS {
+            if let Some(Value::String(path)) = arguments.get(*key) {
+                let resolved = self.confine(path)?;
+                arguments.insert(key.to_string(), json!(resolved));
+                paths.push(resolved);
+            }
+        }
+        if let Some(Value::String(location)) = arguments.get("location") {
+            let (path, line, column) = parse_location(location).map_err(|e| e.to_string())?;
+            let resolved = self.confine(&path)?;
+            let location = match column {
+                Some(column) => format!("{}:{}:{}", resolved.display(), line, column),
+                None => format!("{}:{}", resolved.display(), line),
+            };
+            arguments.insert("location".to_string(), json!(location));
+        }
+        Ok(paths)
+    }
+
+    /// Relative paths are taken from the workspace
+    fn confine(&self, path: &str) -> Result<PathBuf, String> {
+        confine(&self.workspace, &self.workspace.join(path)).map_err(|e| e.to_string())
+    }
+
+    /// Take or renew `client`'s lease on every path, unless another client holds one
+    fn lease(&self, client: &str, paths: &[PathBuf]) -> Result<(), String> {
+        let now = Instant::now();
+        let mut leases = self.leases.lock().unwrap();
+        leases.retain(|_, (_, expires)| *expires > now);
+        if let Some((path, (_, expires))) = paths.iter()
+            .find_map(|path| leases.get(path).filter(|(holder, _)| holder != client).map(|lease| (path, lease))) {
+            return Err(format!("{} is being changed by another client; try again in {}s",
+                path.display(), expires.duration_since(now).as_secs() + 1));
+        }
+        for path in paths {
+            leases.insert(path.clone(), (client.to_string(), now + Duration::from_secs(LEASE_SECS)));
+        }
+        Ok(())
+    }
+
+    /// Drop the leases of a client that went away
+    fn release(&self, client: &str) {
+        self.leases.lock().unwrap().retain(|_, (holder, _)| holder != client);
+    }
+}
+
+fn tool_error(message: String) -> Value {
+    json!({ "content": [{ "type": "text", "text": message }], "isError": true })
+}
+
+/// Serve MCP over this process's stdin and stdout. `out` must be the real
+/// stdout; see `take_stdout`.
+pub async fn serve_stdio<W: AsyncWrite + Unpin + Send + 'static>(host: ToolHost, out: W) -> std::io::Result<()> {
+    let host = Arc::new(host);
+    let out = Arc::new(tokio::sync::Mutex::new(out));
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut requests = tokio::task::JoinSet::new();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (host, out) = (host.clone(), out.clone());
+        // Requests run concurrently; a long job must not hold up a ping
+        requests.spawn(async move {
+            let response = match serde_json::from_str::<Value>(&line) {
+                Ok(message) => host.handle(STDIO_CLIENT, message).await,
+                Err(e) => Some(json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": e.to_string() } })),
+            };
+            if let Some(response) = response {
+                let mut out = out.lock().await;
+                let _ = out.write_all(format!("{}\n", response).as_bytes()).await;
+                let _ = out.flush().await;
+            }
+        });
+    }
+    // The client closed stdin; finish what it already asked for
+    while requests.join_next().await.is_some() {}
+    Ok(())
+}
+
+/// Keep the real stdout for MCP messages and send everything else printed
+/// to stdout (the tools' progress lines) to stderr
+#[cfg(unix)]
+pub fn take_stdout() -> std::io::Result<std::fs::File> {
+    use std::os::fd::FromRawFd;
+    // SAFETY: plain descriptor calls; the duplicate is owned by the returned file
+    unsafe {
+        let stdout = libc::dup(libc::STDOUT_FILENO);
+        if stdout < 0 || libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(std::fs::File::from_raw_fd(stdout))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn take_stdout() -> std::io::Result<std::fs::File> {
+    Err(std::io::Error::other("serving MCP over stdio needs a Unix system"))
+}
+
+struct SseState {
+    host: ToolHost,
+    sessions: Mutex<HashMap<String, mpsc::UnboundedSender<String>>>,
+}
+
+/// Removes a session when its event stream is dropped
+struct SessionGuard {
+    state: Arc<SseState>,
+    id: String,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.state.sessions.lock().unwrap().remove(&self.id);
+        self.state.host.release(&self.id);
+    }
+}
+
+/// Serve MCP over SSE on `addr` until the process is stopped
+pub async fn serve_sse(host: ToolHost, addr: SocketAddr) -> std::io::Result<()> {
+    let state = Arc::new(SseState { host, sessions: Mutex::new(HashMap::new()) });
+    let app = Router::new()
+        .route("/sse", get(open_session))
+        .route("/message", post(post_message))
+        .with_state(state);
+
+    let listener = TcpListener::bind(addr).await?;
+    println!("🚀 MCP tool host listening on http://{}/sse", listener.local_addr()?);
+    axum::serve(listener, app).await
+}
+
+async fn open_session(State(state): State<Arc<SseState>>) -> Sse<impl futures::Stream<Item = Result<Event, Infallible>>> {
+    // Session IDs are the only credential for posting messages, so they must not be guessable
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let (sender, receiver) = mpsc::unbounded_channel();
+    state.sessions.lock().unwrap().insert(id.clone(), sender);
+    println!("🔌 MCP session {} opened", id);
+
+    let endpoint = Event::default().event("endpoint").data(format!("/message?sessionId={}", id));
+    let guard = SessionGuard { state, id };
+    let messages = futures::stream::unfold((receiver, guard), |(mut receiver, guard)| async move {
+        let message = receiver.recv().await?;
+        Some((Event::default().event("message").data(message), (receiver, guard)))
+    });
+    let events = futures::stream::once(async { endpoint }).chain(messages).map(Ok);
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+#[derive(Deserialize)]
+struct SessionQuery {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+}
+
+async fn post_message(
+    State(state): State<Arc<SseState>>,
+    Query(query): Query<SessionQuery>,
+    Json(message): Json<Value>,
+) -> StatusCode {
+    let Some(sender) = state.sessions.lock().unwrap().get(&query.session_id).cloned() else {
+        return StatusCode::NOT_FOUND;
+    };
+    // The answer goes out on the session's event stream
+    tokio::spawn(async move {
+        if let Some(response) = state.host.handle(&query.session_id, message).await {
+            let _ = sender.send(response.to_string());
+        }
+    });
+    StatusCode::ACCEPTED
+}
@@ -0,0 +1,244 @@
+use mcp_core::types::{Prompt, Resource, Tool as McpToolDefinition, ToolResponseContent};
+use rig::completion::ToolDefinition;
+use rig::tool::Tool;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::io::{IsTerminal, Write};
+use std::time::Duration;
+
+use crate::config::McpServerConfig;
+use crate::mcp_client::MCPClient;
+
+/// Separator between a server's name and its tool names, e.g. `github__create_issue`
+pub const NAMESPACE_SEPARATOR: &str = "__";
+
+/// Name the agent sees for `tool` on `server`; the prefix keeps tools of
+/// different servers apart and away from built-ins like `read_file`
+pub fn namespaced(server: &str, tool: &str) -> String {
+    format!("{}{}{}", server, NAMESPACE_SEPARATOR, tool)
+}
+
+/// A connected MCP server and the tools, resources and prompts it offers
+#[derive(Clone)]
+pub struct McpServer {
+    pub name: String,
+    pub client: MCPClient,
+    pub config: McpServerConfig,
+    pub tools: Vec<McpToolDefinition>,
+    pub resources: Vec<Resource>,
+    pub prompts: Vec<Prompt>,
+}
+
+/// A server resource attached to the agents' context
+#[derive(Debug, Clone)]
+pub struct McpResourceDoc {
+    pub server: String,
+    pub uri: String,
+    pub text: String,
+}
+
+impl McpServer {
+    /// The server's tools, ready to register with an agent
+    pub fn agent_tools(&self) -> impl Iterator<Item = McpServerTool> + '_ {
+        self.tools.iter().map(|tool| McpServerTool {
+            server: self.name.clone(),
+            tool: tool.clone(),
+            client: self.client.clone(),
+            timeout: self.config.tool_timeout(&tool.name),
+            confirm: self.config.requires_confirmation(&tool.name),
+        })
+    }
+
+    /// Read a resource as a context document
+    pub async fn read(&self, uri: &str) -> anyhow::Result<McpResourceDoc> {
+        let text = self.client.read_resource(uri).await?;
+        Ok(McpResourceDoc { server: self.name.clone(), uri: uri.to_string(), text })
+    }
+
+    /// Read a resource for the context, subscribing to it if configured
+    pub async fn attach(&self, uri: &str) -> anyhow::Result<McpResourceDoc> {
+        let doc = self.read(uri).await?;
+        if self.config.subscribe
+            && let Err(e) = self.client.subscribe(uri, &doc.text).await {
+            println!("⚠️  MCP server {} | Failed to subscribe to {}: {}", self.name, uri, e);
+        }
+        Ok(doc)
+    }
+}
+
+/// Short description of how a server is reached, for the startup summary
+fn describe(server: &McpServerConfig) -> String {
+    match (&server.command, &server.url) {
+        (Some(command), _) => format!("stdio: {}", std::iter::once(command).chain(&server.args).cloned().collect::<Vec<_>>().join(" ")),
+        (None, Some(url)) => format!("sse: {}", url),
+        (None, None) => "not configured".to_string(),
+    }
+}
+
+async fn connect(name: &str, server: &McpServerConfig) -> anyhow::Result<McpServer> {
+    let client = MCPClient::connect(name, server).await?;
+    let tools = client.list_tools().await?.tools.into_iter()
+        .filter(|tool| server.exposes_tool(&tool.name))
+        .collect();
+    // Resources and prompts are optional capabilities; servers without them answer with an error
+    let resources = client.list_resources().await.unwrap_or_default();
+    let prompts = client.list_prompts().await.unwrap_or_default();
+    client.spawn_health_check();
+    client.spawn_subscription_poll();
+    Ok(McpServer { name: name.to_string(), client, config: server.clone(), tools, resources, prompts })
+}
+
+/// Connect to every configured server at once and print which servers and
+/// tools are available. Servers that fail are reported and left out.
+pub async fn connect_servers(servers: &BTreeMap<String, McpServerConfig>) -> Vec<McpServer> {
+    if servers.is_empty() {
+        println!("ℹ️  No MCP servers configured and no MCP_SERVER_URL provided, running without MCP integration");
+        return Vec::new();
+    }
+
+    println!("🔌 Connecting to {} MCP servers...", servers.len());
+    let results = futures::future::join_all(
+        servers.iter().map(|(name, server)| async move { (name, server, connect(name, server).await) })
+    ).await;
+
+    let mut connected = Vec::new();
+    for (name, server, result) in results {
+        match result {
+            Ok(server_tools) => {
+                let tools = server_tools.tools.iter()
+                    .map(|tool| {
+                        let marker = if server.requires_confirmation(&tool.name) { " (confirm)" } else { "" };
+                        format!("{}{}", namespaced(name, &tool.name), marker)
+                    })
+                    .collect::<Vec<_>>();
+                println!("  ✅ {} ({}) | {} tools: {} | {} resources | {} prompts", name, describe(server),
+                    tools.len(), tools.join(", "), server_tools.resources.len(), server_tools.prompts.len());
+                connected.push(server_tools);
+            }
+            Err(e) => println!("  ❌ {} ({}) | {}. Continuing without it.", name, describe(server), e),
+        }
+    }
+    let tool_count = connected.iter().map(|server| server.tools.len()).sum::<usize>();
+    println!("✅ {} of {} MCP servers connected, {} MCP tools available", connected.len(), servers.len(), tool_count);
+    connected
+}
+
+/// Read the resources each server's `resources` patterns select, subscribing
+/// to them when the server is configured with `subscribe`
+pub async fn attach_configured_resources(servers: &[McpServer]) -> Vec<McpResourceDoc> {
+    let mut docs = Vec::new();
+    for server in servers {
+        let patterns = server.config.resources.iter()
+            .filter_map(|pattern| glob::Pattern::new(pattern).ok())
+            .collect::<Vec<_>>();
+        for resource in &server.resources {
+            let uri = resource.uri.as_str();
+            if !patterns.iter().any(|pattern| pattern.matches(uri)) {
+                continue;
+            }
+            match server.attach(uri).await {
+                Ok(doc) => {
+                    println!("📎 Attached {} from MCP server {} ({} characters)", uri, server.name, doc.text.len());
+                    docs.push(doc);
+                }
+                Err(e) => println!("⚠️  Failed to read {} from MCP server {}: {}", uri, server.name, e),
+            }
+        }
+    }
+    docs
+}
+
+/// A tool of an MCP server, registered under its namespaced name. Failures
+/// (the tool's own errors, a server that is down) are returned to the model
+/// as the tool's output, so they never end the turn.
+pub struct McpServerTool {
+    server: String,
+    tool: McpToolDefinition,
+    client: MCPClient,
+    timeout: Duration,
+    /// Ask the user before each call
+    confirm: bool,
+}
+
+/// Ask on the terminal whether a tool may run; no answer means no. Without
+/// a terminal on stdin (headless runs, piped input, the tool host) nobody
+/// can answer, so the call is declined straight away.
+async fn confirm_call(name: &str, args: &serde_json::Value) -> Result<(), String> {
+    if !std::io::stdin().is_terminal() {
+        return Err(format!("{} needs the user's confirmation, but there is no terminal to ask on", name));
+    }
+
+    // One question at a time when the model calls several tools at once
+    static ASKING: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+    let _asking = ASKING.lock().await;
+
+    print!("❓ Allow {} with {}? [y/N] ", name, args);
+    let _ = std::io::stdout().flush();
+    let answer = tokio::task::spawn_blocking(|| {
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer).map(|_| answer)
+    }).await;
+    match answer {
+        Ok(Ok(answer)) if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") => Ok(()),
+        _ => Err(format!("the user declined to run {}", name)),
+    }
+}
+
+impl Tool for McpServerTool {
+    const NAME: &'static str = "mcp_tool";
+    type Error = Infallible;
+    type Args = serde_json::Value;
+    type Output = String;
+
+    fn name(&self) -> String {
+        namespaced(&self.server, &self.tool.name)
+    }
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: self.name(),
+            description: format!("[MCP server {}] {}", self.server, self.tool.description.clone().unwrap_or_default()),
+            parameters: self.tool.input_schema.clone(),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let name = self.name();
+        println!("🔧 Tool: {} | Arguments: {}", name, args);
+
+        if self.confirm
+            && let Err(reason) = confirm_call(&name, &args).await {
+            println!("🚫 Tool: {} | Not run: {}", name, reason);
+            return Ok(format!("Error: {}", reason));
+        }
+
+        // Run on its own task: reconnecting awaits futures that are not Sync,
+        // which rig requires of tool calls
+        let (client, tool_name, timeout) = (self.client.clone(), self.tool.name.clone(), self.timeout);
+        let call = tokio::spawn(async move { client.call_tool(&tool_name, args, timeout).await });
+        let response = match call.await.map_err(anyhow::Error::from).and_then(|result| result) {
+            Ok(response) => response,
+            Err(e) => {
+                println!("❌ Tool: {} | Error: {}", name, e);
+                return Ok(format!("Error: MCP server {} failed to run {}: {}", self.server, self.tool.name, e));
+            }
+        };
+
+        let text = response.content.into_iter()
+            .map(|content| match content {
+                ToolResponseContent::Text(text) => text.text,
+                ToolResponseContent::Image(image) => format!("data:{};base64,{}", image.mime_type, image.data),
+                ToolResponseContent::Audio(audio) => format!("data:{};base64,{}", audio.mime_type, audio.data),
+                ToolResponseContent::Resource(resource) => resource.resource.text.unwrap_or_else(|| resource.resource.uri.to_string()),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if response.is_error.unwrap_or(false) {
+            println!("❌ Tool: {} | Error: {}", name, text);
+            return Ok(format!("Error: {}", text));
+        }
+        println!("✅ Tool: {} | Success: {} characters", name, text.len());
+        Ok(text)
+    }
+}
@@ -0,0 +1,270 @@
+//! Transports for MCP clients: SSE from mcp-core, and a stdio transport that
+//! launches the server as a child process with its own arguments and environment.
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use mcp_core::protocol::{Protocol, RequestOptions};
+use mcp_core::transport::{
+    ClientSseTransport, JsonRpcError, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, Message, RequestId,
+    Transport,
+};
+use mcp_core::types::ErrorCode;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::process::Stdio;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+/// MCP server running as a child process, spoken to with newline-delimited
+/// JSON-RPC over its stdin and stdout. Its stderr is logged at debug level.
+#[derive(Clone)]
+pub struct ChildStdioTransport {
+    protocol: Protocol,
+    command: String,
+    args: Vec<String>,
+    env: BTreeMap<String, String>,
+    child: Arc<Mutex<Option<Child>>>,
+    stdin: Arc<Mutex<Option<ChildStdin>>>,
+    stdout: Arc<Mutex<Option<BufReader<ChildStdout>>>>,
+    /// Requests still waiting for a response, failed at once if the server exits
+    pending: Arc<Mutex<HashSet<RequestId>>>,
+}
+
+impl ChildStdioTransport {
+    /// `protocol` handles what the server sends on its own, e.g. notifications
+    pub fn new(command: &str, args: &[String], env: &BTreeMap<String, String>, protocol: Protocol) -> Self {
+        ChildStdioTransport {
+            protocol,
+            command: command.to_string(),
+            args: args.to_vec(),
+            env: env.clone(),
+            child: Arc::new(Mutex::new(None)),
+            stdin: Arc::new(Mutex::new(None)),
+            stdout: Arc::new(Mutex::new(None)),
+            pending: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    async fn write_message(&self, message: &impl Serialize) -> Result<()> {
+        let mut line = serde_json::to_string(message)?;
+        line.push('\n');
+        let mut stdin = self.stdin.lock().await;
+        let stdin = stdin.as_mut().ok_or_else(|| anyhow!("MCP server {} is not running", self.command))?;
+        stdin.write_all(line.as_bytes()).await?;
+        stdin.flush().await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Transport for ChildStdioTransport {
+    async fn open(&self) -> Result<()> {
+        let mut child = Command::new(&self.command)
+            .args(&self.args)
+            .envs(&self.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| anyhow!("Failed to start MCP server {}: {}", self.command, e))?;
+
+        let stdin = child.stdin.take().ok_or_else(|| anyhow!("MCP server stdin not available"))?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("MCP server stdout not available"))?;
+        if let Some(stderr) = child.stderr.take() {
+            let command = self.command.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    tracing::debug!("{}: {}", command, line);
+                }
+            });
+        }
+
+        *self.stdin.lock().await = Some(stdin);
+        *self.stdout.lock().await = Some(BufReader::new(stdout));
+        *self.child.lock().await = Some(child);
+
+        // Dispatch everything the server sends: responses to our requests,
+        // its own requests (e.g. ping) and notifications
+        let transport = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match transport.poll_message().await {
+                    Ok(Some(Message::Response(response))) => transport.protocol.handle_response(response).await,
+                    Ok(Some(Message::Request(request))) => {
+                        let response = transport.protocol.handle_request(request).await;
+                        let _ = transport.send_response(response.id, response.result, response.error).await;
+                    }
+                    Ok(Some(Message::Notification(notification))) => {
+                        transport.protocol.handle_notification(notification).await
+                    }
+                    Ok(None) => {
+                        tracing::debug!("{}: exited", transport.command);
+                        break;
+                    }
+                    Err(e) => {
+                        tracing::debug!("{}: {}", transport.command, e);
+                        break;
+                    }
+                }
+            }
+            // Waiting and later requests fail right away instead of running into their timeout
+            *transport.stdin.lock().await = None;
+            for id in transport.pending.lock().await.drain() {
+                transport.protocol.handle_response(JsonRpcResponse {
+                    id,
+                    result: None,
+                    error: Some(JsonRpcError {
+                        code: ErrorCode::ConnectionClosed as i32,
+                        message: format!("MCP server {} exited", transport.command),
+                        data: None,
+                    }),
+                    ..Default::default()
+                }).await;
+            }
+        });
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        *self.stdin.lock().await = None;
+        if let Some(mut child) = self.child.lock().await.take() {
+            let _ = child.kill().await;
+        }
+        Ok(())
+    }
+
+    async fn poll_message(&self) -> Result<Option<Message>> {
+        let mut stdout = self.stdout.lock().await;
+        let stdout = stdout.as_mut().ok_or_else(|| anyhow!("MCP server {} is not running", self.command))?;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if stdout.read_line(&mut line).await? == 0 {
+                return Ok(None);
+            }
+            // Some servers print blank lines between messages
+            if !line.trim().is_empty() {
+                return Ok(Some(serde_json::from_str(&line)?));
+            }
+        }
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        options: RequestOptions,
+    ) -> Pin<Box<dyn Future<Output = Result<JsonRpcResponse>> + Send + Sync>> {
+        let transport = self.clone();
+        let method = method.to_string();
+        Box::pin(async move {
+            let (id, response) = transport.protocol.create_request().await;
+            let request = JsonRpcRequest { id, method, params, jsonrpc: Default::default() };
+            transport.pending.lock().await.insert(id);
+            if let Err(e) = transport.write_message(&request).await {
+                transport.pending.lock().await.remove(&id);
+                transport.protocol.cancel_response(id).await;
+                return Err(e);
+            }
+
+            let response = tokio::time::timeout(options.timeout, response).await;
+            transport.pending.lock().await.remove(&id);
+            let (code, message) = match response {
+                Ok(Ok(response)) => return Ok(response),
+                // The response channel was dropped, e.g. by `close`
+                Ok(Err(_)) => (ErrorCode::ConnectionClosed, format!("Connection to MCP server {} closed", transport.command)),
+                Err(_) => (ErrorCode::RequestTimeout, "Request timed out".to_string()),
+            };
+            transport.protocol.cancel_response(id).await;
+            Ok(JsonRpcResponse {
+                id,
+                result: None,
+                error: Some(JsonRpcError { code: code as i32, message, data: None }),
+                ..Default::default()
+            })
+        })
+    }
+
+    async fn send_notification(&self, method: &str, params: Option<serde_json::Value>) -> Result<()> {
+        let notification = JsonRpcNotification { method: method.to_string(), params, jsonrpc: Default::default() };
+        self.write_message(&notification).await
+    }
+
+    async fn send_response(
+        &self,
+        id: RequestId,
+        result: Option<serde_json::Value>,
+        error: Option<JsonRpcError>,
+    ) -> Result<()> {
+        let response = JsonRpcResponse { id, result, error, jsonrpc: Default::default() };
+        self.write_message(&response).await
+    }
+}
+
+/// Either transport, so one client type serves SSE and stdio servers alike
+#[derive(Clone)]
+pub enum McpTransport {
+    Sse(ClientSseTransport),
+    Stdio(ChildStdioTransport),
+}
+
+#[async_trait]
+impl Transport for McpTransport {
+    async fn open(&self) -> Result<()> {
+        match self {
+            McpTransport::Sse(transport) => transport.open().await,
+            McpTransport::Stdio(transport) => transport.open().await,
+        }
+    }
+
+    async fn close(&self) -> Result<()> {
+        match self {
+            McpTransport::Sse(transport) => transport.close().await,
+            McpTransport::Stdio(transport) => transport.close().await,
+        }
+    }
+
+    async fn poll_message(&self) -> Result<Option<Message>> {
+        match self {
+            McpTransport::Sse(transport) => transport.poll_message().await,
+            McpTransport::Stdio(transport) => transport.poll_message().await,
+        }
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        options: RequestOptions,
+    ) -> Pin<Box<dyn Future<Output = Result<JsonRpcResponse>> + Send + Sync>> {
+        match self {
+            McpTransport::Sse(transport) => transport.request(method, params, options),
+            McpTransport::Stdio(transport) => transport.request(method, params, options),
+        }
+    }
+
+    async fn send_notification(&self, method: &str, params: Option<serde_json::Value>) -> Result<()> {
+        match self {
+            McpTransport::Sse(transport) => transport.send_notification(method, params).await,
+            McpTransport::Stdio(transport) => transport.send_notification(method, params).await,
+        }
+    }
+
+    async fn send_response(
+        &self,
+        id: RequestId,
+        result: Option<serde_json::Value>,
+        error: Option<JsonRpcError>,
+    ) -> Result<()> {
+        match self {
+            McpTransport::Sse(transport) => transport.send_response(id, result, error).await,
+            McpTransport::Stdio(transport) => transport.send_response(id, result, error).await,
+        }
+    }
+}
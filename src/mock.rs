@@ -0,0 +1,196 @@
+use rig::OneOrMany;
+use rig::completion::{self, CompletionError, CompletionRequest};
+use rig::message::{AssistantContent, Message, UserContent};
+use rig::streaming::{RawStreamingChoice, StreamingCompletionResponse};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::agent_factory::ModelProvider;
+use crate::config::ProviderConfig;
+use crate::tool_protocol::ToolCalling;
+
+/// A tool call the mock model makes
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptedToolCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: serde_json::Value,
+}
+
+/// One scripted model response: text, tool calls or both
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScriptedResponse {
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub tool_calls: Vec<ScriptedToolCall>,
+}
+
+/// Responses replayed in order, one per completion request. A JSON file like:
+///
+/// ```json
+/// [
+///   {"text": "Let me look.", "tool_calls": [{"name": "read_file", "arguments": {"path": "run.sh"}}]},
+///   {"text": "The script is empty."}
+/// ]
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockScript {
+    pub responses: Vec<ScriptedResponse>,
+}
+
+impl MockScript {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read mock script {}: {}", path.display(), e))?;
+        let responses = serde_json::from_str(&text)
+            .map_err(|e| format!("invalid mock script {}: {}", path.display(), e))?;
+        Ok(MockScript { responses })
+    }
+}
+
+/// Raw response of the mock model; it has nothing to add to the choice
+#[derive(Debug, Clone)]
+pub struct MockResponse;
+
+/// Deterministic completion model for offline runs. With a script it
+/// replays the scripted responses in order and fails once they run out;
+/// without one it echoes the prompt back.
+#[derive(Clone)]
+pub struct MockCompletionModel {
+    script: Result<Arc<MockScript>, String>,
+    /// Shared by clones so every request advances the same script
+    next: Arc<AtomicUsize>,
+    /// Every request received, shared by clones
+    requests: Arc<Mutex<Vec<CompletionRequest>>>,
+}
+
+impl MockCompletionModel {
+    pub fn new(script: Option<MockScript>) -> Self {
+        MockCompletionModel { script: Ok(Arc::new(script.unwrap_or_default())), next: Arc::default(), requests: Arc::default() }
+    }
+
+    /// A model whose every request fails with `error`, for scripts that failed to load
+    fn broken(error: String) -> Self {
+        MockCompletionModel { script: Err(error), next: Arc::default(), requests: Arc::default() }
+    }
+
+    /// The requests the model was sent so far, oldest first
+    pub fn requests(&self) -> Vec<CompletionRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn respond(&self, request: &CompletionRequest) -> Result<(ScriptedResponse, Option<usize>), CompletionError> {
+        self.requests.lock().unwrap().push(request.clone());
+        let script = self.script.as_ref().map_err(|e| CompletionError::ProviderError(e.clone()))?;
+        if script.responses.is_empty() {
+            let response = ScriptedResponse { text: format!("Mock reply to: {}", last_user_text(request)), tool_calls: Vec::new() };
+            return Ok((response, None));
+        }
+
+        let index = self.next.fetch_add(1, Ordering::SeqCst);
+        match script.responses.get(index) {
+            Some(response) => Ok((response.clone(), Some(index))),
+            None => Err(CompletionError::ProviderError(format!(
+                "Mock script exhausted: request {} but only {} responses scripted", index + 1, script.responses.len()))),
+        }
+    }
+}
+
+/// Text of the latest user message; tool results are shown as "[tool result]"
+fn last_user_text(request: &CompletionRequest) -> String {
+    let Some(Message::User { content }) = request.chat_history.iter().last() else {
+        return String::new();
+    };
+    content.iter()
+        .map(|content| match content {
+            UserContent::Text(text) => text.text.clone(),
+            UserContent::ToolResult(_) => "[tool result]".to_string(),
+            _ => String::new(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Stable tool call ids: `call_<response>_<call>`
+fn tool_call_id(index: Option<usize>, call: usize) -> String {
+    format!("call_{}_{}", index.map_or(0, |index| index + 1), call + 1)
+}
+
+impl completion::CompletionModel for MockCompletionModel {
+    type Response = MockResponse;
+    type StreamingResponse = MockResponse;
+
+    async fn completion(&self, request: CompletionRequest) -> Result<completion::CompletionResponse<MockResponse>, CompletionError> {
+        let (response, index) = self.respond(&request)?;
+
+        let mut choice = Vec::new();
+        if !response.text.is_empty() || response.tool_calls.is_empty() {
+            choice.push(AssistantContent::text(response.text));
+        }
+        for (call, tool_call) in response.tool_calls.into_iter().enumerate() {
+            choice.push(AssistantContent::tool_call(tool_call_id(index, call), tool_call.name, tool_call.arguments));
+        }
+
+        Ok(completion::CompletionResponse {
+            choice: OneOrMany::many(choice).map_err(|e| CompletionError::ResponseError(e.to_string()))?,
+            raw_response: MockResponse,
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingCompletionResponse<MockResponse>, CompletionError> {
+        let (response, index) = self.respond(&request)?;
+
+        // Stream the text word by word, then the tool calls
+        let mut chunks = response.text.split_inclusive(' ')
+            .map(|word| Ok(RawStreamingChoice::Message(word.to_string())))
+            .collect::<Vec<_>>();
+        for (call, tool_call) in response.tool_calls.into_iter().enumerate() {
+            chunks.push(Ok(RawStreamingChoice::ToolCall {
+                id: tool_call_id(index, call),
+                name: tool_call.name,
+                arguments: tool_call.arguments,
+            }));
+        }
+        chunks.push(Ok(RawStreamingChoice::FinalResponse(MockResponse)));
+
+        Ok(StreamingCompletionResponse::stream(Box::pin(futures::stream::iter(chunks))))
+    }
+}
+
+/// Scripted mock model, configured from `providers.mock.script` or LLMO_MOCK_SCRIPT
+pub struct MockProvider {
+    pub settings: ProviderConfig,
+}
+
+impl MockProvider {
+    pub fn script_path(&self) -> Option<std::path::PathBuf> {
+        self.settings.script.clone()
+            .or_else(|| std::env::var("LLMO_MOCK_SCRIPT").ok().filter(|path| !path.is_empty()).map(Into::into))
+    }
+}
+
+impl ModelProvider for MockProvider {
+    type Model = MockCompletionModel;
+
+    const NAME: &'static str = "mock";
+
+    fn model_name(&self) -> String {
+        self.settings.model.clone().unwrap_or_else(|| "scripted".to_string())
+    }
+
+    fn tool_calling(&self) -> ToolCalling {
+        self.settings.tool_calling.unwrap_or(ToolCalling::Native)
+    }
+
+    fn model(&self) -> Result<Self::Model, anyhow::Error> {
+        Ok(match self.script_path().map(|path| MockScript::load(&path)).transpose() {
+            Ok(script) => MockCompletionModel::new(script),
+            Err(e) => MockCompletionModel::broken(e),
+        })
+    }
+}
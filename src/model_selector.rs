@@ -1,66 +1,347 @@
-use rig::agent::Agent;
-use rig::providers::{openai, gemini};
-use rig::completion::{Message, Prompt, Chat};
-use crate::mcp_test::MCPClient;
-use mcp_core::types::ToolsListResponse;
+use rig::completion::Message;
+use crate::agent_factory::{self, build_agent, provider_label, AgentEvent, ChatAgent, EventSink, ModelProvider};
+use crate::config::{Config, ConfigOverrides};
+use crate::anthropic::AnthropicProvider;
+use crate::gemini::GeminiProvider;
+use crate::local::LocalProvider;
+use crate::ollama::OllamaProvider;
+use crate::mock::MockProvider;
+use crate::mcp_servers::{McpResourceDoc, McpServer};
+use crate::context_workflow::ContextWorkflow;
+use crate::retry::is_provider_failure;
+use crate::usage::{count_completions, UsageTracker};
 use anyhow::Error;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 pub enum ModelType {
     Local,
     Gemini,
+    Anthropic,
+    Ollama,
+    /// Scripted responses, for offline tests
+    Mock,
 }
 
-pub enum AgentWrapper {
-    Local(Agent<openai::CompletionModel>),
-    Gemini(Agent<gemini::completion::CompletionModel>),
-}
+impl ModelType {
+    /// Provider names accepted in USE_MODEL and the config file
+    pub const NAMES: &'static [&'static str] = &["local", "gemini", "anthropic", "ollama", "mock"];
 
-impl AgentWrapper {
-    pub async fn prompt(&self, prompt: &str) -> Result<String, Error> {
-        match self {
-            AgentWrapper::Local(agent) => agent.prompt(prompt).await.map_err(|e| anyhow::anyhow!(e)),
-            AgentWrapper::Gemini(agent) => agent.prompt(prompt).await.map_err(|e| anyhow::anyhow!(e)),
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "local" => Some(ModelType::Local),
+            "gemini" => Some(ModelType::Gemini),
+            "anthropic" => Some(ModelType::Anthropic),
+            "ollama" => Some(ModelType::Ollama),
+            "mock" => Some(ModelType::Mock),
+            _ => None,
         }
     }
 
-    pub async fn chat(&self, prompt: &str, history: Vec<Message>) -> Result<String, Error> {
+    /// Environment variable the provider reads its API key from
+    pub fn api_key_var(&self) -> Option<&'static str> {
         match self {
-            AgentWrapper::Local(agent) => agent.chat(prompt, history).await.map_err(|e| anyhow::anyhow!(e)),
-            AgentWrapper::Gemini(agent) => agent.chat(prompt, history).await.map_err(|e| anyhow::anyhow!(e)),
+            ModelType::Local => LocalProvider::API_KEY_VAR,
+            ModelType::Gemini => GeminiProvider::API_KEY_VAR,
+            ModelType::Anthropic => AnthropicProvider::API_KEY_VAR,
+            ModelType::Ollama => OllamaProvider::API_KEY_VAR,
+            ModelType::Mock => MockProvider::API_KEY_VAR,
         }
     }
 }
 
-pub fn get_model_type() -> ModelType {
-    match std::env::var("USE_MODEL").as_deref() {
-        Ok("gemini") => ModelType::Gemini,
-        Ok("local") => ModelType::Local,
-        _ => ModelType::Local, // Default to local
+/// An agent built on one provider, labelled for logging
+struct ProviderAgent {
+    label: String,
+    agent: Box<dyn ChatAgent>,
+}
+
+impl ProviderAgent {
+    async fn new<P: ModelProvider>(
+        provider: &P,
+        config: &Config,
+        usage: &UsageTracker,
+        prompt: &str,
+        mcp_servers: Vec<McpServer>,
+        context_docs: Vec<String>,
+    ) -> Result<ProviderAgent, Error> {
+        let agent = build_agent(provider, prompt, config, usage, mcp_servers, context_docs).await?;
+        Ok(ProviderAgent { label: provider_label(provider), agent: Box::new(agent) })
     }
 }
 
-pub fn get_agent(prompt: &str, mcp_config: Option<(MCPClient, ToolsListResponse)>) -> AgentWrapper {
-    match get_model_type() {
-        ModelType::Local => {
-            let agent = crate::local::get_agent(prompt, mcp_config);
-            AgentWrapper::Local(agent)
+/// Agents for the selected provider and its fallbacks, tried in order
+pub struct AgentWrapper {
+    chain: Vec<ProviderAgent>,
+    usage: UsageTracker,
+}
+
+impl AgentWrapper {
+    /// Token usage of all agents in the chain
+    pub fn usage(&self) -> &UsageTracker {
+        &self.usage
+    }
+
+    pub async fn prompt(&self, prompt: &str) -> Result<String, Error> {
+        self.chat(prompt, Vec::new()).await
+    }
+
+    /// Run the turn on each provider in turn until one succeeds. Tool errors
+    /// and other failures that are not the provider's fault are returned
+    /// directly, as are failures after a completion already succeeded: its
+    /// tools have run, and rerunning the turn would run them again.
+    pub async fn chat(&self, prompt: &str, history: Vec<Message>) -> Result<String, Error> {
+        let mut providers = self.chain.iter().peekable();
+        loop {
+            let Some(current) = providers.next() else {
+                return Err(anyhow::anyhow!("No providers configured"));
+            };
+            let (result, completions) = count_completions(current.agent.chat(prompt, history.clone())).await;
+            match result {
+                Ok(response) => return Ok(response),
+                Err(e) => match providers.peek() {
+                    Some(next) if is_provider_failure(&e) && completions == 0 => {
+                        println!("↪️  {} failed: {} | Falling back to {}", current.label, e, next.label);
+                    }
+                    _ => return Err(e),
+                },
+            }
         }
-        ModelType::Gemini => {
-            let agent = crate::gemini::_get_agent(prompt, mcp_config);
-            AgentWrapper::Gemini(agent)
+    }
+
+    /// Streaming variant of `chat`; text and tool activity go to `on_event`.
+    /// Falls back only while nothing has been streamed or run yet.
+    pub async fn stream_chat(&self, prompt: &str, history: Vec<Message>, max_turns: usize, on_event: EventSink<'_>) -> Result<String, Error> {
+        let emitted = AtomicBool::new(false);
+        let track = |event: AgentEvent<'_>| {
+            emitted.store(true, Ordering::Relaxed);
+            on_event(event);
+        };
+        let mut providers = self.chain.iter().peekable();
+        loop {
+            let Some(current) = providers.next() else {
+                return Err(anyhow::anyhow!("No providers configured"));
+            };
+            match current.agent.stream_chat(prompt, history.clone(), max_turns, &track).await {
+                Ok(response) => return Ok(response),
+                Err(e) => match providers.peek() {
+                    Some(next) if is_provider_failure(&e) && !emitted.load(Ordering::Relaxed) => {
+                        println!("↪️  {} failed: {} | Falling back to {}", current.label, e, next.label);
+                    }
+                    _ => return Err(e),
+                },
+            }
         }
     }
 }
 
-pub fn get_agent_with_context(prompt: &str, mcp_config: Option<(MCPClient, ToolsListResponse)>, context_docs: Vec<String>) -> AgentWrapper {
-    match get_model_type() {
-        ModelType::Local => {
-            let agent = crate::local::get_agent_with_context(prompt, mcp_config, context_docs);
-            AgentWrapper::Local(agent)
+pub fn get_model_type(config: &Config) -> ModelType {
+    ModelType::from_name(&config.provider).unwrap_or(ModelType::Local)
+}
+
+#[allow(dead_code)]
+pub async fn get_agent(config: &Config, prompt: &str, mcp_servers: Vec<McpServer>) -> Result<AgentWrapper, Error> {
+    get_agent_with_context(config, prompt, mcp_servers, Vec::new()).await
+}
+
+/// Name of the model the configured provider will use
+pub fn active_model_name(config: &Config) -> String {
+    let settings = config.provider_settings();
+    match get_model_type(config) {
+        ModelType::Local => LocalProvider { settings }.model_name(),
+        ModelType::Gemini => GeminiProvider { settings }.model_name(),
+        ModelType::Anthropic => AnthropicProvider { settings }.model_name(),
+        ModelType::Ollama => OllamaProvider { settings }.model_name(),
+        ModelType::Mock => MockProvider { settings }.model_name(),
+    }
+}
+
+/// Make sure the model of the configured provider answers
+async fn check_model(config: &Config) -> Result<(), Error> {
+    let settings = config.provider_settings();
+    match get_model_type(config) {
+        ModelType::Local => agent_factory::check_model(&LocalProvider { settings }).await,
+        ModelType::Gemini => agent_factory::check_model(&GeminiProvider { settings }).await,
+        ModelType::Anthropic => agent_factory::check_model(&AnthropicProvider { settings }).await,
+        ModelType::Ollama => agent_factory::check_model(&OllamaProvider { settings }).await,
+        ModelType::Mock => agent_factory::check_model(&MockProvider { settings }).await,
+    }
+}
+
+async fn build_provider_agent(
+    model_type: ModelType,
+    config: &Config,
+    usage: &UsageTracker,
+    name: &str,
+    prompt: &str,
+    mcp_servers: Vec<McpServer>,
+    context_docs: Vec<String>,
+) -> Result<ProviderAgent, Error> {
+    let settings = config.settings_for(name);
+    match model_type {
+        ModelType::Local => ProviderAgent::new(&LocalProvider { settings }, config, usage, prompt, mcp_servers, context_docs).await,
+        ModelType::Gemini => ProviderAgent::new(&GeminiProvider { settings }, config, usage, prompt, mcp_servers, context_docs).await,
+        ModelType::Anthropic => ProviderAgent::new(&AnthropicProvider { settings }, config, usage, prompt, mcp_servers, context_docs).await,
+        ModelType::Ollama => ProviderAgent::new(&OllamaProvider { settings }, config, usage, prompt, mcp_servers, context_docs).await,
+        ModelType::Mock => ProviderAgent::new(&MockProvider { settings }, config, usage, prompt, mcp_servers, context_docs).await,
+    }
+}
+
+pub async fn get_agent_with_context(config: &Config, prompt: &str, mcp_servers: Vec<McpServer>, context_docs: Vec<String>) -> Result<AgentWrapper, Error> {
+    let usage = UsageTracker::new(config.prices.clone(), config.budget.clone());
+    build_chain(config, usage, prompt, mcp_servers, context_docs).await
+}
+
+/// The agent chain for `config.provider` and its fallbacks
+async fn build_chain(
+    config: &Config,
+    usage: UsageTracker,
+    prompt: &str,
+    mcp_servers: Vec<McpServer>,
+    context_docs: Vec<String>,
+) -> Result<AgentWrapper, Error> {
+    let names = std::iter::once(&config.provider).chain(config.fallback.iter());
+    let mut chain = Vec::new();
+    for (model_type, name) in names.filter_map(|name| ModelType::from_name(name).map(|model_type| (model_type, name))) {
+        chain.push(build_provider_agent(model_type, config, &usage, name, prompt, mcp_servers.clone(), context_docs.clone()).await?);
+    }
+
+    Ok(AgentWrapper { chain, usage })
+}
+
+/// The default agent plus one agent per configured route, built on first
+/// use. All of them share one usage tracker, with usage attributed per route.
+pub struct AgentRouter {
+    config: Config,
+    prompt: String,
+    mcp_servers: Vec<McpServer>,
+    context_docs: Vec<String>,
+    /// MCP resources attached to the context, after `context_docs`
+    resources: Vec<McpResourceDoc>,
+    default: AgentWrapper,
+    routes: HashMap<String, AgentWrapper>,
+}
+
+impl AgentRouter {
+    pub async fn new(
+        config: &Config,
+        prompt: &str,
+        mcp_servers: Vec<McpServer>,
+        context_docs: Vec<String>,
+        resources: Vec<McpResourceDoc>,
+    ) -> Result<AgentRouter, Error> {
+        let docs = context_docs.iter().cloned()
+            .chain(resources.iter().map(ContextWorkflow::create_resource_doc))
+            .collect();
+        let default = get_agent_with_context(config, prompt, mcp_servers.clone(), docs).await?;
+        Ok(AgentRouter {
+            config: config.clone(),
+            prompt: prompt.to_string(),
+            mcp_servers,
+            context_docs,
+            resources,
+            default,
+            routes: HashMap::new(),
+        })
+    }
+
+    /// Codebase documents followed by the attached resources
+    fn all_context_docs(&self) -> Vec<String> {
+        self.context_docs.iter().cloned()
+            .chain(self.resources.iter().map(ContextWorkflow::create_resource_doc))
+            .collect()
+    }
+
+    pub fn mcp_servers(&self) -> &[McpServer] {
+        &self.mcp_servers
+    }
+
+    pub fn resources(&self) -> &[McpResourceDoc] {
+        &self.resources
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn context_docs(&self) -> &[String] {
+        &self.context_docs
+    }
+
+    /// Attach resources, replacing earlier versions of the same ones, and
+    /// rebuild the agents with the new context. Conversation history is kept
+    /// by the caller, so it survives.
+    pub async fn attach_resources(&mut self, resources: Vec<McpResourceDoc>) -> Result<(), Error> {
+        for resource in resources {
+            match self.resources.iter_mut().find(|doc| doc.server == resource.server && doc.uri == resource.uri) {
+                Some(doc) => *doc = resource,
+                None => self.resources.push(resource),
+            }
         }
-        ModelType::Gemini => {
-            let agent = crate::gemini::_get_agent_with_context(prompt, mcp_config, context_docs);
-            AgentWrapper::Gemini(agent)
+        self.rebuild().await
+    }
+
+    /// Replace the codebase documents, e.g. after the workspace was analyzed again
+    pub async fn set_context_docs(&mut self, context_docs: Vec<String>) -> Result<(), Error> {
+        self.context_docs = context_docs;
+        self.rebuild().await
+    }
+
+    /// Switch the active provider to another model. The current agents stay
+    /// in place if the new settings are invalid or the model does not answer.
+    pub async fn set_model(&mut self, model: &str) -> Result<(), Error> {
+        let mut config = self.config.clone();
+        config.apply_overrides(&ConfigOverrides { model: Some(model.to_string()), ..Default::default() });
+        config.validate()?;
+        check_model(&config).await?;
+
+        let usage = self.usage().clone();
+        self.default = build_chain(&config, usage, &self.prompt, self.mcp_servers.clone(), self.all_context_docs()).await?;
+        self.config = config;
+        self.routes.clear();
+        Ok(())
+    }
+
+    /// Build the default agent again; routed agents are rebuilt when next used
+    async fn rebuild(&mut self) -> Result<(), Error> {
+        let usage = self.usage().clone();
+        self.default = build_chain(&self.config, usage, &self.prompt, self.mcp_servers.clone(), self.all_context_docs()).await?;
+        self.routes.clear();
+        Ok(())
+    }
+
+    /// Agent for requests without a route
+    pub fn default_agent(&self) -> &AgentWrapper {
+        &self.default
+    }
+
+    /// Usage of every agent in the session
+    pub fn usage(&self) -> &UsageTracker {
+        self.default.usage()
+    }
+
+    /// Configured routes as "label → provider (model)"
+    pub fn describe_routes(&self) -> Vec<String> {
+        self.config.routes.keys()
+            .filter_map(|label| self.config.for_route(label).map(|config| {
+                format!("{} → {} ({})", label, config.provider, active_model_name(&config))
+            }))
+            .collect()
+    }
+
+    pub fn has_route(&self, label: &str) -> bool {
+        self.config.routes.contains_key(label)
+    }
+
+    /// Agent for an agent role or task label; labels without a route get the default agent
+    pub async fn agent_for(&mut self, label: &str) -> Result<&AgentWrapper, Error> {
+        if !self.routes.contains_key(label)
+            && let Some(config) = self.config.for_route(label) {
+            println!("🧭 Route {} | Provider: {} | Model: {}", label, config.provider, active_model_name(&config));
+            let usage = self.usage().scoped(label);
+            let agent = build_chain(&config, usage, &self.prompt, self.mcp_servers.clone(), self.all_context_docs()).await?;
+            self.routes.insert(label.to_string(), agent);
         }
+        Ok(self.routes.get(label).unwrap_or(&self.default))
     }
 }
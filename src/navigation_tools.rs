@@ -0,0 +1,331 @@
+use rig::tool::Tool;
+use rig::completion::request::ToolDefinition;
+use serde::Deserialize;
+use schemars::JsonSchema;
+use std::path::{Path, PathBuf};
+
+use crate::file_tools::{confine, FileToolError};
+use crate::lsp_client::{char_to_utf16_column, is_installed, server_for, shared_client};
+use crate::symbol_index::{parse_location, Language, SymbolIndex, SymbolLocation};
+
+// Symbol navigation arguments shared by go-to-definition and find-references
+#[derive(Clone, Deserialize, JsonSchema)]
+pub struct SymbolQueryArgs {
+    /// Symbol name to look up
+    #[serde(default)]
+    pub symbol: Option<String>,
+    /// Location of a symbol usage as "path:line" or "path:line:column"
+    #[serde(default)]
+    pub location: Option<String>,
+    /// Directory to search (default: the workspace)
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Ask a locally installed language server first (default: false)
+    #[serde(default)]
+    pub use_lsp: bool,
+}
+
+fn symbol_query_parameters(symbol_description: &str) -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "symbol": {
+                "type": "string",
+                "description": symbol_description
+            },
+            "location": {
+                "type": "string",
+                "description": "Location of a usage of the symbol as 'path:line' or 'path:line:column' (1-based). Used to pick the symbol when 'symbol' is omitted"
+            },
+            "path": {
+                "type": "string",
+                "description": "Directory to search (default: the workspace)"
+            },
+            "use_lsp": {
+                "type": "boolean",
+                "description": "Ask a locally installed language server (rust-analyzer, pyright, ...) first, falling back to tree-sitter (default: false)",
+                "default": false
+            }
+        }
+    })
+}
+
+/// The symbol a query refers to, and the usage position if one was given
+struct ResolvedQuery {
+    name: String,
+    position: Option<(String, usize, usize)>,
+}
+
+/// The directory a query searches: `path` or the workspace, which it must lie in
+fn search_root(workspace: &Path, path: Option<&str>) -> Result<PathBuf, FileToolError> {
+    let root = path.map_or(workspace, Path::new);
+    if !root.exists() {
+        return Err(FileToolError::FileNotFound(root.display().to_string()));
+    }
+    confine(workspace, root)?;
+    Ok(root.to_path_buf())
+}
+
+/// Run tree-sitter work off the async runtime; it parses the whole workspace
+async fn blocking<T: Send + 'static>(work: impl FnOnce() -> Result<T, FileToolError> + Send + 'static) -> Result<T, FileToolError> {
+    tokio::task::spawn_blocking(work).await.map_err(|e| FileToolError::Io(std::io::Error::other(e)))?
+}
+
+fn resolve_query(workspace: &Path, index: &mut SymbolIndex, args: &SymbolQueryArgs) -> Result<ResolvedQuery, FileToolError> {
+    let Some(location) = &args.location else {
+        let name = args.symbol.clone()
+            .ok_or_else(|| FileToolError::InvalidPath("Provide either 'symbol' or 'location'".to_string()))?;
+        return Ok(ResolvedQuery { name, position: None });
+    };
+
+    let (path, line, column) = parse_location(location)?;
+    confine(workspace, Path::new(&path))?;
+    let name = match &args.symbol {
+        Some(symbol) => symbol.clone(),
+        None => index.identifier_at(&path, line, column)?,
+    };
+
+    // Without an explicit column, point at the symbol's first occurrence on the line
+    let column = match column {
+        Some(column) => column,
+        None => {
+            let content = std::fs::read_to_string(&path)?;
+            let line_text = content.lines().nth(line.saturating_sub(1)).unwrap_or("");
+            line_text.find(&name)
+                .map(|byte| line_text[..byte].chars().count() + 1)
+                .ok_or_else(|| FileToolError::InvalidPath(format!("'{}' does not appear on {}:{}", name, path, line)))?
+        }
+    };
+
+    Ok(ResolvedQuery { name, position: Some((path, line, column)) })
+}
+
+/// Index the search root and resolve the query, off the async runtime
+async fn prepare(workspace: &Path, args: &SymbolQueryArgs) -> Result<(PathBuf, SymbolIndex, ResolvedQuery), FileToolError> {
+    let root = search_root(workspace, args.path.as_deref())?;
+    let (workspace, index_root, args) = (workspace.to_path_buf(), root.clone(), args.clone());
+    let (index, query) = blocking(move || {
+        let mut index = SymbolIndex::new(&index_root)?;
+        let query = resolve_query(&workspace, &mut index, &args)?;
+        Ok((index, query))
+    }).await?;
+    Ok((root, index, query))
+}
+
+enum LspQuery {
+    Definition,
+    References,
+}
+
+/// Ask the file's language server, which keeps running for later lookups.
+/// Returns Err with a reason when it is unavailable.
+async fn lsp_lookup(root: &Path, file: &str, line: usize, column: usize, query: LspQuery) -> Result<Vec<SymbolLocation>, String> {
+    let language = Language::from_path(Path::new(file))
+        .ok_or_else(|| format!("no language support for {}", file))?;
+    let (program, args) = server_for(language)
+        .ok_or_else(|| format!("no known language server for {:?}", language))?;
+    if !is_installed(program) {
+        return Err(format!("{} is not installed", program));
+    }
+
+    let client = shared_client(program, args, root).await.map_err(|e| e.to_string())?;
+    let mut client = client.lock().await;
+
+    let uri = client.open_document(Path::new(file), language).await.map_err(|e| e.to_string())?;
+    let result = async {
+        let line_text = tokio::fs::read_to_string(file).await?
+            .lines()
+            .nth(line - 1)
+            .unwrap_or("")
+            .to_string();
+        let character = char_to_utf16_column(&line_text, column - 1);
+        match query {
+            LspQuery::Definition => client.definition(&uri, line - 1, character).await,
+            LspQuery::References => client.references(&uri, line - 1, character).await,
+        }
+    }.await;
+
+    // Closed again so the next lookup sends the file's current content
+    let _ = client.close_document(&uri).await;
+    result.map_err(|e: FileToolError| e.to_string())
+}
+
+fn format_location(location: &SymbolLocation) -> String {
+    format!("{} {}", location, location.line_text)
+}
+
+// Go To Definition Tool
+#[derive(Debug, Clone)]
+pub struct DefinitionFinder {
+    /// Lookups stay inside this directory
+    pub root: PathBuf,
+}
+
+impl Tool for DefinitionFinder {
+    const NAME: &'static str = "find_definition";
+    type Error = FileToolError;
+    type Args = SymbolQueryArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Go to definition: find where a symbol (function, type, method, constant, ...) is defined. Give a symbol name, or a path:line[:column] where it is used. Returns path:line:column references. Supports Rust, Python, JavaScript, TypeScript, Go, C, C++ and Java.".to_string(),
+            parameters: symbol_query_parameters("Name of the symbol to find the definition of"),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        println!("🔧 Tool: find_definition | Symbol: {:?} | Location: {:?}", args.symbol, args.location);
+
+        let (root, mut index, query) = prepare(&self.root, &args).await?;
+        let mut notes = Vec::new();
+
+        if args.use_lsp {
+            match &query.position {
+                Some((file, line, column)) => {
+                    match lsp_lookup(&root, file, *line, *column, LspQuery::Definition).await {
+                        Ok(locations) if !locations.is_empty() => {
+                            println!("✅ Tool: find_definition | Language server found {} definitions", locations.len());
+                            let lines: Vec<String> = locations.iter().map(format_location).collect();
+                            return Ok(format!("Definition of `{}` (language server):\n{}", query.name, lines.join("\n")));
+                        }
+                        Ok(_) => notes.push("language server returned no definition".to_string()),
+                        Err(reason) => notes.push(reason),
+                    }
+                }
+                None => notes.push("language server needs a 'location'".to_string()),
+            }
+        }
+
+        let name = query.name.clone();
+        let definitions = blocking(move || Ok(index.definitions(&name))).await?;
+        let note = if notes.is_empty() { String::new() } else { format!(" [{}]", notes.join("; ")) };
+
+        if definitions.is_empty() {
+            println!("✅ Tool: find_definition | No definition found");
+            return Ok(format!("No definition of `{}` found in {} (tree-sitter){}", query.name, root.display(), note));
+        }
+
+        println!("✅ Tool: find_definition | Success: {} definitions", definitions.len());
+        let lines: Vec<String> = definitions.iter()
+            .map(|d| format!("{} [{}] {}", d.location, d.kind, d.location.line_text))
+            .collect();
+        Ok(format!("Definition of `{}` (tree-sitter){}:\n{}", query.name, note, lines.join("\n")))
+    }
+}
+
+// Find References Tool
+#[derive(Debug, Clone)]
+pub struct ReferenceFinder {
+    /// Lookups stay inside this directory
+    pub root: PathBuf,
+}
+
+impl Tool for ReferenceFinder {
+    const NAME: &'static str = "find_references";
+    type Error = FileToolError;
+    type Args = SymbolQueryArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Find all references to a symbol across the workspace, skipping comments and strings. Give a symbol name, or a path:line[:column] where it is used. Use before renaming or changing a signature.".to_string(),
+            parameters: symbol_query_parameters("Name of the symbol to find references to"),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        println!("🔧 Tool: find_references | Symbol: {:?} | Location: {:?}", args.symbol, args.location);
+
+        let (root, mut index, query) = prepare(&self.root, &args).await?;
+        let mut notes = Vec::new();
+
+        if args.use_lsp {
+            // Without a usage location, anchor the language server on the definition
+            let position = match query.position.clone() {
+                Some(position) => Some(position),
+                None => {
+                    let name = query.name.clone();
+                    let (returned, anchor) = blocking(move || {
+                        let anchor = index.definitions(&name)
+                            .first()
+                            .map(|d| (d.location.path.clone(), d.location.line, d.location.column));
+                        Ok((index, anchor))
+                    }).await?;
+                    index = returned;
+                    anchor
+                }
+            };
+
+            match position {
+                Some((file, line, column)) => {
+                    match lsp_lookup(&root, &file, line, column, LspQuery::References).await {
+                        Ok(locations) if !locations.is_empty() => {
+                            println!("✅ Tool: find_references | Language server found {} references", locations.len());
+                            let lines: Vec<String> = locations.iter().map(format_location).collect();
+                            return Ok(format!("Found {} references to `{}` (language server):\n{}",
+                                locations.len(), query.name, lines.join("\n")));
+                        }
+                        Ok(_) => notes.push("language server returned no references".to_string()),
+                        Err(reason) => notes.push(reason),
+                    }
+                }
+                None => notes.push("no position to anchor the language server".to_string()),
+            }
+        }
+
+        let name = query.name.clone();
+        let references = blocking(move || Ok(index.references(&name))).await?;
+        let note = if notes.is_empty() { String::new() } else { format!(" [{}]", notes.join("; ")) };
+
+        if references.is_empty() {
+            println!("✅ Tool: find_references | No references found");
+            return Ok(format!("No references to `{}` found in {} (tree-sitter){}", query.name, root.display(), note));
+        }
+
+        let mut files: Vec<&str> = references.iter().map(|r| r.location.path.as_str()).collect();
+        files.dedup();
+
+        println!("✅ Tool: find_references | Success: {} references in {} files", references.len(), files.len());
+        let lines: Vec<String> = references.iter()
+            .map(|r| {
+                let marker = if r.is_definition { " (definition)" } else { "" };
+                format!("{}{} {}", r.location, marker, r.location.line_text)
+            })
+            .collect();
+        Ok(format!("Found {} references to `{}` in {} files (tree-sitter){}:\n{}",
+            references.len(), query.name, files.len(), note, lines.join("\n")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(symbol: Option<&str>, location: Option<String>, path: Option<String>) -> SymbolQueryArgs {
+        SymbolQueryArgs { symbol: symbol.map(str::to_string), location, path, use_lsp: false }
+    }
+
+    #[tokio::test]
+    async fn lookups_stay_in_the_workspace() {
+        let workspace = std::env::temp_dir().join(format!("llmo-navigation-{}", std::process::id()));
+        std::fs::create_dir_all(&workspace).unwrap();
+        std::fs::write(workspace.join("lib.rs"), "pub fn helper() {}\n").unwrap();
+        let outside = workspace.with_extension("rs");
+        std::fs::write(&outside, "fn helper() {}\n").unwrap();
+        let finder = DefinitionFinder { root: workspace.clone() };
+
+        let found = finder.call(args(Some("helper"), None, None)).await.unwrap();
+        assert!(found.contains("lib.rs:1:8"), "{}", found);
+
+        let error = finder.call(args(Some("helper"), None, Some("/".to_string()))).await.unwrap_err();
+        assert!(matches!(error, FileToolError::PermissionDenied(_)), "{}", error);
+        let error = finder.call(args(None, Some(format!("{}:1:4", outside.display())), None)).await.unwrap_err();
+        assert!(matches!(error, FileToolError::PermissionDenied(_)), "{}", error);
+
+        std::fs::remove_dir_all(&workspace).unwrap();
+        std::fs::remove_file(&outside).unwrap();
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::Error;
+use rig::client::CompletionClient;
+use rig::providers::ollama;
+
+use crate::agent_factory::ModelProvider;
+use crate::config::ProviderConfig;
+use crate::tool_protocol::ToolCalling;
+use crate::usage::ReportedUsage;
+
+/// Native Ollama API, configured from OLLAMA_API_BASE and OLLAMA_MODEL_NAME
+pub struct OllamaProvider {
+    pub settings: ProviderConfig,
+}
+
+impl ModelProvider for OllamaProvider {
+    type Model = ollama::CompletionModel;
+
+    const NAME: &'static str = "ollama";
+
+    fn model_name(&self) -> String {
+        self.settings.model.clone()
+            .unwrap_or_else(|| std::env::var("OLLAMA_MODEL_NAME").unwrap_or_else(|_| "qwen3:32b".to_string()))
+    }
+
+    fn model(&self) -> Result<Self::Model, Error> {
+        let api_base = self.settings.api_base.clone()
+            .unwrap_or_else(|| std::env::var("OLLAMA_API_BASE").unwrap_or_else(|_| "http://localhost:11434".into()));
+        Ok(ollama::Client::from_url(&api_base).completion_model(&self.model_name()))
+    }
+
+    fn temperature(&self) -> f64 {
+        self.settings.temperature.unwrap_or(0.2)
+    }
+
+    fn max_tokens(&self) -> u64 {
+        self.settings.max_tokens.unwrap_or(3000)
+    }
+
+    /// Local models often lack function calling, so they are probed unless configured
+    fn tool_calling(&self) -> ToolCalling {
+        self.settings.tool_calling.unwrap_or(ToolCalling::Auto)
+    }
+
+    fn reported_usage(response: &ollama::CompletionResponse) -> ReportedUsage {
+        ReportedUsage { input: response.prompt_eval_count, output: response.eval_count, total: None }
+    }
+
+    fn reported_streaming_usage(response: &ollama::StreamingCompletionResponse) -> ReportedUsage {
+        ReportedUsage { input: response.prompt_eval_count, output: response.eval_count, total: None }
+    }
+}
@@ -0,0 +1,248 @@
+use rig::OneOrMany;
+use rig::message::AssistantContent;
+use serde::Deserialize;
+
+const THINK_OPEN: &str = "<think>";
+const THINK_CLOSE: &str = "</think>";
+
+/// How reasoning (`<think>` blocks) is shown in the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThinkingDisplay {
+    /// Print the reasoning as it arrives, dimmed
+    Show,
+    /// Print a one-line note with the length of the reasoning
+    #[default]
+    Collapsed,
+    Hidden,
+}
+
+/// Reasoning settings for models such as Qwen3
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThinkingConfig {
+    /// Turn thinking on or off for every request; unset leaves it to the model
+    pub enabled: Option<bool>,
+    #[serde(default)]
+    pub display: ThinkingDisplay,
+}
+
+/// A piece of model output, either reasoning or part of the answer
+#[derive(Debug, PartialEq)]
+pub enum Segment {
+    Reasoning(String),
+    Answer(String),
+}
+
+/// Splits streamed text into reasoning and answer, coping with tags split across chunks
+#[derive(Default)]
+pub struct ThinkSplitter {
+    in_think: bool,
+    /// Text held back because it may be the start of a tag
+    pending: String,
+    /// Whether answer text has been produced yet; leading blank lines are dropped
+    answered: bool,
+    /// The same for the current reasoning block
+    reasoned: bool,
+}
+
+impl ThinkSplitter {
+    pub fn push(&mut self, chunk: &str) -> Vec<Segment> {
+        self.pending.push_str(chunk);
+        let mut segments = Vec::new();
+
+        loop {
+            let tag = if self.in_think { THINK_CLOSE } else { THINK_OPEN };
+            // A closing tag without an opening one ends reasoning whose opening
+            // tag was part of the prompt template; only the rest of it is still here
+            let stray_close = self.pending.find(THINK_CLOSE)
+                .filter(|close| !self.in_think && self.pending.find(THINK_OPEN).is_none_or(|open| *close < open));
+            if let Some(index) = stray_close {
+                let text = self.pending[..index].to_string();
+                self.in_think = true;
+                self.emit(&mut segments, text);
+                self.in_think = false;
+                self.answered = false;
+                self.pending.drain(..index + THINK_CLOSE.len());
+                continue;
+            }
+
+            match self.pending.find(tag) {
+                Some(index) => {
+                    let text = self.pending[..index].to_string();
+                    self.emit(&mut segments, text);
+                    self.pending.drain(..index + tag.len());
+                    self.in_think = !self.in_think;
+                    self.reasoned = false;
+                }
+                None => {
+                    // Outside reasoning a stray closing tag may be on its way too
+                    let markers: &[&str] = if self.in_think { &[THINK_CLOSE] } else { &[THINK_OPEN, THINK_CLOSE] };
+                    let pending = &self.pending;
+                    let keep = markers.iter()
+                        .flat_map(|marker| (1..marker.len()).filter(move |length| pending.ends_with(&marker[..*length])))
+                        .max()
+                        .unwrap_or(0);
+                    let text = self.pending[..self.pending.len() - keep].to_string();
+                    self.emit(&mut segments, text);
+                    self.pending.drain(..self.pending.len() - keep);
+                    return segments;
+                }
+            }
+        }
+    }
+
+    /// Flush whatever was held back at the end of the stream
+    pub fn finish(&mut self) -> Vec<Segment> {
+        let text = std::mem::take(&mut self.pending);
+        let mut segments = Vec::new();
+        self.emit(&mut segments, text);
+        segments
+    }
+
+    fn emit(&mut self, segments: &mut Vec<Segment>, text: String) {
+        if self.in_think {
+            let text = if self.reasoned { text } else { text.trim_start().to_string() };
+            if !text.is_empty() {
+                self.reasoned = true;
+                segments.push(Segment::Reasoning(text));
+            }
+            return;
+        }
+        let text = if self.answered { text } else { text.trim_start().to_string() };
+        if !text.is_empty() {
+            self.answered = true;
+            segments.push(Segment::Answer(text));
+        }
+    }
+}
+
+/// Separate the reasoning from a complete answer. A closing tag without an
+/// opening one (chat templates often pre-fill `<think>`) means everything
+/// before it is reasoning.
+pub fn split_reasoning(text: &str) -> (String, String) {
+    let text = match (text.find(THINK_OPEN), text.find(THINK_CLOSE)) {
+        (None, Some(_)) => format!("{}{}", THINK_OPEN, text),
+        (Some(open), Some(close)) if close < open => format!("{}{}", THINK_OPEN, text),
+        _ => text.to_string(),
+    };
+
+    let mut splitter = ThinkSplitter::default();
+    let (mut reasoning, mut answer) = (String::new(), String::new());
+    for segment in splitter.push(&text).into_iter().chain(splitter.finish()) {
+        match segment {
+            Segment::Reasoning(text) => reasoning.push_str(&text),
+            Segment::Answer(text) => answer.push_str(&text),
+        }
+    }
+    (reasoning.trim().to_string(), answer)
+}
+
+/// The assistant message to keep in history: text without its reasoning, tool calls unchanged
+pub fn strip_reasoning(choice: &OneOrMany<AssistantContent>) -> OneOrMany<AssistantContent> {
+    let text = choice.iter()
+        .filter_map(|content| match content {
+            AssistantContent::Text(text) => Some(text.text.as_str()),
+            AssistantContent::ToolCall(_) => None,
+        })
+        .collect::<String>();
+    let (_, answer) = split_reasoning(&text);
+
+    let tool_calls = choice.iter().filter(|content| matches!(content, AssistantContent::ToolCall(_))).cloned();
+    let content = (!answer.is_empty()).then(|| AssistantContent::text(answer)).into_iter().chain(tool_calls);
+    OneOrMany::many(content).unwrap_or_else(|_| OneOrMany::one(AssistantContent::text("")))
+}
+
+/// Take a leading `/think` or `/no_think` off the user's input; it switches
+/// thinking on or off for that request only
+pub fn parse_thinking_toggle(input: &str) -> (Option<bool>, &str) {
+    for (switch, enabled) in [("/no_think", false), ("/think", true)] {
+        if let Some(rest) = input.strip_prefix(switch)
+            && (rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+            return (Some(enabled), rest.trim_start());
+        }
+    }
+    (None, input)
+}
+
+/// Append the Qwen3 soft switch (`/think` or `/no_think`) to a prompt
+pub fn with_thinking_switch(prompt: &str, enabled: Option<bool>) -> String {
+    match enabled {
+        Some(true) => format!("{} /think", prompt),
+        Some(false) => format!("{} /no_think", prompt),
+        None => prompt.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reasoning and answer produced by streaming `chunks` through a splitter
+    fn split_chunks(chunks: &[&str]) -> (String, String) {
+        let mut splitter = ThinkSplitter::default();
+        let mut segments = Vec::new();
+        for chunk in chunks {
+            segments.extend(splitter.push(chunk));
+        }
+        segments.extend(splitter.finish());
+
+        let (mut reasoning, mut answer) = (String::new(), String::new());
+        for segment in segments {
+            match segment {
+                Segment::Reasoning(text) => reasoning.push_str(&text),
+                Segment::Answer(text) => answer.push_str(&text),
+            }
+        }
+        (reasoning, answer)
+    }
+
+    #[test]
+    fn tags_split_across_chunks() {
+        assert_eq!(split_chunks(&["<thi", "nk>plan", "</th", "ink>answer"]), ("plan".to_string(), "answer".to_string()));
+        assert_eq!(split_chunks(&["<", "think", ">", "plan", "<", "/think", ">", "answer"]), ("plan".to_string(), "answer".to_string()));
+    }
+
+    #[test]
+    fn one_character_chunks_match_the_whole_text() {
+        let text = "<think>\nCheck run.sh first.\n</think>\n\nThe job trains for 3 epochs.";
+        let chunks: Vec<String> = text.chars().map(String::from).collect();
+        let chunks: Vec<&str> = chunks.iter().map(String::as_str).collect();
+        assert_eq!(split_chunks(&chunks), split_chunks(&[text]));
+        assert_eq!(split_chunks(&[text]), ("Check run.sh first.\n".to_string(), "The job trains for 3 epochs.".to_string()));
+    }
+
+    #[test]
+    fn unclosed_block_is_reasoning() {
+        assert_eq!(split_chunks(&["<think>still ", "thinking"]), ("still thinking".to_string(), String::new()));
+    }
+
+    #[test]
+    fn closing_tag_without_opening_one_ends_reasoning() {
+        assert_eq!(split_chunks(&["from the template</think>Answer"]), ("from the template".to_string(), "Answer".to_string()));
+        assert_eq!(split_reasoning("from the template</think>Answer"), ("from the template".to_string(), "Answer".to_string()));
+        // Text streamed before the tag arrived was already shown as answer; the tag itself never is
+        assert_eq!(split_chunks(&["from the template</th", "ink>Answer"]), (String::new(), "from the templateAnswer".to_string()));
+    }
+
+    #[test]
+    fn text_without_tags_is_the_answer() {
+        assert_eq!(split_chunks(&["if a <", " b then"]), (String::new(), "if a < b then".to_string()));
+        assert_eq!(split_reasoning("Plain answer."), (String::new(), "Plain answer.".to_string()));
+    }
+
+    #[test]
+    fn strip_reasoning_keeps_answers_and_tool_calls() {
+        let plain = OneOrMany::one(AssistantContent::text("No tags here."));
+        assert_eq!(strip_reasoning(&plain), plain);
+
+        let tool_call = AssistantContent::tool_call("call_1", "read_file", serde_json::json!({ "path": "run.sh" }));
+        let choice = OneOrMany::many(vec![AssistantContent::text("<think>need the script</think>Reading it."), tool_call.clone()]).unwrap();
+        let stripped: Vec<AssistantContent> = strip_reasoning(&choice).into_iter().collect();
+        assert_eq!(stripped, [AssistantContent::text("Reading it."), tool_call.clone()]);
+
+        let choice = OneOrMany::many(vec![AssistantContent::text("<think>only reasoning</think>"), tool_call.clone()]).unwrap();
+        let stripped: Vec<AssistantContent> = strip_reasoning(&choice).into_iter().collect();
+        assert_eq!(stripped, [tool_call]);
+    }
+}
@@ -0,0 +1,184 @@
+use rig::completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse, PromptError};
+use rig::streaming::StreamingCompletionResponse;
+use serde::Deserialize;
+use regex::Regex;
+use std::future::Future;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use crate::usage::BudgetExceeded;
+
+/// Status line rig puts in front of streaming error bodies, e.g. "503 Service Unavailable: ..."
+static STATUS_PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\d{3}) [A-Za-z]").unwrap()
+});
+/// Status codes inside JSON error bodies, e.g. `"code": 429` or `"status": "503"`
+static STATUS_FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#""(?:code|status)"\s*:\s*"?(\d{3})\b"#).unwrap()
+});
+/// Whole phrases providers use for connection failures, rate limiting and
+/// server errors; rig reports most of these as text without the status code
+static RETRYABLE_PHRASES: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?i)\b(?:rate[ _]limit(?:ed|_error|_exceeded)?|too many requests|overloaded(?:_error)?",
+        r"|internal server error|bad gateway|service unavailable|gateway timeout|temporarily unavailable",
+        r"|resource_exhausted|error sending request|connection refused|connection reset)\b",
+    )).unwrap()
+});
+
+/// Exponential backoff settings for completion requests
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// Attempts per request, including the first (default: 4)
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+    /// Delay before the first retry (default: 500 ms)
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    /// Upper bound for the delay between retries (default: 8000 ms)
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+}
+
+fn default_max_attempts() -> u32 { 4 }
+fn default_initial_backoff_ms() -> u64 { 500 }
+fn default_max_backoff_ms() -> u64 { 8000 }
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: default_max_attempts(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1-based), doubling each time
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u64.saturating_pow(retry.saturating_sub(1));
+        Duration::from_millis(self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms))
+    }
+
+    /// Run `operation` until it succeeds, fails with a non-retryable error or
+    /// runs out of attempts, logging every failed attempt
+    pub async fn run<T, F, Fut>(&self, label: &str, mut operation: F) -> Result<T, CompletionError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, CompletionError>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_attempts && is_retryable(&e) => {
+                    let delay = self.backoff(attempt);
+                    println!("⚠️  {} | Attempt {}/{} failed: {} | Retrying in {:.1}s",
+                        label, attempt, self.max_attempts, e, delay.as_secs_f64());
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    if attempt > 1 || is_retryable(&e) {
+                        println!("❌ {} | Attempt {}/{} failed: {}", label, attempt, self.max_attempts, e);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+/// Connection failures, timeouts, rate limits and server errors are worth retrying
+pub fn is_retryable(error: &CompletionError) -> bool {
+    match error {
+        CompletionError::HttpError(e) => {
+            e.is_connect() || e.is_timeout() || e.status().is_some_and(|status| {
+                status.as_u16() == 429 || status.is_server_error()
+            })
+        }
+        CompletionError::ProviderError(message) | CompletionError::ResponseError(message) => {
+            let retryable_status = |status: &str| status.parse::<u16>().is_ok_and(|status| status == 429 || (500..600).contains(&status));
+            STATUS_PREFIX.captures(message).is_some_and(|captures| retryable_status(&captures[1]))
+                || STATUS_FIELD.captures_iter(message).any(|captures| retryable_status(&captures[1]))
+                || RETRYABLE_PHRASES.is_match(message)
+        }
+        CompletionError::JsonError(_) | CompletionError::RequestError(_) => false,
+    }
+}
+
+/// True if a failed turn was the provider's fault (as opposed to a tool
+/// error, hitting the tool-call depth limit or running out of budget), so
+/// another provider may succeed
+pub fn is_provider_failure(error: &anyhow::Error) -> bool {
+    if error.chain().any(|cause| cause.is::<BudgetExceeded>()) {
+        return false;
+    }
+    matches!(error.downcast_ref::<PromptError>(), Some(PromptError::CompletionError(_)))
+        || error.downcast_ref::<CompletionError>().is_some()
+}
+
+/// Completion model wrapper that retries requests according to a `RetryPolicy`
+#[derive(Clone)]
+pub struct RetryingModel<M> {
+    inner: M,
+    policy: RetryPolicy,
+    label: String,
+}
+
+impl<M> RetryingModel<M> {
+    pub fn new(inner: M, policy: RetryPolicy, label: String) -> Self {
+        RetryingModel { inner, policy, label }
+    }
+}
+
+impl<M: CompletionModel> CompletionModel for RetryingModel<M> {
+    type Response = M::Response;
+    type StreamingResponse = M::StreamingResponse;
+
+    async fn completion(&self, request: CompletionRequest) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        self.policy.run(&self.label, || self.inner.completion(request.clone())).await
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        self.policy.run(&self.label, || self.inner.stream(request.clone())).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider_error(message: &str) -> CompletionError {
+        CompletionError::ProviderError(message.to_string())
+    }
+
+    #[test]
+    fn status_codes_decide_retries() {
+        assert!(is_retryable(&provider_error("503 Service Unavailable: upstream down")));
+        assert!(is_retryable(&provider_error("429 Too Many Requests")));
+        assert!(is_retryable(&provider_error(r#"{"error": {"code": 429, "message": "slow down"}}"#)));
+        assert!(is_retryable(&provider_error(r#"{"error": {"status": "500"}}"#)));
+        assert!(!is_retryable(&provider_error("400 Bad Request: invalid model")));
+        assert!(!is_retryable(&provider_error(r#"{"error": {"code": 401, "message": "invalid key"}}"#)));
+    }
+
+    #[test]
+    fn phrases_decide_retries() {
+        assert!(is_retryable(&provider_error("Overloaded")));
+        assert!(is_retryable(&CompletionError::ResponseError("rate_limit_error: try again later".to_string())));
+        assert!(is_retryable(&provider_error("error sending request for url (http://localhost:1234/)")));
+        assert!(is_retryable(&provider_error("Connection refused")));
+        assert!(!is_retryable(&provider_error("model not found")));
+        // Only whole phrases count
+        assert!(!is_retryable(&provider_error("the overloadedness metric is missing")));
+    }
+
+    #[test]
+    fn malformed_responses_are_not_retried() {
+        let json = serde_json::from_str::<serde_json::Value>("{").unwrap_err();
+        assert!(!is_retryable(&CompletionError::JsonError(json)));
+    }
+}
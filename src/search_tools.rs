@@ -0,0 +1,380 @@
+use rig::tool::Tool;
+use rig::completion::request::ToolDefinition;
+use serde::Deserialize;
+use schemars::JsonSchema;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use walkdir::WalkDir;
+use regex::RegexBuilder;
+use glob::{MatchOptions, Pattern};
+
+use crate::file_tools::{confine, is_ignored, FileToolError};
+
+/// Returns true for hidden entries (other than the search root itself)
+fn is_hidden(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0
+        && entry.file_name().to_str().is_some_and(|name| name.starts_with('.'))
+}
+
+/// Walk every file under `root`, honoring the shared ignore rules
+fn walk_files(root: &Path, include_hidden: bool) -> impl Iterator<Item = walkdir::DirEntry> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(move |e| !is_ignored(e) && (include_hidden || !is_hidden(e)))
+        .flatten()
+        .filter(|e| e.file_type().is_file())
+}
+
+/// Path of `path` relative to the search root, with forward slashes
+fn relative_display(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Path as it should be passed back to the other file tools
+fn reference_path(path: &Path) -> String {
+    path.strip_prefix(".")
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// The directory to search: `path`, or the workspace, which it must stay inside
+fn check_search_root<'a>(workspace: &'a Path, path: Option<&'a str>) -> Result<&'a Path, FileToolError> {
+    let root = path.map_or(workspace, Path::new);
+    if !root.exists() {
+        return Err(FileToolError::FileNotFound(root.display().to_string()));
+    }
+    confine(workspace, root)?;
+    if !root.is_dir() {
+        return Err(FileToolError::InvalidPath(format!("{} is not a directory", root.display())));
+    }
+    Ok(root)
+}
+
+fn check_max_results(max_results: usize) -> Result<(), FileToolError> {
+    if max_results == 0 {
+        return Err(FileToolError::InvalidArgument("max_results must be at least 1".to_string()));
+    }
+    Ok(())
+}
+
+// Code Search Tool
+#[derive(Deserialize, JsonSchema)]
+pub struct SearchCodeArgs {
+    /// Regular expression to search for
+    pub pattern: String,
+    /// Directory to search in, inside the workspace (default: the workspace)
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Case handling: "sensitive", "insensitive" or "smart" (default: smart)
+    #[serde(default)]
+    pub case: CaseMode,
+    /// Only search files with these extensions, comma separated (e.g. "rs,toml")
+    #[serde(default)]
+    pub file_type: Option<String>,
+    /// Number of context lines to show around each match (default: 0)
+    #[serde(default)]
+    pub context_lines: usize,
+    /// Maximum number of matches to return (default: 50)
+    #[serde(default = "default_max_results")]
+    pub max_results: usize,
+    /// Include hidden files and directories (default: false)
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+#[derive(Deserialize, JsonSchema, Default, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CaseMode {
+    Sensitive,
+    Insensitive,
+    /// Case insensitive unless the pattern contains an uppercase letter
+    #[default]
+    Smart,
+}
+
+fn default_max_results() -> usize { 50 }
+
+#[derive(Debug, Clone)]
+pub struct CodeSearcher {
+    /// Searches stay inside this directory
+    pub root: PathBuf,
+}
+
+impl Tool for CodeSearcher {
+    const NAME: &'static str = "search_code";
+    type Error = FileToolError;
+    type Args = SearchCodeArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Search file contents with a regular expression (like grep). Returns matches as path:line: text. Use this to locate symbols instead of reading whole files.".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "pattern": {
+                        "type": "string",
+                        "description": "Regular expression to search for (Rust regex syntax)"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Directory to search in, inside the workspace (default: the workspace)"
+                    },
+                    "case": {
+                        "type": "string",
+                        "enum": ["sensitive", "insensitive", "smart"],
+                        "description": "Case handling. 'smart' is case insensitive unless the pattern has an uppercase letter (default: smart)",
+                        "default": "smart"
+                    },
+                    "file_type": {
+                        "type": "string",
+                        "description": "Only search files with these extensions, comma separated (e.g. 'rs,toml')"
+                    },
+                    "context_lines": {
+                        "type": "integer",
+                        "description": "Number of context lines to show around each match (default: 0)",
+                        "default": 0,
+                        "minimum": 0
+                    },
+                    "max_results": {
+                        "type": "integer",
+                        "description": "Maximum number of matches to return (default: 50)",
+                        "default": 50,
+                        "minimum": 1
+                    },
+                    "include_hidden": {
+                        "type": "boolean",
+                        "description": "Include hidden files and directories (default: false)",
+                        "default": false
+                    }
+                },
+                "required": ["pattern"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let root = check_search_root(&self.root, args.path.as_deref())?;
+        println!("🔧 Tool: search_code | Pattern: {} | Path: {}", args.pattern, root.display());
+        check_max_results(args.max_results)?;
+
+        let case_insensitive = match args.case {
+            CaseMode::Sensitive => false,
+            CaseMode::Insensitive => true,
+            CaseMode::Smart => !args.pattern.chars().any(|c| c.is_uppercase()),
+        };
+        let regex = RegexBuilder::new(&args.pattern)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|e| FileToolError::InvalidPattern(e.to_string()))?;
+
+        let extensions: Option<Vec<String>> = args.file_type.as_ref().map(|types| {
+            types.split(',')
+                .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                .filter(|ext| !ext.is_empty())
+                .collect()
+        });
+
+        let mut output = String::new();
+        let mut match_count = 0;
+        let mut files_with_matches = 0;
+        let mut truncated = false;
+
+        for entry in walk_files(root, args.include_hidden) {
+            let file_path = entry.path();
+
+            if let Some(ref extensions) = extensions {
+                let extension = file_path.extension()
+                    .and_then(|ext| ext.to_str())
+                    .unwrap_or("")
+                    .to_lowercase();
+                if !extensions.contains(&extension) {
+                    continue;
+                }
+            }
+
+            // Skip binary and non UTF-8 files
+            let content = match fs::read(file_path).await {
+                Ok(bytes) if !bytes.contains(&0) => match String::from_utf8(bytes) {
+                    Ok(content) => content,
+                    Err(_) => continue,
+                },
+                _ => continue,
+            };
+
+            let lines: Vec<&str> = content.lines().collect();
+            let mut matched_lines = Vec::new();
+
+            for (index, line) in lines.iter().enumerate() {
+                if regex.is_match(line) {
+                    if match_count >= args.max_results {
+                        truncated = true;
+                        break;
+                    }
+                    match_count += 1;
+                    matched_lines.push(index);
+                }
+            }
+
+            if !matched_lines.is_empty() {
+                files_with_matches += 1;
+                let display_path = reference_path(file_path);
+                let mut next_line = 0;
+
+                for &index in &matched_lines {
+                    let start = index.saturating_sub(args.context_lines).max(next_line);
+                    let end = (index + args.context_lines).min(lines.len() - 1);
+                    if args.context_lines > 0 && start > next_line && next_line > 0 {
+                        output.push_str("--\n");
+                    }
+
+                    for (line_number, line) in lines.iter().enumerate().take(end + 1).skip(start) {
+                        let separator = if matched_lines.binary_search(&line_number).is_ok() { ':' } else { '-' };
+                        output.push_str(&format!("{}{}{}{} {}\n",
+                            display_path, separator, line_number + 1, separator, line));
+                    }
+                    next_line = next_line.max(end + 1);
+                }
+
+                if args.context_lines > 0 {
+                    output.push_str("--\n");
+                }
+            }
+
+            if truncated {
+                break;
+            }
+        }
+
+        if match_count == 0 {
+            println!("✅ Tool: search_code | No matches");
+            return Ok(format!("No matches found for '{}' in {}", args.pattern, root.display()));
+        }
+
+        println!("✅ Tool: search_code | Success: {} matches in {} files", match_count, files_with_matches);
+
+        let mut result = format!("Found {} matches in {} files:\n", match_count, files_with_matches);
+        result.push_str(&output);
+        if truncated {
+            result.push_str(&format!("... results truncated at {} matches. Narrow the pattern, path or file_type to see more.\n", args.max_results));
+        }
+        Ok(result)
+    }
+}
+
+// File Finder Tool
+#[derive(Deserialize, JsonSchema)]
+pub struct FindFilesArgs {
+    /// Glob pattern to match (e.g. "*.rs", "src/**/mod.rs")
+    pub pattern: String,
+    /// Directory to search in, inside the workspace (default: the workspace)
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Maximum number of files to return (default: 200)
+    #[serde(default = "default_max_files")]
+    pub max_results: usize,
+    /// Include hidden files and directories (default: false)
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+fn default_max_files() -> usize { 200 }
+
+#[derive(Debug, Clone)]
+pub struct FileFinder {
+    /// Searches stay inside this directory
+    pub root: PathBuf,
+}
+
+impl Tool for FileFinder {
+    const NAME: &'static str = "find_files";
+    type Error = FileToolError;
+    type Args = FindFilesArgs;
+    type Output = String;
+
+    async fn definition(&self, _prompt: String) -> ToolDefinition {
+        ToolDefinition {
+            name: Self::NAME.to_string(),
+            description: "Find files by glob pattern. A pattern without '/' matches file names anywhere (e.g. '*.rs'); a pattern with '/' matches the path relative to the search directory (e.g. 'src/**/mod.rs').".to_string(),
+            parameters: serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "pattern": {
+                        "type": "string",
+                        "description": "Glob pattern to match (e.g. '*.rs', 'src/**/mod.rs')"
+                    },
+                    "path": {
+                        "type": "string",
+                        "description": "Directory to search in, inside the workspace (default: the workspace)"
+                    },
+                    "max_results": {
+                        "type": "integer",
+                        "description": "Maximum number of files to return (default: 200)",
+                        "default": 200,
+                        "minimum": 1
+                    },
+                    "include_hidden": {
+                        "type": "boolean",
+                        "description": "Include hidden files and directories (default: false)",
+                        "default": false
+                    }
+                },
+                "required": ["pattern"]
+            }),
+        }
+    }
+
+    async fn call(&self, args: Self::Args) -> Result<Self::Output, Self::Error> {
+        let root = check_search_root(&self.root, args.path.as_deref())?;
+        println!("🔧 Tool: find_files | Pattern: {} | Path: {}", args.pattern, root.display());
+        check_max_results(args.max_results)?;
+
+        let glob = Pattern::new(&args.pattern)
+            .map_err(|e| FileToolError::InvalidPattern(e.to_string()))?;
+        let match_full_path = args.pattern.contains('/');
+        let options = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            require_literal_leading_dot: false,
+        };
+
+        let mut matches = Vec::new();
+        let mut truncated = false;
+
+        for entry in walk_files(root, args.include_hidden) {
+            let relative_path = relative_display(root, entry.path());
+            let candidate = if match_full_path {
+                relative_path.as_str()
+            } else {
+                entry.file_name().to_str().unwrap_or("")
+            };
+
+            if glob.matches_with(candidate, options) {
+                if matches.len() >= args.max_results {
+                    truncated = true;
+                    break;
+                }
+                matches.push(reference_path(entry.path()));
+            }
+        }
+
+        if matches.is_empty() {
+            println!("✅ Tool: find_files | No matches");
+            return Ok(format!("No files matching '{}' in {}", args.pattern, root.display()));
+        }
+
+        matches.sort();
+        println!("✅ Tool: find_files | Success: {} files", matches.len());
+
+        let mut result = format!("Found {} files:\n{}\n", matches.len(), matches.join("\n"));
+        if truncated {
+            result.push_str(&format!("... results truncated at {} files. Use a narrower pattern to see more.\n", args.max_results));
+        }
+        Ok(result)
+    }
+}
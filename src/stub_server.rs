@@ -0,0 +1,255 @@
+//! OpenAI-compatible stub server for end-to-end tests. Answers
+//! `/v1/chat/completions` (plain and streamed, with tool calls) from fixture
+//! files, so the CLI can run against the `local` provider without network.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::sse::{Event, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+/// A tool call made by a fixture response
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureToolCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+}
+
+/// One canned response and the request it answers. A fixture matches when
+/// the latest message has the given role and contains the given text; the
+/// first matching fixture wins. Without `contains` or `role` it matches anything.
+///
+/// ```json
+/// [
+///   {"role": "user", "contains": "run.sh", "content": "Let me look.",
+///    "tool_calls": [{"name": "read_file", "arguments": {"path": "run.sh"}}]},
+///   {"role": "tool", "content": "The script is empty."},
+///   {"contains": "overload", "status": 503, "error": "The server is overloaded"}
+/// ]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Fixture {
+    /// Text the latest message must contain
+    #[serde(default)]
+    pub contains: Option<String>,
+    /// Role of the latest message: "user" or "tool"
+    #[serde(default)]
+    pub role: Option<String>,
+    #[serde(default)]
+    pub content: String,
+    #[serde(default)]
+    pub tool_calls: Vec<FixtureToolCall>,
+    /// Answer with this HTTP status and `error` message instead
+    #[serde(default)]
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl Fixture {
+    fn matches(&self, role: &str, text: &str) -> bool {
+        self.role.as_deref().is_none_or(|expected| expected == role)
+            && self.contains.as_deref().is_none_or(|needle| text.contains(needle))
+    }
+}
+
+/// Load fixtures from a JSON file holding an array of fixtures, or from every
+/// `.json` file in a directory, in file name order
+pub fn load_fixtures(path: &Path) -> Result<Vec<Fixture>, String> {
+    let files = if path.is_dir() {
+        let mut files = std::fs::read_dir(path)
+            .map_err(|e| format!("Failed to read fixture directory {}: {}", path.display(), e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|file| file.extension().is_some_and(|ext| ext == "json"))
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    } else {
+        vec![path.to_path_buf()]
+    };
+
+    let mut fixtures = Vec::new();
+    for file in files {
+        let text = std::fs::read_to_string(&file)
+            .map_err(|e| format!("Failed to read fixture file {}: {}", file.display(), e))?;
+        let mut loaded: Vec<Fixture> = serde_json::from_str(&text)
+            .map_err(|e| format!("Invalid fixture file {}: {}", file.display(), e))?;
+        fixtures.append(&mut loaded);
+    }
+    Ok(fixtures)
+}
+
+struct StubState {
+    fixtures: Vec<Fixture>,
+    requests: AtomicUsize,
+}
+
+/// A running stub server
+pub struct StubServer {
+    pub addr: SocketAddr,
+    handle: JoinHandle<std::io::Result<()>>,
+    state: Arc<StubState>,
+}
+
+impl StubServer {
+    /// Start serving `fixtures` on `addr`; use port 0 to pick a free port
+    pub async fn spawn(addr: SocketAddr, fixtures: Vec<Fixture>) -> std::io::Result<StubServer> {
+        let state = Arc::new(StubState { fixtures, requests: AtomicUsize::new(0) });
+        let app = Router::new()
+            .route("/v1/chat/completions", post(chat_completions))
+            .route("/chat/completions", post(chat_completions))
+            .route("/v1/models", get(models))
+            .with_state(state.clone());
+
+        let listener = TcpListener::bind(addr).await?;
+        let addr = listener.local_addr()?;
+        let handle = tokio::spawn(async move { axum::serve(listener, app).await });
+        Ok(StubServer { addr, handle, state })
+    }
+
+    /// Base URL to configure as `api_base` of the local provider
+    pub fn base_url(&self) -> String {
+        format!("http://{}/v1", self.addr)
+    }
+
+    /// Number of chat completion requests answered so far
+    pub fn request_count(&self) -> usize {
+        self.state.requests.load(Ordering::SeqCst)
+    }
+
+    /// Serve until the server fails
+    pub async fn wait(&mut self) -> std::io::Result<()> {
+        (&mut self.handle).await.map_err(std::io::Error::other)?
+    }
+}
+
+async fn models() -> Json<Value> {
+    Json(json!({ "object": "list", "data": [{ "id": "stub", "object": "model", "owned_by": "llmo" }] }))
+}
+
+/// Text of a message's content, which may be a string or a list of parts
+fn content_text(content: &Value) -> String {
+    match content {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter()
+            .filter_map(|part| part.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(""),
+        _ => String::new(),
+    }
+}
+
+/// Deterministic token count: whitespace separated words
+fn count_tokens(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+async fn chat_completions(State(state): State<Arc<StubState>>, Json(request): Json<Value>) -> Response {
+    let number = state.requests.fetch_add(1, Ordering::SeqCst) + 1;
+    let model = request.get("model").and_then(Value::as_str).unwrap_or("stub").to_string();
+    let messages = request.get("messages").and_then(Value::as_array).cloned().unwrap_or_default();
+
+    let last = messages.last();
+    let role = last.and_then(|message| message.get("role")).and_then(Value::as_str).unwrap_or("user");
+    let text = last.and_then(|message| message.get("content")).map(content_text).unwrap_or_default();
+    println!("📨 Request {} | {} message from {} | {} messages", number, role, model, messages.len());
+
+    let fixture = state.fixtures.iter().find(|fixture| fixture.matches(role, &text)).cloned()
+        .unwrap_or_else(|| Fixture {
+            contains: None,
+            role: None,
+            content: format!("Stub reply to: {}", text),
+            tool_calls: Vec::new(),
+            status: None,
+            error: None,
+        });
+
+    if let Some(status) = fixture.status.filter(|status| *status >= 400) {
+        let status = StatusCode::from_u16(status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let message = fixture.error.unwrap_or_else(|| status.to_string());
+        return (status, Json(json!({ "error": { "message": message, "type": "stub_error" } }))).into_response();
+    }
+
+    let prompt_tokens = messages.iter()
+        .filter_map(|message| message.get("content"))
+        .map(|content| count_tokens(&content_text(content)))
+        .sum::<usize>();
+    let completion_tokens = count_tokens(&fixture.content)
+        + fixture.tool_calls.iter().map(|call| count_tokens(&call.arguments.to_string()) + 1).sum::<usize>();
+    let usage = json!({
+        "prompt_tokens": prompt_tokens,
+        "completion_tokens": completion_tokens,
+        "total_tokens": prompt_tokens + completion_tokens,
+    });
+
+    let id = format!("chatcmpl-stub-{}", number);
+    let finish_reason = if fixture.tool_calls.is_empty() { "stop" } else { "tool_calls" };
+    let tool_call_id = |index: usize| format!("call_{}_{}", number, index + 1);
+
+    if request.get("stream").and_then(Value::as_bool).unwrap_or(false) {
+        let chunk = |delta: Value, finish_reason: Option<&str>| json!({
+            "id": id, "object": "chat.completion.chunk", "created": 0, "model": model,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+        });
+
+        let mut chunks = vec![chunk(json!({ "role": "assistant", "content": "" }), None)];
+        for word in fixture.content.split_inclusive(' ') {
+            chunks.push(chunk(json!({ "content": word }), None));
+        }
+        // Like OpenAI: the name first, then the arguments
+        for (index, call) in fixture.tool_calls.iter().enumerate() {
+            chunks.push(chunk(json!({ "tool_calls": [{
+                "index": index, "id": tool_call_id(index), "type": "function",
+                "function": { "name": call.name, "arguments": "" },
+            }] }), None));
+            chunks.push(chunk(json!({ "tool_calls": [{
+                "index": index, "function": { "arguments": call.arguments.to_string() },
+            }] }), None));
+        }
+        chunks.push(chunk(json!({}), Some(finish_reason)));
+
+        let include_usage = request.pointer("/stream_options/include_usage").and_then(Value::as_bool).unwrap_or(false);
+        if include_usage {
+            chunks.push(json!({
+                "id": id, "object": "chat.completion.chunk", "created": 0, "model": model,
+                "choices": [], "usage": usage,
+            }));
+        }
+
+        let events = chunks.into_iter()
+            .map(|chunk| Event::default().data(chunk.to_string()))
+            .chain(std::iter::once(Event::default().data("[DONE]")))
+            .map(Ok::<_, Infallible>);
+        return Sse::new(futures::stream::iter(events)).into_response();
+    }
+
+    let tool_calls = fixture.tool_calls.iter().enumerate()
+        .map(|(index, call)| json!({
+            "id": tool_call_id(index), "type": "function",
+            "function": { "name": call.name, "arguments": call.arguments.to_string() },
+        }))
+        .collect::<Vec<_>>();
+    let mut message = json!({ "role": "assistant", "content": fixture.content });
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+
+    Json(json!({
+        "id": id, "object": "chat.completion", "created": 0, "model": model,
+        "choices": [{ "index": 0, "message": message, "logprobs": null, "finish_reason": finish_reason }],
+        "usage": usage,
+    })).into_response()
+}
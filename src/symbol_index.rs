@@ -0,0 +1,435 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use tree_sitter::{Node, Parser, Point, Query, QueryCursor, StreamingIterator, Tree};
+use walkdir::WalkDir;
+
+use crate::file_tools::{is_ignored, FileToolError};
+
+/// Languages with a tree-sitter grammar for symbol navigation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Tsx,
+    Go,
+    C,
+    Cpp,
+    Java,
+}
+
+impl Language {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "rs" => Some(Language::Rust),
+            "py" | "pyi" => Some(Language::Python),
+            "js" | "jsx" | "mjs" | "cjs" => Some(Language::JavaScript),
+            "ts" | "mts" | "cts" => Some(Language::TypeScript),
+            "tsx" => Some(Language::Tsx),
+            "go" => Some(Language::Go),
+            "c" | "h" => Some(Language::C),
+            "cpp" | "cc" | "cxx" | "hpp" | "hh" | "hxx" => Some(Language::Cpp),
+            "java" => Some(Language::Java),
+            _ => None,
+        }
+    }
+
+    /// Language identifier used by the Language Server Protocol
+    pub fn lsp_id(&self) -> &'static str {
+        match self {
+            Language::Rust => "rust",
+            Language::Python => "python",
+            Language::JavaScript => "javascript",
+            Language::TypeScript => "typescript",
+            Language::Tsx => "typescriptreact",
+            Language::Go => "go",
+            Language::C => "c",
+            Language::Cpp => "cpp",
+            Language::Java => "java",
+        }
+    }
+
+    fn grammar(&self) -> tree_sitter::Language {
+        match self {
+            Language::Rust => tree_sitter_rust::LANGUAGE.into(),
+            Language::Python => tree_sitter_python::LANGUAGE.into(),
+            Language::JavaScript => tree_sitter_javascript::LANGUAGE.into(),
+            Language::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            Language::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            Language::Go => tree_sitter_go::LANGUAGE.into(),
+            Language::C => tree_sitter_c::LANGUAGE.into(),
+            Language::Cpp => tree_sitter_cpp::LANGUAGE.into(),
+            Language::Java => tree_sitter_java::LANGUAGE.into(),
+        }
+    }
+
+    /// Tag queries shipped with the grammar, plus a few definitions they miss
+    fn tags_queries(&self) -> Vec<&'static str> {
+        match self {
+            Language::Rust => vec![tree_sitter_rust::TAGS_QUERY, RUST_EXTRA_TAGS],
+            Language::Python => vec![tree_sitter_python::TAGS_QUERY],
+            Language::JavaScript => vec![tree_sitter_javascript::TAGS_QUERY],
+            // The TypeScript tags only cover TS specific syntax, the JS ones cover the rest
+            Language::TypeScript | Language::Tsx => vec![tree_sitter_javascript::TAGS_QUERY, tree_sitter_typescript::TAGS_QUERY],
+            Language::Go => vec![tree_sitter_go::TAGS_QUERY],
+            Language::C => vec![tree_sitter_c::TAGS_QUERY],
+            Language::Cpp => vec![tree_sitter_cpp::TAGS_QUERY],
+            Language::Java => vec![tree_sitter_java::TAGS_QUERY],
+        }
+    }
+}
+
+const RUST_EXTRA_TAGS: &str = r#"
+(const_item name: (identifier) @name) @definition.constant
+(static_item name: (identifier) @name) @definition.constant
+(enum_variant name: (identifier) @name) @definition.variant
+(field_declaration name: (field_identifier) @name) @definition.field
+"#;
+
+/// A position in the workspace. Line and column are 1-based, column counts characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolLocation {
+    pub path: String,
+    pub line: usize,
+    pub column: usize,
+    pub line_text: String,
+}
+
+impl fmt::Display for SymbolLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.path, self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub name: String,
+    /// Tag kind from the grammar's tags query (function, class, method, ...)
+    pub kind: String,
+    pub location: SymbolLocation,
+}
+
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub location: SymbolLocation,
+    pub is_definition: bool,
+}
+
+/// A parsed source file
+struct ParsedFile {
+    language: Language,
+    source: String,
+    tree: Tree,
+}
+
+/// Parses workspace files with tree-sitter and builds per-file symbol tables
+pub struct SymbolIndex {
+    root: PathBuf,
+    parser: Parser,
+    queries: HashMap<Language, Vec<Query>>,
+}
+
+impl SymbolIndex {
+    pub fn new(root: &Path) -> Result<Self, FileToolError> {
+        if !root.is_dir() {
+            return Err(FileToolError::InvalidPath(format!("{} is not a directory", root.display())));
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            parser: Parser::new(),
+            queries: HashMap::new(),
+        })
+    }
+
+    /// All supported source files under the root, honoring the ignore rules
+    fn source_files(&self) -> Vec<PathBuf> {
+        WalkDir::new(&self.root)
+            .into_iter()
+            .filter_entry(|e| !is_ignored(e))
+            .flatten()
+            .filter(|e| e.file_type().is_file() && Language::from_path(e.path()).is_some())
+            .map(|e| e.into_path())
+            .collect()
+    }
+
+    fn parse(&mut self, path: &Path, source: String) -> Option<ParsedFile> {
+        let language = Language::from_path(path)?;
+        self.parser.set_language(&language.grammar()).ok()?;
+        let tree = self.parser.parse(&source, None)?;
+        Some(ParsedFile { language, source, tree })
+    }
+
+    fn read_and_parse(&mut self, path: &Path, name_filter: Option<&str>) -> Option<ParsedFile> {
+        let source = std::fs::read_to_string(path).ok()?;
+        if let Some(name) = name_filter
+            && !source.contains(name) {
+            return None;
+        }
+        self.parse(path, source)
+    }
+
+    fn queries_for(&mut self, language: Language) -> &[Query] {
+        self.queries.entry(language).or_insert_with(|| {
+            let grammar = language.grammar();
+            language.tags_queries()
+                .into_iter()
+                .filter_map(|source| match Query::new(&grammar, source) {
+                    Ok(query) => Some(query),
+                    Err(e) => {
+                        tracing::warn!("Skipping tags query for {:?}: {}", language, e);
+                        None
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn location(&self, path: &Path, file: &ParsedFile, point: Point) -> SymbolLocation {
+        let line_text = file.source.lines().nth(point.row).unwrap_or("");
+        let column = line_text.get(..point.column).map(|s| s.chars().count()).unwrap_or(point.column);
+        SymbolLocation {
+            path: display_path(path),
+            line: point.row + 1,
+            column: column + 1,
+            line_text: line_text.trim().to_string(),
+        }
+    }
+
+    fn file_definitions(&mut self, path: &Path, file: &ParsedFile, name_filter: Option<&str>) -> Vec<Definition> {
+        let mut definitions = Vec::new();
+        let mut found = Vec::new();
+
+        for query in self.queries_for(file.language) {
+            let capture_names = query.capture_names();
+            let mut cursor = QueryCursor::new();
+            let mut matches = cursor.matches(query, file.tree.root_node(), file.source.as_bytes());
+
+            while let Some(query_match) = matches.next() {
+                let mut name_node = None;
+                let mut kind = None;
+                for capture in query_match.captures {
+                    let capture_name = capture_names[capture.index as usize];
+                    if capture_name == "name" {
+                        name_node = Some(capture.node);
+                    } else if let Some(definition_kind) = capture_name.strip_prefix("definition.") {
+                        kind = Some(definition_kind.to_string());
+                    }
+                }
+
+                if let (Some(node), Some(kind)) = (name_node, kind) {
+                    let Ok(name) = node.utf8_text(file.source.as_bytes()) else { continue };
+                    if name_filter.is_some_and(|filter| filter != name) {
+                        continue;
+                    }
+                    found.push((name.to_string(), kind, node.start_position()));
+                }
+            }
+        }
+
+        // Several patterns can tag the same node (e.g. method and function), keep the first
+        for (name, kind, point) in found {
+            let location = self.location(path, file, point);
+            if definitions.iter().any(|d: &Definition| d.location == location) {
+                continue;
+            }
+            definitions.push(Definition { name, kind, location });
+        }
+
+        definitions
+    }
+
+    /// Find definitions of `name` across the workspace
+    pub fn definitions(&mut self, name: &str) -> Vec<Definition> {
+        let mut definitions = Vec::new();
+        for path in self.source_files() {
+            if let Some(file) = self.read_and_parse(&path, Some(name)) {
+                definitions.extend(self.file_definitions(&path, &file, Some(name)));
+            }
+        }
+        definitions
+    }
+
+    /// Which of `names` are defined somewhere in the workspace, in one pass over it
+    fn defined_names(&mut self, names: &[String]) -> HashSet<String> {
+        let mut defined = HashSet::new();
+        for path in self.source_files() {
+            let Ok(source) = std::fs::read_to_string(&path) else { continue };
+            if !names.iter().any(|name| source.contains(name.as_str())) {
+                continue;
+            }
+            let Some(file) = self.parse(&path, source) else { continue };
+            defined.extend(self.file_definitions(&path, &file, None).into_iter()
+                .map(|d| d.name)
+                .filter(|name| names.contains(name)));
+        }
+        defined
+    }
+
+    /// Find every identifier occurrence of `name` across the workspace, skipping comments and strings
+    pub fn references(&mut self, name: &str) -> Vec<Reference> {
+        let mut references = Vec::new();
+        for path in self.source_files() {
+            let Some(file) = self.read_and_parse(&path, Some(name)) else { continue };
+            let definition_positions: Vec<(usize, usize)> = self.file_definitions(&path, &file, Some(name))
+                .iter()
+                .map(|d| (d.location.line, d.location.column))
+                .collect();
+
+            let mut points = Vec::new();
+            collect_identifiers(file.tree.root_node(), file.source.as_bytes(), name, &mut points);
+
+            for point in points {
+                let location = self.location(&path, &file, point);
+                let is_definition = definition_positions.contains(&(location.line, location.column));
+                references.push(Reference { location, is_definition });
+            }
+        }
+        references
+    }
+
+    /// Identifier at a 1-based line and optional 1-based column. Without a column the
+    /// first identifier on the line that has a definition in the workspace is used.
+    pub fn identifier_at(&mut self, path: &str, line: usize, column: Option<usize>) -> Result<String, FileToolError> {
+        let file_path = Path::new(path);
+        if !file_path.exists() {
+            return Err(FileToolError::FileNotFound(path.to_string()));
+        }
+        let file = self.read_and_parse(file_path, None)
+            .ok_or_else(|| FileToolError::InvalidPath(format!("{} is not a supported source file", path)))?;
+
+        let line_text = file.source.lines().nth(line.saturating_sub(1))
+            .ok_or_else(|| FileToolError::InvalidPath(format!("Line {} out of range in {}", line, path)))?;
+
+        if let Some(column) = column {
+            let byte_column = line_text.char_indices()
+                .nth(column.saturating_sub(1))
+                .map(|(i, _)| i)
+                .unwrap_or(line_text.len());
+            let point = Point { row: line - 1, column: byte_column };
+            let node = file.tree.root_node()
+                .descendant_for_point_range(point, point)
+                .filter(|node| is_identifier(node))
+                .ok_or_else(|| FileToolError::InvalidPath(format!("No identifier at {}:{}:{}", path, line, column)))?;
+            return Ok(node.utf8_text(file.source.as_bytes()).unwrap_or_default().to_string());
+        }
+
+        let mut candidates = Vec::new();
+        collect_line_identifiers(file.tree.root_node(), file.source.as_bytes(), line - 1, &mut candidates);
+        if candidates.is_empty() {
+            return Err(FileToolError::InvalidPath(format!("No identifier on {}:{}", path, line)));
+        }
+
+        let defined = self.defined_names(&candidates);
+        let index = candidates.iter().position(|candidate| defined.contains(candidate)).unwrap_or(0);
+        Ok(candidates.swap_remove(index))
+    }
+}
+
+fn is_identifier(node: &Node) -> bool {
+    node.child_count() == 0 && node.kind().ends_with("identifier")
+}
+
+fn collect_identifiers(node: Node, source: &[u8], name: &str, points: &mut Vec<Point>) {
+    if is_identifier(&node) {
+        if node.utf8_text(source).is_ok_and(|text| text == name) {
+            points.push(node.start_position());
+        }
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_identifiers(child, source, name, points);
+    }
+}
+
+fn collect_line_identifiers(node: Node, source: &[u8], row: usize, names: &mut Vec<String>) {
+    if node.start_position().row > row || node.end_position().row < row {
+        return;
+    }
+    if is_identifier(&node) {
+        if let Ok(text) = node.utf8_text(source)
+            && !names.iter().any(|n| n == text) {
+            names.push(text.to_string());
+        }
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_line_identifiers(child, source, row, names);
+    }
+}
+
+/// Path as it should be passed back to the other file tools
+pub fn display_path(path: &Path) -> String {
+    path.strip_prefix(".")
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Parse a "path:line" or "path:line:column" reference
+pub fn parse_location(location: &str) -> Result<(String, usize, Option<usize>), FileToolError> {
+    let invalid = || FileToolError::InvalidPath(format!(
+        "Expected path:line or path:line:column with 1-based numbers, got '{}'", location));
+
+    let mut parts = location.rsplitn(3, ':');
+    let last = parts.next().ok_or_else(invalid)?;
+    let middle = parts.next().ok_or_else(invalid)?;
+
+    // Lines and columns are 1-based
+    let position = |text: &str| text.parse::<usize>().ok().filter(|value| *value >= 1).ok_or_else(invalid);
+
+    match (parts.next(), middle.parse::<usize>()) {
+        (Some(path), Ok(_)) => Ok((path.to_string(), position(middle)?, Some(position(last)?))),
+        _ => {
+            let line = position(last)?;
+            let path = location[..location.len() - last.len() - 1].to_string();
+            Ok((path, line, None))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_line_and_column() {
+        assert_eq!(parse_location("src/main.rs:12:5").unwrap(), ("src/main.rs".to_string(), 12, Some(5)));
+        assert_eq!(parse_location("src/main.rs:12").unwrap(), ("src/main.rs".to_string(), 12, None));
+    }
+
+    #[test]
+    fn keeps_colons_in_the_path() {
+        assert_eq!(parse_location("C:/code/main.rs:3:1").unwrap(), ("C:/code/main.rs".to_string(), 3, Some(1)));
+        assert_eq!(parse_location("a:b.py:7").unwrap(), ("a:b.py".to_string(), 7, None));
+    }
+
+    #[test]
+    fn rejects_zero_and_missing_numbers() {
+        for location in ["src/main.rs:0", "src/main.rs:0:4", "src/main.rs:4:0", "src/main.rs", "src/main.rs:x", "src/main.rs:-1", ":"] {
+            let error = parse_location(location).unwrap_err();
+            assert!(matches!(error, FileToolError::InvalidPath(_)), "{}: {}", location, error);
+        }
+    }
+
+    #[test]
+    fn picks_the_defined_identifier_on_a_line() {
+        let root = std::env::temp_dir().join(format!("llmo-symbol-index-{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        let file = root.join("lib.rs");
+        std::fs::write(&file, "fn helper() -> u32 { 1 }\nfn main() { let total = helper(); }\n").unwrap();
+
+        let mut index = SymbolIndex::new(&root).unwrap();
+        let path = file.display().to_string();
+        assert_eq!(index.identifier_at(&path, 2, None).unwrap(), "main");
+        assert_eq!(index.identifier_at(&path, 2, Some(26)).unwrap(), "helper");
+        assert_eq!(index.definitions("helper")[0].location.line, 1);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,488 @@
+use futures::StreamExt;
+use rig::OneOrMany;
+use rig::completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse, ToolDefinition};
+use rig::message::{AssistantContent, Message, ToolResultContent, UserContent};
+use rig::streaming::{RawStreamingChoice, StreamingCompletionResponse};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::reasoning::strip_reasoning;
+use crate::retry::is_retryable;
+
+/// How tools are offered to a model, as configured per provider
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolCalling {
+    /// Probe the model at startup and use native tool calls if they work
+    Auto,
+    /// The API's function calling
+    Native,
+    /// Tools described in the system prompt, calls parsed from the answer text
+    Prompt,
+}
+
+/// Tool protocol an agent ends up using
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolProtocol {
+    Native,
+    Prompt,
+}
+
+const PROBE_TOOL: &str = "probe";
+
+/// Room for the probe's answer; reasoning models think before they call the tool
+const PROBE_MAX_TOKENS: u64 = 4096;
+
+/// Ask the model to call a trivial tool and see whether it answers with a
+/// native tool call. Returns `None` when the answer says nothing about tool
+/// calling because the model used it up reasoning; callers assume native
+/// tool calls then. Fails when the model cannot be used at all: the server
+/// is unreachable, or it rejects the request with and without tools.
+pub async fn probe_tool_calling<M: CompletionModel>(model: &M, label: &str) -> Result<Option<ToolProtocol>, CompletionError> {
+    let request = CompletionRequest {
+        preamble: Some("You check whether tool calling works. Always answer by calling the tool you are given.".to_string()),
+        chat_history: OneOrMany::one(Message::user(format!("Call the `{}` tool with value \"ok\".", PROBE_TOOL))),
+        documents: Vec::new(),
+        tools: vec![ToolDefinition {
+            name: PROBE_TOOL.to_string(),
+            description: "Confirms that tool calling works".to_string(),
+            parameters: json!({
+                "type": "object",
+                "properties": { "value": { "type": "string" } },
+                "required": ["value"]
+            }),
+        }],
+        temperature: Some(0.0),
+        max_tokens: Some(PROBE_MAX_TOKENS),
+        additional_params: None,
+    };
+
+    match model.completion(request.clone()).await {
+        Ok(response) if response.choice.iter().any(|content| matches!(content, AssistantContent::ToolCall(_))) => {
+            println!("🧪 {} | Native tool calls: supported", label);
+            Ok(Some(ToolProtocol::Native))
+        }
+        // Judge the answer, not the reasoning in front of it
+        Ok(response) if strip_reasoning(&response.choice).iter()
+            .all(|content| matches!(content, AssistantContent::Text(text) if text.text.trim().is_empty())) => {
+            println!("⚠️  {} | Could not probe tool calling: the model only reasoned | Assuming native tool calls", label);
+            Ok(None)
+        }
+        Ok(_) => {
+            println!("🧪 {} | Native tool calls: not used by the model | Falling back to prompt-based tool calls", label);
+            Ok(Some(ToolProtocol::Prompt))
+        }
+        Err(e) if is_retryable(&e) => Err(e),
+        Err(e) => {
+            // Only the tools may be the problem; the model has to answer without them
+            model.completion(CompletionRequest { tools: Vec::new(), ..request }).await?;
+            println!("🧪 {} | Native tool calls: rejected ({}) | Falling back to prompt-based tool calls", label, e);
+            Ok(Some(ToolProtocol::Prompt))
+        }
+    }
+}
+
+const TOOL_CALL_OPEN: &str = "<tool_call>";
+const TOOL_CALL_CLOSE: &str = "</tool_call>";
+const FENCE: &str = "```";
+
+/// Instructions appended to the preamble in prompt mode
+fn protocol_instructions(tools: &[ToolDefinition]) -> String {
+    let mut text = String::from(
+        "# Tools\n\n\
+         You can call the tools listed below. To call a tool, answer with one block per call:\n\n\
+         <tool_call>\n\
+         {\"name\": \"<tool name>\", \"arguments\": {<arguments as JSON>}}\n\
+         </tool_call>\n\n\
+         Write nothing after your tool calls. Each result comes back in a <tool_response> block. \
+         Once you have everything you need, answer without any <tool_call> block.\n\n\
+         Available tools:\n");
+    for tool in tools {
+        text.push_str(&format!("\n## {}\n{}\nParameters (JSON schema): {}\n", tool.name, tool.description, tool.parameters));
+    }
+    text
+}
+
+fn tool_call_block(name: &str, arguments: &serde_json::Value) -> String {
+    format!("{}\n{}\n{}", TOOL_CALL_OPEN, json!({ "name": name, "arguments": arguments }), TOOL_CALL_CLOSE)
+}
+
+/// Rewrite tool calls and results in the history as text, so models
+/// without function calling see the conversation in the protocol's format
+fn history_as_text(history: &OneOrMany<Message>) -> Result<OneOrMany<Message>, CompletionError> {
+    let mut tool_names = HashMap::new();
+    let mut messages = Vec::new();
+
+    for message in history.iter() {
+        let message = match message {
+            Message::Assistant { content } => {
+                let text = content.iter()
+                    .map(|content| match content {
+                        AssistantContent::Text(text) => text.text.clone(),
+                        AssistantContent::ToolCall(tool_call) => {
+                            tool_names.insert(tool_call.id.clone(), tool_call.function.name.clone());
+                            tool_call_block(&tool_call.function.name, &tool_call.function.arguments)
+                        }
+                    })
+                    .filter(|text| !text.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                Message::assistant(text)
+            }
+            Message::User { content } => {
+                let content = content.iter().cloned().map(|content| match content {
+                    UserContent::ToolResult(result) => {
+                        let output = result.content.iter()
+                            .filter_map(|content| match content {
+                                ToolResultContent::Text(text) => Some(text.text.clone()),
+                                ToolResultContent::Image(_) => None,
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        let name = tool_names.get(&result.id).map(String::as_str).unwrap_or("unknown");
+                        UserContent::text(format!("<tool_response name=\"{}\">\n{}\n</tool_response>", name, output))
+                    }
+                    content => content,
+                });
+                Message::User { content: OneOrMany::many(content).map_err(|e| CompletionError::RequestError(Box::new(e)))? }
+            }
+        };
+        messages.push(message);
+    }
+
+    OneOrMany::many(messages).map_err(|e| CompletionError::RequestError(Box::new(e)))
+}
+
+#[derive(Deserialize)]
+struct TextToolCall {
+    #[serde(alias = "tool", alias = "function")]
+    name: String,
+    #[serde(default, alias = "parameters", alias = "args")]
+    arguments: serde_json::Value,
+}
+
+/// Split answer text into the prose and the tool calls it contains: JSON in
+/// `<tool_call>` blocks, or fenced JSON blocks naming one of `tool_names`
+fn parse_tool_calls(text: &str, tool_names: &[String]) -> (String, Vec<(String, serde_json::Value)>) {
+    let mut prose = String::new();
+    let mut calls = Vec::new();
+    let mut rest = text;
+
+    loop {
+        let xml = rest.find(TOOL_CALL_OPEN);
+        let fence = rest.find(FENCE);
+        let (start, open, close) = match (xml, fence) {
+            (Some(xml), Some(fence)) if fence < xml => (fence, FENCE, FENCE),
+            (Some(xml), _) => (xml, TOOL_CALL_OPEN, TOOL_CALL_CLOSE),
+            (None, Some(fence)) => (fence, FENCE, FENCE),
+            (None, None) => break,
+        };
+
+        let body_start = start + open.len();
+        // An unclosed <tool_call> block runs to the end of the answer
+        let (body, end) = match rest[body_start..].find(close) {
+            Some(length) => (&rest[body_start..body_start + length], body_start + length + close.len()),
+            None if open == TOOL_CALL_OPEN => (&rest[body_start..], rest.len()),
+            None => break,
+        };
+
+        // Fenced blocks may carry a language tag ("```json")
+        let json = if open == FENCE {
+            body.split_once('\n').map_or(body, |(tag, json)| if tag.trim().chars().all(char::is_alphanumeric) { json } else { body })
+        } else {
+            body
+        };
+
+        match serde_json::from_str::<TextToolCall>(json.trim()) {
+            Ok(call) if open == TOOL_CALL_OPEN || tool_names.contains(&call.name) => {
+                prose.push_str(&rest[..start]);
+                calls.push((call.name, call.arguments));
+            }
+            // Not a tool call, e.g. a code sample in the answer
+            _ => prose.push_str(&rest[..end]),
+        }
+        rest = &rest[end..];
+    }
+
+    prose.push_str(rest);
+    (prose.trim().to_string(), calls)
+}
+
+/// Length of the longest suffix of `text` that could start a tool call block
+fn pending_marker_len(text: &str) -> usize {
+    [TOOL_CALL_OPEN, FENCE].iter()
+        .flat_map(|marker| (1..marker.len()).rev().filter(move |length| text.ends_with(&marker[..*length])))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Completion model wrapper implementing the prompt-based tool protocol. In
+/// native mode requests and responses pass through untouched.
+#[derive(Clone)]
+pub struct ToolProtocolModel<M> {
+    inner: M,
+    protocol: ToolProtocol,
+    /// Numbers tool calls parsed from text, which carry no ids of their own
+    next_call: Arc<AtomicUsize>,
+}
+
+impl<M> ToolProtocolModel<M> {
+    pub fn new(inner: M, protocol: ToolProtocol) -> Self {
+        ToolProtocolModel { inner, protocol, next_call: Arc::default() }
+    }
+
+    fn call_id(&self) -> String {
+        format!("text_call_{}", self.next_call.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    /// Move the tool definitions into the preamble and tool traffic into text
+    fn prompt_request(&self, request: CompletionRequest) -> Result<(CompletionRequest, Vec<String>), CompletionError> {
+        let tool_names = request.tools.iter().map(|tool| tool.name.clone()).collect();
+        let preamble = match (&request.preamble, request.tools.is_empty()) {
+            (preamble, true) => preamble.clone(),
+            (Some(preamble), false) => Some(format!("{}\n\n{}", preamble, protocol_instructions(&request.tools))),
+            (None, false) => Some(protocol_instructions(&request.tools)),
+        };
+        let request = CompletionRequest {
+            preamble,
+            chat_history: history_as_text(&request.chat_history)?,
+            tools: Vec::new(),
+            ..request
+        };
+        Ok((request, tool_names))
+    }
+}
+
+impl<M: CompletionModel + 'static> CompletionModel for ToolProtocolModel<M> {
+    type Response = M::Response;
+    type StreamingResponse = M::StreamingResponse;
+
+    async fn completion(&self, request: CompletionRequest) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        if self.protocol == ToolProtocol::Native {
+            return self.inner.completion(request).await;
+        }
+
+        let (request, tool_names) = self.prompt_request(request)?;
+        let response = self.inner.completion(request).await?;
+
+        let text = response.choice.iter()
+            .filter_map(|content| match content {
+                AssistantContent::Text(text) => Some(text.text.as_str()),
+                AssistantContent::ToolCall(_) => None,
+            })
+            .collect::<String>();
+        let (prose, calls) = parse_tool_calls(&text, &tool_names);
+
+        let mut choice = Vec::new();
+        if !prose.is_empty() || calls.is_empty() {
+            choice.push(AssistantContent::text(prose));
+        }
+        for (name, arguments) in calls {
+            choice.push(AssistantContent::tool_call(self.call_id(), name, arguments));
+        }
+
+        Ok(CompletionResponse {
+            choice: OneOrMany::many(choice).map_err(|e| CompletionError::ResponseError(e.to_string()))?,
+            raw_response: response.raw_response,
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        if self.protocol == ToolProtocol::Native {
+            return self.inner.stream(request).await;
+        }
+
+        let (request, tool_names) = self.prompt_request(request)?;
+        let inner = self.inner.stream(request).await?;
+
+        // Text is passed on as it arrives until something that may open a
+        // tool call block shows up; the rest is parsed once the stream ends
+        let model = self.clone();
+        let stream = futures::stream::unfold(Some((inner, String::new(), false)), move |state| {
+            let (model, tool_names) = (model.clone(), tool_names.clone());
+            async move {
+                let (mut inner, mut buffer, mut holding) = state?;
+                loop {
+                    match inner.next().await {
+                        Some(Ok(AssistantContent::Text(text))) => {
+                            buffer.push_str(&text.text);
+                            holding |= buffer.contains(TOOL_CALL_OPEN) || buffer.contains(FENCE);
+                            if holding {
+                                continue;
+                            }
+                            let ready = buffer.len() - pending_marker_len(&buffer);
+                            if ready == 0 {
+                                continue;
+                            }
+                            let chunk = buffer[..ready].to_string();
+                            buffer.drain(..ready);
+                            return Some((vec![Ok(RawStreamingChoice::Message(chunk))], Some((inner, buffer, holding))));
+                        }
+                        // Native calls the server sent anyway
+                        Some(Ok(AssistantContent::ToolCall(tool_call))) => {
+                            let call = RawStreamingChoice::ToolCall {
+                                id: tool_call.id,
+                                name: tool_call.function.name,
+                                arguments: tool_call.function.arguments,
+                            };
+                            return Some((vec![Ok(call)], Some((inner, buffer, holding))));
+                        }
+                        Some(Err(e)) => return Some((vec![Err(e)], Some((inner, buffer, holding)))),
+                        None => {
+                            let (prose, calls) = if holding {
+                                parse_tool_calls(&buffer, &tool_names)
+                            } else {
+                                (buffer, Vec::new())
+                            };
+                            let mut items = Vec::new();
+                            if !prose.is_empty() {
+                                items.push(Ok(RawStreamingChoice::Message(prose)));
+                            }
+                            for (name, arguments) in calls {
+                                items.push(Ok(RawStreamingChoice::ToolCall { id: model.call_id(), name, arguments }));
+                            }
+                            if let Some(response) = inner.response.take() {
+                                items.push(Ok(RawStreamingChoice::FinalResponse(response)));
+                            }
+                            return Some((items, None));
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(StreamingCompletionResponse::stream(Box::pin(stream.flat_map(futures::stream::iter))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockCompletionModel, MockScript, ScriptedResponse};
+
+    fn names() -> Vec<String> {
+        vec!["read_file".to_string(), "search_code".to_string()]
+    }
+
+    #[test]
+    fn reply_without_calls_is_all_prose() {
+        let (prose, calls) = parse_tool_calls("  The script prints the loss.\n", &names());
+        assert_eq!(prose, "The script prints the loss.");
+        assert!(calls.is_empty());
+    }
+
+    #[test]
+    fn keeps_text_before_and_after_a_call() {
+        let text = "Let me look.\n<tool_call>\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"run.sh\"}}\n</tool_call>\nBack soon.";
+        let (prose, calls) = parse_tool_calls(text, &names());
+        assert_eq!(prose, "Let me look.\n\nBack soon.");
+        assert_eq!(calls, [("read_file".to_string(), json!({ "path": "run.sh" }))]);
+    }
+
+    #[test]
+    fn parses_several_calls_in_one_reply() {
+        let text = "<tool_call>{\"name\": \"read_file\", \"arguments\": {\"path\": \"a.py\"}}</tool_call>\n\
+                    <tool_call>{\"tool\": \"search_code\", \"parameters\": {\"pattern\": \"lr\"}}</tool_call>\n\
+                    ```json\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"b.py\"}}\n```";
+        let (prose, calls) = parse_tool_calls(text, &names());
+        assert_eq!(prose, "");
+        assert_eq!(calls, [
+            ("read_file".to_string(), json!({ "path": "a.py" })),
+            ("search_code".to_string(), json!({ "pattern": "lr" })),
+            ("read_file".to_string(), json!({ "path": "b.py" })),
+        ]);
+    }
+
+    #[test]
+    fn malformed_json_stays_in_the_prose() {
+        let text = "Trying.\n<tool_call>{\"name\": \"read_file\", \"arguments\": {\"path\": }</tool_call>";
+        let (prose, calls) = parse_tool_calls(text, &names());
+        assert_eq!(prose, text);
+        assert!(calls.is_empty());
+    }
+
+    #[test]
+    fn fenced_json_needs_a_known_tool_name() {
+        let text = "Config:\n```json\n{\"name\": \"model\", \"arguments\": {}}\n```";
+        let (prose, calls) = parse_tool_calls(text, &names());
+        assert_eq!(prose, text);
+        assert!(calls.is_empty());
+    }
+
+    #[test]
+    fn unclosed_call_runs_to_the_end() {
+        let (prose, calls) = parse_tool_calls("<tool_call>\n{\"name\": \"read_file\", \"arguments\": {\"path\": \"x\"}}", &names());
+        assert_eq!(prose, "");
+        assert_eq!(calls, [("read_file".to_string(), json!({ "path": "x" }))]);
+    }
+
+    #[test]
+    fn holds_back_a_possible_block_start() {
+        assert_eq!(pending_marker_len("Let me check <tool"), 5);
+        assert_eq!(pending_marker_len("Here ``"), 2);
+        assert_eq!(pending_marker_len("Done."), 0);
+    }
+
+    fn prompt_model(text: &str) -> (ToolProtocolModel<MockCompletionModel>, MockCompletionModel) {
+        let mock = MockCompletionModel::new(Some(MockScript {
+            responses: vec![ScriptedResponse { text: text.to_string(), tool_calls: Vec::new() }],
+        }));
+        (ToolProtocolModel::new(mock.clone(), ToolProtocol::Prompt), mock)
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            preamble: Some("You are a test agent.".to_string()),
+            chat_history: OneOrMany::one(Message::user("What is in run.sh?")),
+            documents: Vec::new(),
+            tools: vec![ToolDefinition { name: "read_file".to_string(), description: "Read a file".to_string(), parameters: json!({}) }],
+            temperature: None,
+            max_tokens: None,
+            additional_params: None,
+        }
+    }
+
+    fn tool_calls(choice: &[AssistantContent]) -> Vec<(String, serde_json::Value)> {
+        choice.iter()
+            .filter_map(|content| match content {
+                AssistantContent::ToolCall(call) => Some((call.function.name.clone(), call.function.arguments.clone())),
+                AssistantContent::Text(_) => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn prompt_mode_moves_tools_into_the_preamble_and_parses_calls() {
+        let (model, mock) = prompt_model("Reading.\n<tool_call>{\"name\": \"read_file\", \"arguments\": {\"path\": \"run.sh\"}}</tool_call>");
+        let response = model.completion(request()).await.unwrap();
+
+        let choice: Vec<AssistantContent> = response.choice.into_iter().collect();
+        assert_eq!(choice[0], AssistantContent::text("Reading."));
+        assert_eq!(tool_calls(&choice), [("read_file".to_string(), json!({ "path": "run.sh" }))]);
+
+        let sent = &mock.requests()[0];
+        assert!(sent.tools.is_empty());
+        let preamble = sent.preamble.as_deref().unwrap();
+        assert!(preamble.starts_with("You are a test agent.") && preamble.contains("## read_file"), "{}", preamble);
+    }
+
+    #[tokio::test]
+    async fn prompt_mode_streams_prose_and_calls() {
+        let (model, _) = prompt_model("Reading now. <tool_call>{\"name\": \"read_file\", \"arguments\": {\"path\": \"run.sh\"}}</tool_call>");
+        let mut stream = model.stream(request()).await.unwrap();
+
+        let mut text = String::new();
+        let mut calls = Vec::new();
+        while let Some(content) = stream.next().await {
+            match content.unwrap() {
+                AssistantContent::Text(chunk) => text.push_str(&chunk.text),
+                AssistantContent::ToolCall(call) => calls.push((call.function.name, call.function.arguments)),
+            }
+        }
+        assert_eq!(text.trim(), "Reading now.");
+        assert_eq!(calls, [("read_file".to_string(), json!({ "path": "run.sh" }))]);
+    }
+}
@@ -0,0 +1,415 @@
+use futures::StreamExt;
+use rig::completion::{CompletionError, CompletionModel, CompletionRequest, CompletionResponse};
+use rig::message::AssistantContent;
+use rig::streaming::{RawStreamingChoice, StreamingCompletionResponse};
+use serde::Deserialize;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+
+/// Token counts for one completion request
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+    /// True if any of the counts was estimated locally
+    pub estimated: bool,
+}
+
+/// Token counts as reported by a provider; any of them may be missing
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReportedUsage {
+    pub input: Option<u64>,
+    pub output: Option<u64>,
+    pub total: Option<u64>,
+}
+
+impl ReportedUsage {
+    pub fn new(input: u64, output: u64) -> Self {
+        ReportedUsage { input: Some(input), output: Some(output), total: Some(input + output) }
+    }
+
+    /// Fill in whatever the provider left out, deriving from the total where
+    /// possible and falling back to the local estimates, which are only
+    /// computed when needed
+    fn resolve(self, estimate_input: impl FnOnce() -> u64, estimate_output: impl FnOnce() -> u64) -> TokenUsage {
+        let reported_input = self.input
+            .or_else(|| self.total.zip(self.output).map(|(total, output)| total.saturating_sub(output)));
+        let input = reported_input.unwrap_or_else(estimate_input);
+        let output = self.output
+            .or_else(|| self.total.map(|total| total.saturating_sub(input)));
+        TokenUsage {
+            input,
+            output: output.unwrap_or_else(estimate_output),
+            estimated: reported_input.is_none() || output.is_none(),
+        }
+    }
+}
+
+/// Approximate number of tokens in `text` (cl100k_base encoding)
+pub fn estimate_tokens(text: &str) -> u64 {
+    if text.is_empty() {
+        return 0;
+    }
+    tiktoken_rs::cl100k_base_singleton().encode_ordinary(text).len() as u64
+}
+
+/// Approximate prompt size of a request: preamble, documents, history and tool definitions
+fn estimate_request_tokens(request: &CompletionRequest) -> u64 {
+    let mut text = request.preamble.clone().unwrap_or_default();
+    for document in &request.documents {
+        text.push_str(&document.text);
+    }
+    for message in request.chat_history.iter() {
+        text.push_str(&serde_json::to_string(message).unwrap_or_default());
+    }
+    for tool in &request.tools {
+        text.push_str(&serde_json::to_string(tool).unwrap_or_default());
+    }
+    estimate_tokens(&text)
+}
+
+/// Approximate size of the model's answer, text and tool calls alike
+fn estimate_choice_tokens<'a>(choice: impl IntoIterator<Item = &'a AssistantContent>) -> u64 {
+    let text = choice.into_iter().map(content_text).collect::<String>();
+    estimate_tokens(&text)
+}
+
+fn content_text(content: &AssistantContent) -> String {
+    match content {
+        AssistantContent::Text(text) => text.text.clone(),
+        AssistantContent::ToolCall(tool_call) => {
+            format!("{}{}", tool_call.function.name, tool_call.function.arguments)
+        }
+    }
+}
+
+/// Price of a model in US dollars per million tokens
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.input as f64 * self.input_per_million + usage.output as f64 * self.output_per_million) / 1_000_000.0
+    }
+}
+
+/// Limits for a whole session; no further requests are sent once one is reached
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Budget {
+    /// Input plus output tokens
+    pub max_tokens: Option<u64>,
+    /// Cost in US dollars, computed from `[prices]`
+    pub max_cost: Option<f64>,
+}
+
+#[derive(Error, Debug, Clone)]
+pub enum BudgetExceeded {
+    #[error("Token budget exceeded: {used} of {limit} tokens used")]
+    Tokens { used: u64, limit: u64 },
+    #[error("Cost budget exceeded: ${used:.4} of ${limit:.4} spent")]
+    Cost { used: f64, limit: f64 },
+}
+
+/// Accumulated usage over a number of requests
+#[derive(Debug, Clone, Default)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub input: u64,
+    pub output: u64,
+    /// Some of the counts were estimated locally
+    pub estimated: bool,
+    pub cost: f64,
+    /// Some requests went to models without a configured price
+    pub unpriced: bool,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &TokenUsage, cost: Option<f64>) {
+        self.requests += 1;
+        self.input += usage.input;
+        self.output += usage.output;
+        self.estimated |= usage.estimated;
+        match cost {
+            Some(cost) => self.cost += cost,
+            None => self.unpriced = true,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.input + self.output
+    }
+}
+
+impl fmt::Display for UsageTotals {
+    /// e.g. "1200 in / 350 out" or "~1200 in / ~350 out" when estimated
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = if self.estimated { "~" } else { "" };
+        write!(f, "{}{} in / {}{} out", marker, self.input, marker, self.output)
+    }
+}
+
+#[derive(Default)]
+struct UsageState {
+    turn: UsageTotals,
+    session: UsageTotals,
+    agents: BTreeMap<String, UsageTotals>,
+}
+
+/// Usage shared by all agents of a session, broken down per turn and per agent
+#[derive(Clone, Default)]
+pub struct UsageTracker {
+    prices: Arc<BTreeMap<String, ModelPrice>>,
+    budget: Budget,
+    state: Arc<Mutex<UsageState>>,
+    /// Role or task label put in front of agent labels
+    scope: Option<String>,
+}
+
+impl UsageTracker {
+    pub fn new(prices: BTreeMap<String, ModelPrice>, budget: Budget) -> Self {
+        UsageTracker { prices: Arc::new(prices), budget, state: Arc::default(), scope: None }
+    }
+
+    /// A tracker feeding the same totals that attributes usage to `scope: <agent>`
+    pub fn scoped(&self, scope: &str) -> UsageTracker {
+        UsageTracker { scope: Some(scope.to_string()), ..self.clone() }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, UsageState> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn record(&self, agent: &str, model: &str, usage: TokenUsage) {
+        let cost = self.prices.get(model).map(|price| price.cost(&usage));
+        let agent = match &self.scope {
+            Some(scope) => format!("{}: {}", scope, agent),
+            None => agent.to_string(),
+        };
+        let mut state = self.state();
+        state.turn.add(&usage, cost);
+        state.session.add(&usage, cost);
+        state.agents.entry(agent).or_default().add(&usage, cost);
+    }
+
+    /// Start counting a new turn
+    pub fn begin_turn(&self) {
+        self.state().turn = UsageTotals::default();
+    }
+
+    pub fn turn(&self) -> UsageTotals {
+        self.state().turn.clone()
+    }
+
+    pub fn session(&self) -> UsageTotals {
+        self.state().session.clone()
+    }
+
+    /// Session usage per agent label
+    pub fn agents(&self) -> Vec<(String, UsageTotals)> {
+        self.state().agents.iter().map(|(agent, totals)| (agent.clone(), totals.clone())).collect()
+    }
+
+    /// The budget limit the session has reached, if any
+    pub fn budget_exceeded(&self) -> Option<BudgetExceeded> {
+        let session = self.session();
+        if let Some(limit) = self.budget.max_tokens
+            && session.total() >= limit {
+            return Some(BudgetExceeded::Tokens { used: session.total(), limit });
+        }
+        if let Some(limit) = self.budget.max_cost
+            && session.cost >= limit {
+            return Some(BudgetExceeded::Cost { used: session.cost, limit });
+        }
+        None
+    }
+
+    fn check_budget(&self) -> Result<(), CompletionError> {
+        match self.budget_exceeded() {
+            Some(exceeded) => Err(CompletionError::RequestError(Box::new(exceeded))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Completion model wrapper that records token usage of every request and
+/// refuses new requests once the session budget is used up
+pub struct MeteredModel<M: CompletionModel> {
+    inner: M,
+    tracker: UsageTracker,
+    agent: String,
+    model: String,
+    reported: fn(&M::Response) -> ReportedUsage,
+    reported_streaming: fn(&M::StreamingResponse) -> ReportedUsage,
+}
+
+impl<M: CompletionModel> Clone for MeteredModel<M> {
+    fn clone(&self) -> Self {
+        MeteredModel {
+            inner: self.inner.clone(),
+            tracker: self.tracker.clone(),
+            agent: self.agent.clone(),
+            model: self.model.clone(),
+            reported: self.reported,
+            reported_streaming: self.reported_streaming,
+        }
+    }
+}
+
+impl<M: CompletionModel> MeteredModel<M> {
+    pub fn new(
+        inner: M,
+        tracker: UsageTracker,
+        agent: String,
+        model: String,
+        reported: fn(&M::Response) -> ReportedUsage,
+        reported_streaming: fn(&M::StreamingResponse) -> ReportedUsage,
+    ) -> Self {
+        MeteredModel { inner, tracker, agent, model, reported, reported_streaming }
+    }
+}
+
+tokio::task_local! {
+    /// Completions that succeeded inside the current `count_completions` call
+    static COMPLETIONS: Cell<usize>;
+}
+
+/// Run `future` and count the completion requests that succeed within it.
+/// Unlike the session totals, the count is not affected by requests other
+/// tasks run at the same time.
+pub async fn count_completions<F: Future>(future: F) -> (F::Output, usize) {
+    COMPLETIONS.scope(Cell::new(0), async move {
+        let output = future.await;
+        (output, COMPLETIONS.with(Cell::get))
+    }).await
+}
+
+fn completion_succeeded() {
+    let _ = COMPLETIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+impl<M: CompletionModel + 'static> CompletionModel for MeteredModel<M> {
+    type Response = M::Response;
+    type StreamingResponse = M::StreamingResponse;
+
+    async fn completion(&self, request: CompletionRequest) -> Result<CompletionResponse<Self::Response>, CompletionError> {
+        self.tracker.check_budget()?;
+        // Kept for the estimate in case the provider reports no usage
+        let sent = request.clone();
+        let response = self.inner.completion(request).await?;
+        completion_succeeded();
+
+        let usage = (self.reported)(&response.raw_response)
+            .resolve(|| estimate_request_tokens(&sent), || estimate_choice_tokens(response.choice.iter()));
+        self.tracker.record(&self.agent, &self.model, usage);
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<StreamingCompletionResponse<Self::StreamingResponse>, CompletionError> {
+        self.tracker.check_budget()?;
+        let sent = Arc::new(request.clone());
+        let inner = self.inner.stream(request).await?;
+        completion_succeeded();
+
+        // Pass the chunks through and record usage once the stream is done
+        let (tracker, agent, model, reported) = (self.tracker.clone(), self.agent.clone(), self.model.clone(), self.reported_streaming);
+        let stream = futures::stream::unfold(Some((inner, String::new())), move |state| {
+            let (tracker, agent, model, sent) = (tracker.clone(), agent.clone(), model.clone(), sent.clone());
+            async move {
+                let (mut inner, mut output) = state?;
+                match inner.next().await {
+                    Some(Ok(content)) => {
+                        output.push_str(&content_text(&content));
+                        let choice = match content {
+                            AssistantContent::Text(text) => RawStreamingChoice::Message(text.text),
+                            AssistantContent::ToolCall(tool_call) => RawStreamingChoice::ToolCall {
+                                id: tool_call.id,
+                                name: tool_call.function.name,
+                                arguments: tool_call.function.arguments,
+                            },
+                        };
+                        Some((Ok(choice), Some((inner, output))))
+                    }
+                    Some(Err(e)) => Some((Err(e), Some((inner, output)))),
+                    None => {
+                        let reported_usage = inner.response.as_ref().map(reported).unwrap_or_default();
+                        let usage = reported_usage.resolve(|| estimate_request_tokens(&sent), || estimate_tokens(&output));
+                        tracker.record(&agent, &model, usage);
+                        inner.response.take().map(|response| (Ok(RawStreamingChoice::FinalResponse(response)), None))
+                    }
+                }
+            }
+        });
+
+        Ok(StreamingCompletionResponse::stream(Box::pin(stream)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MockCompletionModel, MockScript, ScriptedResponse};
+    use rig::OneOrMany;
+    use rig::completion::Message;
+
+    fn metered(responses: usize) -> MeteredModel<MockCompletionModel> {
+        let responses = (0..responses)
+            .map(|_| ScriptedResponse { text: "ok".to_string(), tool_calls: Vec::new() })
+            .collect();
+        let mock = MockCompletionModel::new(Some(MockScript { responses }));
+        MeteredModel::new(mock, UsageTracker::default(), "test".to_string(), "scripted".to_string(),
+            |_| ReportedUsage::default(), |_| ReportedUsage::default())
+    }
+
+    fn request() -> CompletionRequest {
+        CompletionRequest {
+            preamble: None,
+            chat_history: OneOrMany::one(Message::user("Hello")),
+            documents: Vec::new(),
+            tools: Vec::new(),
+            temperature: None,
+            max_tokens: None,
+            additional_params: None,
+        }
+    }
+
+    #[test]
+    fn estimates_only_what_the_provider_left_out() {
+        let unused = || panic!("estimated although the provider reported usage");
+        let usage = ReportedUsage::new(120, 30).resolve(unused, unused);
+        assert_eq!((usage.input, usage.output, usage.estimated), (120, 30, false));
+
+        let usage = ReportedUsage { total: Some(150), output: Some(30), ..Default::default() }.resolve(unused, unused);
+        assert_eq!((usage.input, usage.output, usage.estimated), (120, 30, false));
+
+        let usage = ReportedUsage { total: Some(150), ..Default::default() }.resolve(|| 100, unused);
+        assert_eq!((usage.input, usage.output, usage.estimated), (100, 50, true));
+
+        let usage = ReportedUsage::default().resolve(|| 100, || 20);
+        assert_eq!((usage.input, usage.output, usage.estimated), (100, 20, true));
+    }
+
+    #[tokio::test]
+    async fn counts_only_completions_inside_the_call() {
+        let model = metered(2);
+        model.completion(request()).await.unwrap();
+
+        let (result, completions) = count_completions(model.completion(request())).await;
+        assert!(result.is_ok());
+        assert_eq!(completions, 1);
+        assert_eq!(model.tracker.session().requests, 2);
+
+        // The script is exhausted, so this request fails
+        let (result, completions) = count_completions(model.completion(request())).await;
+        assert!(result.is_err());
+        assert_eq!(completions, 0);
+    }
+}
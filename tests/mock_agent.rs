@@ -0,0 +1,195 @@
+//! Offline agent turns against the scripted mock model
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use llmo::agent_factory::{build_agent, AgentEvent, ChatAgent, ModelProvider};
+use llmo::config::Config;
+use llmo::mock::{MockCompletionModel, MockScript, ScriptedResponse, ScriptedToolCall};
+use llmo::usage::UsageTracker;
+use rig::completion::Message;
+use rig::message::{AssistantContent, ToolResultContent, UserContent};
+use serde_json::json;
+
+/// Hands out clones of one mock model, so the test can see the requests it was sent
+struct ScriptedProvider(MockCompletionModel);
+
+impl ModelProvider for ScriptedProvider {
+    type Model = MockCompletionModel;
+
+    const NAME: &'static str = "mock";
+
+    fn model_name(&self) -> String {
+        "scripted".to_string()
+    }
+
+    fn model(&self) -> Result<Self::Model, anyhow::Error> {
+        Ok(self.0.clone())
+    }
+}
+
+/// A fresh workspace under the system temp directory
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("llmo-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn config(workspace: &Path) -> Config {
+    let path = workspace.join("llmo.toml");
+    std::fs::write(&path, format!("provider = \"mock\"\nworkspace = {:?}\n", workspace.display().to_string())).unwrap();
+    Config::load(Some(&path), None).unwrap()
+}
+
+fn tool_call(name: &str, arguments: serde_json::Value) -> ScriptedToolCall {
+    ScriptedToolCall { name: name.to_string(), arguments }
+}
+
+/// Text of every tool result in `message`
+fn tool_results(message: &Message) -> Vec<String> {
+    let Message::User { content } = message else {
+        return Vec::new();
+    };
+    content.iter()
+        .filter_map(|content| match content {
+            UserContent::ToolResult(result) => Some(result),
+            _ => None,
+        })
+        .flat_map(|result| result.content.iter())
+        .filter_map(|content| match content {
+            ToolResultContent::Text(text) => Some(text.text.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Names of the tools `message` calls
+fn tool_calls(message: &Message) -> Vec<String> {
+    let Message::Assistant { content } = message else {
+        return Vec::new();
+    };
+    content.iter()
+        .filter_map(|content| match content {
+            AssistantContent::ToolCall(call) => Some(call.function.name.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn scripted_turn_reads_a_file_and_runs_a_job() {
+    let dir = workspace("mock-turn");
+    let notes = dir.join("notes.txt");
+    std::fs::write(&notes, "learning rate: 0.01\n").unwrap();
+    let script = dir.join("run.sh");
+    std::fs::write(&script, "echo job finished\n").unwrap();
+    let log = dir.join("job.log");
+
+    let model = MockCompletionModel::new(Some(MockScript {
+        responses: vec![
+            ScriptedResponse {
+                text: "Let me read the notes.".to_string(),
+                tool_calls: vec![tool_call("read_file", json!({ "path": notes, "line_numbers": false }))],
+            },
+            ScriptedResponse {
+                text: String::new(),
+                tool_calls: vec![tool_call("execute_job", json!({ "script_path": script, "output_file": log }))],
+            },
+            ScriptedResponse { text: "The job ran with a learning rate of 0.01.".to_string(), tool_calls: Vec::new() },
+        ],
+    }));
+    let provider = ScriptedProvider(model.clone());
+    let config = config(&dir);
+    let usage = UsageTracker::default();
+    let agent = build_agent(&provider, "You are a test agent.", &config, &usage, vec![], vec![]).await.unwrap();
+
+    let events = Mutex::new(Vec::new());
+    let on_event = |event: AgentEvent<'_>| {
+        let event = match event {
+            AgentEvent::ToolResult { name, output } => format!("result {}: {}", name, output),
+            AgentEvent::ToolError { name, error } => format!("error {}: {}", name, error),
+            AgentEvent::ToolCall { name, .. } => format!("call {}", name),
+            _ => return,
+        };
+        events.lock().unwrap().push(event);
+    };
+    let answer = agent.stream_chat("What does the job print?", Vec::new(), 5, &on_event).await.unwrap();
+    assert_eq!(answer, "The job ran with a learning rate of 0.01.");
+
+    let events = events.into_inner().unwrap();
+    assert_eq!(events.len(), 4, "{:#?}", events);
+    assert_eq!(events[0], "call read_file");
+    assert_eq!(events[1], format!("result read_file: {}", json!("learning rate: 0.01\n")));
+    assert_eq!(events[2], "call execute_job");
+    assert!(events[3].starts_with("result execute_job: "), "{}", events[3]);
+    assert!(events[3].contains("[STDOUT] job finished"), "{}", events[3]);
+    assert!(std::fs::read_to_string(&log).unwrap().contains("[STDOUT] job finished"));
+
+    // The last request carries the whole turn: prompt, both tool calls and their results
+    let requests = model.requests();
+    assert_eq!(requests.len(), 3);
+    let history = requests[2].chat_history.iter().collect::<Vec<_>>();
+    assert_eq!(history.len(), 5, "{:#?}", history);
+    assert!(matches!(history[0], Message::User { .. }));
+    assert_eq!(tool_calls(history[1]), ["read_file"]);
+    assert_eq!(tool_results(history[2]), [json!("learning rate: 0.01\n").to_string()]);
+    assert_eq!(tool_calls(history[3]), ["execute_job"]);
+    let job_results = tool_results(history[4]);
+    assert_eq!(job_results.len(), 1);
+    assert!(job_results[0].contains("[STDOUT] job finished"), "{}", job_results[0]);
+
+    assert_eq!(usage.session().requests, 3);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn exhausted_script_fails_the_turn() {
+    let dir = workspace("mock-exhausted");
+    let model = MockCompletionModel::new(Some(MockScript {
+        responses: vec![ScriptedResponse {
+            text: String::new(),
+            tool_calls: vec![tool_call("list_files", json!({ "path": dir }))],
+        }],
+    }));
+    let provider = ScriptedProvider(model.clone());
+    let config = config(&dir);
+    let agent = build_agent(&provider, "You are a test agent.", &config, &UsageTracker::default(), vec![], vec![]).await.unwrap();
+
+    let error = agent.chat("List the workspace.", Vec::new()).await.unwrap_err();
+    assert!(error.to_string().contains("Mock script exhausted"), "{}", error);
+    assert_eq!(model.requests().len(), 2);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Needs a key from a variable that is never set
+struct KeyedProvider;
+
+impl ModelProvider for KeyedProvider {
+    type Model = MockCompletionModel;
+
+    const NAME: &'static str = "keyed";
+    const API_KEY_VAR: Option<&'static str> = Some("LLMO_TEST_UNSET_API_KEY");
+
+    fn model_name(&self) -> String {
+        "keyed".to_string()
+    }
+
+    fn model(&self) -> Result<Self::Model, anyhow::Error> {
+        self.api_key(None)?;
+        Ok(MockCompletionModel::new(None))
+    }
+}
+
+#[tokio::test]
+async fn missing_api_key_fails_the_build() {
+    let dir = workspace("mock-no-key");
+    let config = config(&dir);
+    let error = build_agent(&KeyedProvider, "You are a test agent.", &config, &UsageTracker::default(), vec![], vec![])
+        .await
+        .err()
+        .expect("agent built without an API key");
+    assert!(error.to_string().contains("LLMO_TEST_UNSET_API_KEY"), "{}", error);
+    assert_eq!(KeyedProvider.api_key(Some(&"configured".to_string())).unwrap(), "configured");
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,146 @@
+//! The local provider end to end against the OpenAI-compatible stub server
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use llmo::agent_factory::{build_agent, AgentEvent, ChatAgent};
+use llmo::config::{Config, ProviderConfig};
+use llmo::local::LocalProvider;
+use llmo::stub_server::{load_fixtures, Fixture, FixtureToolCall, StubServer};
+use llmo::tool_protocol::ToolCalling;
+use llmo::usage::UsageTracker;
+use serde_json::json;
+
+/// A fresh workspace under the system temp directory
+fn workspace(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("llmo-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Config for `workspace` that gives up after a second attempt
+fn config(workspace: &Path) -> Config {
+    let path = workspace.join("llmo.toml");
+    let text = format!(
+        "provider = \"local\"\nworkspace = {:?}\n\n[retry]\nmax_attempts = 2\ninitial_backoff_ms = 10\n",
+        workspace.display().to_string(),
+    );
+    std::fs::write(&path, text).unwrap();
+    Config::load(Some(&path), None).unwrap()
+}
+
+/// The fixtures shipped for manual runs of `llmo-stub`
+fn shipped_fixtures() -> Vec<Fixture> {
+    load_fixtures(&Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/stub")).unwrap()
+}
+
+async fn serve(fixtures: Vec<Fixture>) -> StubServer {
+    StubServer::spawn("127.0.0.1:0".parse().unwrap(), fixtures).await.unwrap()
+}
+
+fn provider(server: &StubServer) -> LocalProvider {
+    LocalProvider {
+        settings: ProviderConfig {
+            model: Some("stub".to_string()),
+            api_base: Some(server.base_url()),
+            api_key: Some("stub".to_string()),
+            tool_calling: Some(ToolCalling::Native),
+            ..ProviderConfig::default()
+        },
+    }
+}
+
+#[tokio::test]
+async fn plain_reply() {
+    let dir = workspace("stub-plain");
+    let server = serve(shipped_fixtures()).await;
+    let usage = UsageTracker::default();
+    let agent = build_agent(&provider(&server), "You are a test agent.", &config(&dir), &usage, vec![], vec![]).await.unwrap();
+
+    let answer = agent.chat("Say hello", Vec::new()).await.unwrap();
+    assert_eq!(answer, "Stub reply to: Say hello");
+    assert_eq!(server.request_count(), 1);
+    assert_eq!(usage.session().requests, 1);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn streamed_reply() {
+    let dir = workspace("stub-streamed");
+    let server = serve(shipped_fixtures()).await;
+    let agent = build_agent(&provider(&server), "You are a test agent.", &config(&dir), &UsageTracker::default(), vec![], vec![]).await.unwrap();
+
+    let chunks = Mutex::new(Vec::new());
+    let on_event = |event: AgentEvent<'_>| {
+        if let AgentEvent::Text(text) = event {
+            chunks.lock().unwrap().push(text.to_string());
+        }
+    };
+    let answer = agent.stream_chat("Say hello again", Vec::new(), 5, &on_event).await.unwrap();
+    assert_eq!(answer, "Stub reply to: Say hello again");
+
+    // One chunk per word
+    let chunks = chunks.into_inner().unwrap();
+    assert!(chunks.len() > 1, "{:?}", chunks);
+    assert_eq!(chunks.concat(), answer);
+    assert_eq!(server.request_count(), 1);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn tool_call() {
+    let dir = workspace("stub-tool");
+    let notes = dir.join("notes.txt");
+    std::fs::write(&notes, "batch size: 32\n").unwrap();
+    let server = serve(vec![
+        Fixture {
+            contains: Some("notes".to_string()),
+            role: Some("user".to_string()),
+            content: "Let me read them.".to_string(),
+            tool_calls: vec![FixtureToolCall {
+                name: "read_file".to_string(),
+                arguments: json!({ "path": notes, "line_numbers": false }),
+            }],
+            status: None,
+            error: None,
+        },
+        Fixture {
+            contains: Some("batch size".to_string()),
+            role: Some("tool".to_string()),
+            content: "The batch size is 32.".to_string(),
+            tool_calls: Vec::new(),
+            status: None,
+            error: None,
+        },
+    ]).await;
+    let agent = build_agent(&provider(&server), "You are a test agent.", &config(&dir), &UsageTracker::default(), vec![], vec![]).await.unwrap();
+
+    let results = Mutex::new(Vec::new());
+    let on_event = |event: AgentEvent<'_>| match event {
+        AgentEvent::ToolResult { name, output } => results.lock().unwrap().push(format!("{}: {}", name, output)),
+        AgentEvent::ToolError { name, error } => panic!("{} failed: {}", name, error),
+        _ => {}
+    };
+    let answer = agent.stream_chat("What do the notes say?", Vec::new(), 5, &on_event).await.unwrap();
+
+    assert_eq!(answer, "The batch size is 32.");
+    assert_eq!(results.into_inner().unwrap(), [format!("read_file: {}", json!("batch size: 32\n"))]);
+    assert_eq!(server.request_count(), 2);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn overloaded_server_is_retried_then_fails() {
+    let dir = workspace("stub-overloaded");
+    let server = serve(shipped_fixtures()).await;
+    let usage = UsageTracker::default();
+    let agent = build_agent(&provider(&server), "You are a test agent.", &config(&dir), &usage, vec![], vec![]).await.unwrap();
+
+    let error = agent.chat("Are you overloaded?", Vec::new()).await.unwrap_err();
+    assert!(error.to_string().contains("The server is overloaded"), "{}", error);
+    // 503 is retryable: both attempts reached the server
+    assert_eq!(server.request_count(), 2);
+    assert_eq!(usage.session().requests, 0);
+    let _ = std::fs::remove_dir_all(&dir);
+}